@@ -9,6 +9,12 @@ use std::fmt::{Display, Formatter, Result};
 pub enum QueryContextError {
     /// Error that occurs when a date string cannot be parsed into a valid date
     InvalidDate(String),
+    /// Error that occurs when a text pattern is not a valid regular expression
+    InvalidRegex(String, String),
+    /// Error that occurs when a filter value is not one of the supported values
+    InvalidFilter(String, &'static str),
+    /// Error that occurs when a filter expression cannot be parsed
+    InvalidExpression(String),
 }
 
 impl Display for QueryContextError {
@@ -18,6 +24,16 @@ impl Display for QueryContextError {
                 fmt,
                 "Invalid date provided: {date}! Must be in format YYYY-MM-DD."
             ),
+            QueryContextError::InvalidRegex(pattern, why) => {
+                write!(fmt, "Invalid pattern provided: {pattern}! {why}")
+            }
+            QueryContextError::InvalidFilter(value, supported) => write!(
+                fmt,
+                "Invalid filter provided: {value}! Must be one of <{supported}>."
+            ),
+            QueryContextError::InvalidExpression(why) => {
+                write!(fmt, "Invalid expression provided: {why}!")
+            }
         }
     }
 }
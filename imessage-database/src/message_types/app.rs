@@ -15,6 +15,7 @@ use crate::{
 
 /// This struct represents Apple's [`MSMessageTemplateLayout`](https://developer.apple.com/documentation/messages/msmessagetemplatelayout).
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AppMessage<'a> {
     /// An image used to represent the message in the transcript
     pub image: Option<&'a str>,
@@ -13,6 +13,7 @@ use crate::{
 /// This struct is not documented by Apple, but represents messages displayed as
 /// `com.apple.messages.URLBalloonProvider` but for App Store apps
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AppStoreMessage<'a> {
     /// The URL that ended up serving content, after all redirects
     pub url: Option<&'a str>,
@@ -17,6 +17,42 @@ pub fn from_payload(payload: &[u8]) -> Option<DigitalTouch> {
     Some(msg.TouchKind.enum_value_or_default())
 }
 
+// MARK: Serde
+/// The `serde` variant names of [`DigitalTouch`], which is generated from a protobuf definition and cannot derive them
+#[cfg(feature = "serde")]
+const VARIANTS: &[&str] = &["unknown", "tap", "heartbeat", "sketch", "kiss", "fireball"];
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DigitalTouch {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = match self {
+            DigitalTouch::Unknown => 0,
+            DigitalTouch::Tap => 1,
+            DigitalTouch::Heartbeat => 2,
+            DigitalTouch::Sketch => 3,
+            DigitalTouch::Kiss => 4,
+            DigitalTouch::Fireball => 5,
+        };
+        serializer.serialize_unit_variant("DigitalTouch", index, VARIANTS[index as usize])
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DigitalTouch {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        match name.as_str() {
+            "unknown" => Ok(DigitalTouch::Unknown),
+            "tap" => Ok(DigitalTouch::Tap),
+            "heartbeat" => Ok(DigitalTouch::Heartbeat),
+            "sketch" => Ok(DigitalTouch::Sketch),
+            "kiss" => Ok(DigitalTouch::Kiss),
+            "fireball" => Ok(DigitalTouch::Fireball),
+            other => Err(serde::de::Error::unknown_variant(other, VARIANTS)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::message_types::digital_touch::{DigitalTouch, from_payload};
@@ -109,3 +145,37 @@ mod tests {
         assert_eq!(expected, Some(DigitalTouch::Fireball));
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use crate::message_types::digital_touch::DigitalTouch;
+
+    #[test]
+    fn can_serialize_digital_touch() {
+        let actual = serde_json::to_string(&DigitalTouch::Fireball).unwrap();
+        assert_eq!(actual, "\"fireball\"");
+    }
+
+    #[test]
+    fn can_round_trip_digital_touch() {
+        for kind in [
+            DigitalTouch::Unknown,
+            DigitalTouch::Tap,
+            DigitalTouch::Heartbeat,
+            DigitalTouch::Sketch,
+            DigitalTouch::Kiss,
+            DigitalTouch::Fireball,
+        ] {
+            let serialized = serde_json::to_string(&kind).unwrap();
+            assert_eq!(
+                serde_json::from_str::<DigitalTouch>(&serialized).unwrap(),
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn cant_deserialize_unknown_digital_touch() {
+        assert!(serde_json::from_str::<DigitalTouch>("\"poke\"").is_err());
+    }
+}
@@ -21,6 +21,11 @@ use crate::{
 
 /// The type of edit performed to a message body part
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum EditStatus {
     /// The content of the message body part was altered
     Edited,
@@ -32,6 +37,7 @@ pub enum EditStatus {
 
 /// Represents a single edit event for a message part
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EditedEvent {
     /// The date the message part was edited
     pub date: i64,
@@ -61,6 +67,7 @@ impl EditedEvent {
 
 /// Tracks the edit status and history for a specific part of a message
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EditedMessagePart {
     /// The type of edit made to the given message part
     pub status: EditStatus,
@@ -107,6 +114,7 @@ impl Default for EditedMessagePart {
 ///
 /// Apple describes editing and unsending messages [here](https://support.apple.com/guide/iphone/unsend-and-edit-messages-iphe67195653/ios).
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EditedMessage {
     /// Contains data representing each part of an edited message
     pub parts: Vec<EditedMessagePart>,
@@ -6,6 +6,11 @@
 ///
 /// Read more [here](https://www.imore.com/how-to-use-bubble-and-screen-effects-imessage-iphone-ipad).
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum BubbleEffect {
     /// Creates a slam effect that makes the bubble appear to slam down onto the screen.
     Slam,
@@ -21,6 +26,11 @@ pub enum BubbleEffect {
 ///
 /// Read more [here](https://www.imore.com/how-to-use-bubble-and-screen-effects-imessage-iphone-ipad).
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum ScreenEffect {
     /// Creates a confetti effect that sprinkles confetti across the screen.
     Confetti,
@@ -63,6 +73,11 @@ pub enum ScreenEffect {
 /// - `com.apple.messages.effect.CKSparklesEffect`
 /// - `com.apple.messages.effect.CKSpotlightEffect`
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Expressive<'a> {
     /// Effects that use the entire screen
     Screen(ScreenEffect),
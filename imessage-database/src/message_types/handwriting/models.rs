@@ -17,6 +17,7 @@ use protobuf::Message;
 /// This message type is not documented by Apple, but represents messages displayed as
 /// `com.apple.Handwriting.HandwritingProvider`.
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HandwrittenMessage {
     /// Unique identifier for the handwritten message
     pub id: String,
@@ -32,6 +33,7 @@ pub struct HandwrittenMessage {
 
 /// Represents a point along a handwritten line.
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     /// X-coordinate of the point
     pub x: u16,
@@ -13,6 +13,7 @@ use crate::{
 /// This struct is not documented by Apple, but represents messages displayed as
 /// `com.apple.messages.URLBalloonProvider` but from the Music app
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MusicMessage<'a> {
     /// URL in Apple Music
     pub url: Option<&'a str>,
@@ -15,6 +15,11 @@ const STICKER_EFFECT_SUFFIX: [u8; 3] = [34, 47, 62];
 
 /// Represents the source that created a sticker attachment
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum StickerSource {
     /// A [Genmoji](https://support.apple.com/guide/iphone/create-genmoji-with-apple-intelligence-iph4e76f5667/ios)
     Genmoji,
@@ -54,6 +59,11 @@ impl StickerSource {
 
 /// Represents different types of [sticker effects](https://www.macrumors.com/how-to/add-effects-to-stickers-in-messages/) that can be applied to sticker iMessage balloons.
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum StickerEffect {
     /// Sticker sent with no effect
     Normal,
@@ -8,6 +8,11 @@
 ///
 /// Read more about text styles [here](https://www.apple.com/newsroom/2024/06/ios-18-makes-iphone-more-personal-capable-and-intelligent-than-ever/).
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum TextEffect {
     /// Default, unstyled text
     Default,
@@ -20,6 +25,7 @@ pub enum TextEffect {
     /// The embedded data contains the url.
     Link(String),
     /// A one-time code, i.e. from a 2FA message
+    #[cfg_attr(feature = "serde", serde(rename = "otp"))]
     OTP,
     /// Traditional formatting styles
     ///
@@ -39,6 +45,11 @@ pub enum TextEffect {
 ///
 /// Read more about unit conversions [here](https://www.macrumors.com/how-to/convert-currencies-temperatures-more-ios-16/).
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Unit {
     /// Currency conversion
     Currency,
@@ -58,6 +69,11 @@ pub enum Unit {
 ///
 /// Read more about text styles [here](https://www.apple.com/newsroom/2024/06/ios-18-makes-iphone-more-personal-capable-and-intelligent-than-ever/).
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Style {
     /// **Bold** styled text
     Bold,
@@ -75,6 +91,11 @@ pub enum Style {
 ///
 /// Read more about text styles [here](https://www.apple.com/newsroom/2024/06/ios-18-makes-iphone-more-personal-capable-and-intelligent-than-ever/).
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Animation {
     /// Denoted by an ID of `5`
     Big,
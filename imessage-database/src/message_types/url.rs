@@ -21,6 +21,7 @@ use crate::{
 /// This struct is not documented by Apple, but represents messages created by
 /// `com.apple.messages.URLBalloonProvider`.
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct URLMessage<'a> {
     /// The webpage's `<og:title>` attribute
     pub title: Option<&'a str>,
@@ -44,6 +44,11 @@ use crate::{
 ///
 /// In this example, a Like on `p:2/` is a like on the third image.
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Tapback<'a> {
     /// Heart
     Loved,
@@ -80,10 +85,16 @@ impl Display for Tapback<'_> {
 /// Messages sent via an app's iMessage integration will send in a special balloon instead of a normal
 /// text balloon. This represents the different variants of message balloon.
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum CustomBalloon<'a> {
     /// Generic third party [applications](crate::message_types::app)
     Application(&'a str),
     /// [URL](crate::message_types::url) previews
+    #[cfg_attr(feature = "serde", serde(rename = "url"))]
     URL,
     /// Handwritten animated messages
     Handwriting,
@@ -106,6 +117,11 @@ pub enum CustomBalloon<'a> {
 /// Apple sometimes overloads `com.apple.messages.URLBalloonProvider` with
 /// other types of messages; this enum represents those variants.
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum URLOverride<'a> {
     /// [`URL`](crate::message_types::url) previews
     Normal(URLMessage<'a>),
@@ -124,6 +140,11 @@ pub enum URLOverride<'a> {
 /// Announcements are messages sent to a thread for actions that are not balloons, i.e.
 /// updating the name of the group or changing the group photo
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Announcement<'a> {
     /// All parts of the message were unsent
     FullyUnsent,
@@ -139,6 +160,11 @@ pub enum Announcement<'a> {
 ///
 /// Tapbacks can either be added or removed; this enum represents those states
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum TapbackAction {
     /// Tapback was added to the message
     Added,
@@ -151,6 +177,11 @@ pub enum TapbackAction {
 /// Messages can exist as one of many different variants, this encapsulates
 /// all of the possibilities.
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Variant<'a> {
     /// An iMessage with a standard text body that may include attachments
     Normal,
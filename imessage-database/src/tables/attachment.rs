@@ -41,6 +41,11 @@ const COLS: &str = "a.rowid, a.filename, a.uti, a.mime_type, a.transfer_name, a.
 ///
 /// The interior `str` contains the subtype, i.e. `x-m4a` for `audio/x-m4a`
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum MediaType<'a> {
     /// Image MIME type, such as `"image/png"` or `"image/jpeg"`
     Image(&'a str),
@@ -84,6 +89,7 @@ impl MediaType<'_> {
 
 /// Represents a single row in the `attachment` table.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Attachment {
     /// The unique identifier for the attachment in the database
     pub rowid: i32,
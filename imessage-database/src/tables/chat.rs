@@ -20,6 +20,7 @@ use crate::{
 /// Chat properties are stored as a `plist` in the database
 /// This represents the metadata for a chatroom
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Properties {
     /// Whether the chat has read receipts enabled
     read_receipts_enabled: bool,
@@ -47,6 +48,7 @@ impl Properties {
 // MARK: Chat Struct
 /// Represents a single row in the `chat` table.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chat {
     /// The unique identifier for the chat in the database
     pub rowid: i32,
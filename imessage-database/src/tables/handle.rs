@@ -8,12 +8,16 @@ use std::collections::{BTreeSet, HashMap};
 use crate::{
     error::table::TableError,
     tables::table::{Cacheable, Deduplicate, Diagnostic, HANDLE, ME, Table},
-    util::output::{done_processing, processing},
+    util::{
+        normalize::{Region, normalize_handle},
+        output::{done_processing, processing},
+    },
 };
 
 // MARK: Handle
 /// Represents a single row in the `handle` table.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Handle {
     /// The unique identifier for the handle in the database
     pub rowid: i32,
@@ -193,6 +197,78 @@ impl Diagnostic for Handle {
 
 // MARK: Impl
 impl Handle {
+    /// Normalize the cached handles so copies of the same phone number or email address map to the same string
+    ///
+    /// Handles for the same person can be saved in different formats depending on the service that created them,
+    /// i.e. `+15551234567` for iMessage and `(555) 123-4567` for SMS. Each ID is normalized with
+    /// [`normalize_handle`], and any handles that share a normalized ID are merged, so passing the result to
+    /// [`Handle::dedupe`] combines them.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::dirs::default_db_path;
+    /// use imessage_database::util::normalize::Region;
+    /// use imessage_database::tables::table::{Cacheable, Deduplicate, get_connection};
+    /// use imessage_database::tables::handle::Handle;
+    ///
+    /// let db_path = default_db_path();
+    /// let conn = get_connection(&db_path).unwrap();
+    /// let handles = Handle::cache(&conn).unwrap();
+    /// let deduped_handles = Handle::dedupe(&Handle::normalize(&handles, &Region::default()));
+    /// ```
+    #[must_use]
+    pub fn normalize(handles: &HashMap<i32, String>, region: &Region) -> HashMap<i32, String> {
+        // Iterate over the handles in a deterministic order
+        let mut sorted_handles: Vec<(&i32, &String)> = handles.iter().collect();
+        sorted_handles.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        // Each handle starts in its own group; groups that share a normalized ID are merged
+        let mut groups: HashMap<i32, i32> = HashMap::new();
+        let mut id_to_handle: HashMap<String, i32> = HashMap::new();
+        let mut normalized: Vec<(i32, BTreeSet<String>)> = Vec::with_capacity(handles.len());
+
+        for (rowid, ids) in sorted_handles {
+            groups.insert(*rowid, *rowid);
+            let ids: BTreeSet<String> = ids
+                .split_whitespace()
+                .map(|id| normalize_handle(id, region))
+                .collect();
+            for id in &ids {
+                match id_to_handle.get(id) {
+                    Some(other) => {
+                        let (a, b) = (find_group(&groups, *rowid), find_group(&groups, *other));
+                        groups.insert(a.max(b), a.min(b));
+                    }
+                    None => {
+                        id_to_handle.insert(id.to_owned(), *rowid);
+                    }
+                }
+            }
+            normalized.push((*rowid, ids));
+        }
+
+        // Collect every normalized ID in each group
+        let mut group_ids: HashMap<i32, BTreeSet<String>> = HashMap::new();
+        for (rowid, ids) in &normalized {
+            group_ids
+                .entry(find_group(&groups, *rowid))
+                .or_default()
+                .extend(ids.iter().cloned());
+        }
+
+        normalized
+            .into_iter()
+            .map(|(rowid, _)| {
+                let ids = group_ids
+                    .get(&find_group(&groups, rowid))
+                    .map(|ids| ids.iter().cloned().collect::<Vec<String>>().join(" "))
+                    .unwrap_or_default();
+                (rowid, ids)
+            })
+            .collect()
+    }
+
     /// The handles table does not have a lot of information and can have many duplicate values.
     ///
     /// This method generates a hashmap of each separate item in this table to a combined string
@@ -253,12 +329,77 @@ impl Handle {
     }
 }
 
+/// Follow a handle's group to the handle that represents it
+fn find_group(groups: &HashMap<i32, i32>, rowid: i32) -> i32 {
+    let mut current = rowid;
+    while let Some(parent) = groups.get(&current) {
+        if *parent == current {
+            break;
+        }
+        current = *parent;
+    }
+    current
+}
+
 // MARK: Tests
 #[cfg(test)]
 mod tests {
-    use crate::tables::{handle::Handle, table::Deduplicate};
+    use crate::{
+        tables::{handle::Handle, table::Deduplicate},
+        util::normalize::Region,
+    };
     use std::collections::{HashMap, HashSet};
 
+    #[test]
+    fn can_normalize_formats() {
+        let mut input: HashMap<i32, String> = HashMap::new();
+        input.insert(1, String::from("+15551234567"));
+        input.insert(2, String::from("(555)123-4567"));
+        input.insert(3, String::from("Steve@Apple.com"));
+
+        let output = Handle::normalize(&input, &Region::default());
+        assert_eq!(output.get(&1), Some(&String::from("+15551234567")));
+        assert_eq!(output.get(&2), Some(&String::from("+15551234567")));
+        assert_eq!(output.get(&3), Some(&String::from("steve@apple.com")));
+    }
+
+    #[test]
+    fn can_normalize_merge_shared_ids() {
+        let mut input: HashMap<i32, String> = HashMap::new();
+        // Deduplicated by `person_centric_id`
+        input.insert(1, String::from("+15551234567 steve@apple.com"));
+        input.insert(2, String::from("+15551234567 steve@apple.com"));
+        // The same number, saved by a different service without a `person_centric_id`
+        input.insert(3, String::from("5551234567"));
+        input.insert(4, String::from("tim@apple.com"));
+
+        let output = Handle::normalize(&input, &Region::default());
+        let expected = String::from("+15551234567 steve@apple.com");
+        assert_eq!(output.get(&1), Some(&expected));
+        assert_eq!(output.get(&2), Some(&expected));
+        assert_eq!(output.get(&3), Some(&expected));
+        assert_eq!(output.get(&4), Some(&String::from("tim@apple.com")));
+
+        let deduped = Handle::dedupe(&output);
+        assert_eq!(deduped.get(&1), deduped.get(&3));
+        assert_ne!(deduped.get(&1), deduped.get(&4));
+    }
+
+    #[test]
+    fn can_normalize_merge_transitively() {
+        let mut input: HashMap<i32, String> = HashMap::new();
+        input.insert(1, String::from("steve@apple.com"));
+        input.insert(2, String::from("+15551234567"));
+        // Links the first two handles
+        input.insert(3, String::from("Steve@Apple.com 5551234567"));
+
+        let output = Handle::normalize(&input, &Region::default());
+        let expected = String::from("+15551234567 steve@apple.com");
+        assert_eq!(output.get(&1), Some(&expected));
+        assert_eq!(output.get(&2), Some(&expected));
+        assert_eq!(output.get(&3), Some(&expected));
+    }
+
     #[test]
     fn test_can_dedupe() {
         let mut input: HashMap<i32, String> = HashMap::new();
@@ -115,7 +115,11 @@
  ```
 */
 
-use std::{collections::HashMap, fmt::Write, io::Read};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+    io::Read,
+};
 
 use chrono::{DateTime, offset::Local};
 use crabstep::TypedStreamDeserializer;
@@ -131,13 +135,14 @@ use crate::{
         variants::{Announcement, BalloonProvider, CustomBalloon, Tapback, TapbackAction, Variant},
     },
     tables::{
+        attachment::Attachment,
         messages::{
             body::{parse_body_legacy, parse_body_typedstream},
             models::{BubbleComponent, GroupAction, Service, TextAttributes},
             query_parts::{ios_13_older_query, ios_14_15_query, ios_16_newer_query},
         },
         table::{
-            ATTRIBUTED_BODY, CHAT_MESSAGE_JOIN, Cacheable, Diagnostic, MESSAGE,
+            ATTACHMENT, ATTRIBUTED_BODY, CHAT_MESSAGE_JOIN, Cacheable, Diagnostic, MESSAGE,
             MESSAGE_ATTACHMENT_JOIN, MESSAGE_PAYLOAD, MESSAGE_SUMMARY_INFO, RECENTLY_DELETED,
             Table,
         },
@@ -145,12 +150,16 @@ use crate::{
     util::{
         bundle_id::parse_balloon_bundle_id,
         dates::{get_local_time, readable_diff},
+        expression::{Expression, Term},
         output::{done_processing, processing},
-        query_context::QueryContext,
+        query_context::{MessageKind, MessageStatus, QueryContext},
         streamtyped,
     },
 };
 
+/// SQL condition for messages sent by the database owner
+const FROM_ME_FILTER: &str = "m.is_from_me = 1";
+
 // MARK: Columns
 /// The required columns, interpolated into the most recent schema due to performance considerations
 pub(crate) const COLS: &str = "rowid, guid, text, service, handle_id, destination_caller_id, subject, date, date_read, date_delivered, is_from_me, is_read, item_type, other_handle, share_status, share_direction, group_title, group_action_type, associated_message_guid, associated_message_type, balloon_bundle_id, expressive_send_style_id, thread_originator_guid, thread_originator_part, date_edited, associated_message_emoji";
@@ -159,6 +168,7 @@ pub(crate) const COLS: &str = "rowid, guid, text, service, handle_id, destinatio
 ///
 /// Additional information is available in the [parent](crate::tables::messages::message) module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(non_snake_case)]
 pub struct Message {
     /// The unique identifier for the message in the database
@@ -779,7 +789,10 @@ impl Message {
     }
 
     /// Get the index of the part of a message a reply is pointing to
-    fn get_reply_index(&self) -> usize {
+    ///
+    /// Defaults to `0` if the message is not a reply or the part cannot be parsed.
+    #[must_use]
+    pub fn get_reply_index(&self) -> usize {
         if let Some(parts) = &self.thread_originator_part {
             return match parts.split(':').next() {
                 Some(part) => str::parse::<usize>(part).unwrap_or(0),
@@ -834,12 +847,239 @@ impl Message {
             }
         }
 
+        // Sender filter, where messages from the database owner have no sender handle
+        let mut senders = Vec::with_capacity(2);
+        if context.from_me {
+            senders.push(String::from(FROM_ME_FILTER));
+        }
+        if let Some(sender_ids) = &context.selected_sender_ids {
+            senders.push(Self::sender_filter(sender_ids));
+        }
+        if !senders.is_empty() {
+            if !filters.is_empty() {
+                filters.push_str(" AND ");
+            }
+            let _ = write!(filters, " ({})", senders.join(" OR "));
+        }
+
+        // Service filter
+        if let Some(services) = &context.selected_services {
+            if !filters.is_empty() {
+                filters.push_str(" AND ");
+            }
+            let _ = write!(filters, " {}", Self::service_filter(services));
+        }
+
+        // Attachment filter
+        if context.has_attachments {
+            if !filters.is_empty() {
+                filters.push_str(" AND ");
+            }
+            let _ = write!(filters, " {}", Self::attachment_filter());
+        }
+
+        // Attachment MIME type filter
+        if let Some(mime_types) = &context.selected_mime_types {
+            if !filters.is_empty() {
+                filters.push_str(" AND ");
+            }
+            let _ = write!(filters, " {}", Self::mime_type_filter(mime_types));
+        }
+
+        // Filter expression, where terms that need the decoded message are checked by `matches_filters()`
+        if let Some(expression) = context
+            .expression
+            .as_ref()
+            .and_then(Self::expression_filter)
+        {
+            if !filters.is_empty() {
+                filters.push_str(" AND ");
+            }
+            let _ = write!(filters, " {expression}");
+        }
+
         if !filters.is_empty() {
             return format!("WHERE {filters}");
         }
         filters
     }
 
+    /// Generate the SQL condition for messages sent by any of the provided handles
+    fn sender_filter(sender_ids: &BTreeSet<i32>) -> String {
+        let ids = sender_ids
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("(m.is_from_me = 0 AND m.handle_id IN ({ids}))")
+    }
+
+    /// Generate the SQL condition for messages sent with any of the provided `service` column values
+    fn service_filter<'a>(services: impl IntoIterator<Item = &'a &'a str>) -> String {
+        let services = services
+            .into_iter()
+            .map(|service| format!("'{service}'"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("m.service IN ({services})")
+    }
+
+    /// Generate the SQL condition for messages that have attachments
+    fn attachment_filter() -> String {
+        format!("m.ROWID IN (SELECT message_id FROM {MESSAGE_ATTACHMENT_JOIN})")
+    }
+
+    /// Generate the SQL condition for messages with an attachment in any of the provided MIME type families
+    fn mime_type_filter<'a>(mime_types: impl IntoIterator<Item = &'a &'a str>) -> String {
+        let mime_types = mime_types
+            .into_iter()
+            .map(|mime_type| format!("at.mime_type LIKE '{mime_type}/%'"))
+            .collect::<Vec<String>>()
+            .join(" OR ");
+        format!(
+            "m.ROWID IN (SELECT aj.message_id FROM {MESSAGE_ATTACHMENT_JOIN} as aj JOIN {ATTACHMENT} as at ON aj.attachment_id = at.ROWID WHERE {mime_types})"
+        )
+    }
+
+    /// Compile the parts of an [`Expression`] that can be expressed in SQL
+    ///
+    /// Terms that need the decoded message compile to [`None`], which does not filter anything.
+    /// The result selects every message that might match, so [`Message::matches_filters()`] checks
+    /// the expression again if it [requires decoding](Expression::requires_decoding).
+    fn expression_filter(expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::And(left, right) => {
+                match (
+                    Self::expression_filter(left),
+                    Self::expression_filter(right),
+                ) {
+                    (Some(left), Some(right)) => Some(format!("({left} AND {right})")),
+                    (left, right) => left.or(right),
+                }
+            }
+            Expression::Or(left, right) => Some(format!(
+                "({} OR {})",
+                Self::expression_filter(left)?,
+                Self::expression_filter(right)?
+            )),
+            // Negating a condition that selects too many messages would select too few
+            Expression::Not(expression) if expression.requires_decoding() => None,
+            Expression::Not(expression) => {
+                Some(format!("NOT {}", Self::expression_filter(expression)?))
+            }
+            Expression::Term(term) => match term {
+                Term::FromMe => Some(format!("({FROM_ME_FILTER})")),
+                Term::FromHandles { handle_ids, .. } => Some(Self::sender_filter(handle_ids)),
+                Term::Service(services) => Some(format!("({})", Self::service_filter(*services))),
+                Term::OnOrAfter(date) => Some(format!("(m.date >= {date})")),
+                Term::Before(date) => Some(format!("(m.date < {date})")),
+                Term::HasAttachment => Some(format!("({})", Self::attachment_filter())),
+                Term::MimeType(mime_type) => {
+                    Some(format!("({})", Self::mime_type_filter([mime_type])))
+                }
+                Term::Text(_) | Term::Kind(_) | Term::Status(_) => None,
+            },
+        }
+    }
+
+    // MARK: Filters
+    /// `true` if the message matches the filters in a [`QueryContext`] that cannot be expressed in SQL, else `false`
+    ///
+    /// These filters depend on the decoded message, so [`Message::generate_text()`] must be called first.
+    /// The database is only queried if an [`Expression`] selects attachment MIME types.
+    #[must_use]
+    pub fn matches_filters(&self, db: &Connection, context: &QueryContext) -> bool {
+        if let Some(text) = &context.text
+            && !text.matches(self.text.as_deref().unwrap_or_default())
+        {
+            return false;
+        }
+        if let Some(kinds) = &context.selected_kinds
+            && !kinds.iter().any(|kind| self.is_kind(*kind))
+        {
+            return false;
+        }
+        if let Some(statuses) = &context.selected_statuses
+            && !statuses.iter().any(|status| self.has_status(*status))
+        {
+            return false;
+        }
+        if let Some(expression) = &context.expression
+            && expression.requires_decoding()
+            && !self.matches_expression(db, expression)
+        {
+            return false;
+        }
+        true
+    }
+
+    /// `true` if the message matches every term of an [`Expression`], else `false`
+    fn matches_expression(&self, db: &Connection, expression: &Expression) -> bool {
+        match expression {
+            Expression::And(left, right) => {
+                self.matches_expression(db, left) && self.matches_expression(db, right)
+            }
+            Expression::Or(left, right) => {
+                self.matches_expression(db, left) || self.matches_expression(db, right)
+            }
+            Expression::Not(expression) => !self.matches_expression(db, expression),
+            Expression::Term(term) => match term {
+                Term::FromMe => self.is_from_me,
+                Term::FromHandles { handle_ids, .. } => {
+                    !self.is_from_me
+                        && self
+                            .handle_id
+                            .is_some_and(|handle_id| handle_ids.contains(&handle_id))
+                }
+                Term::Service(services) => self
+                    .service
+                    .as_deref()
+                    .is_some_and(|service| services.contains(&service)),
+                Term::OnOrAfter(date) => self.date >= *date,
+                Term::Before(date) => self.date < *date,
+                Term::HasAttachment => self.has_attachments(),
+                Term::MimeType(mime_type) => Attachment::from_message(db, self)
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|attachment| {
+                        attachment
+                            .mime_type
+                            .as_deref()
+                            .and_then(|mime| mime.split_once('/'))
+                            .is_some_and(|(family, _)| family.eq_ignore_ascii_case(mime_type))
+                    }),
+                Term::Text(text) => text.matches(self.text.as_deref().unwrap_or_default()),
+                Term::Kind(kind) => self.is_kind(*kind),
+                Term::Status(status) => self.has_status(*status),
+            },
+        }
+    }
+
+    /// `true` if the message is the provided [`MessageKind`], else `false`
+    fn is_kind(&self, kind: MessageKind) -> bool {
+        match kind {
+            MessageKind::Edited => self.has_part_with_status(&EditStatus::Edited),
+            MessageKind::App => matches!(self.variant(), Variant::App(_)),
+            MessageKind::SharePlay => self.is_shareplay(),
+            MessageKind::Announcement => self.is_announcement(),
+        }
+    }
+
+    /// `true` if the message has the provided [`MessageStatus`], else `false`
+    fn has_status(&self, status: MessageStatus) -> bool {
+        match status {
+            MessageStatus::Unsent => self.has_part_with_status(&EditStatus::Unsent),
+            MessageStatus::Deleted => self.is_deleted(),
+        }
+    }
+
+    /// `true` if any message component has the provided [`EditStatus`], else `false`
+    fn has_part_with_status(&self, status: &EditStatus) -> bool {
+        self.edited_parts
+            .as_ref()
+            .is_some_and(|ep| ep.parts.iter().any(|part| &part.status == status))
+    }
+
     /// Get the number of messages in the database
     ///
     /// # Example
@@ -959,16 +1199,36 @@ impl Message {
 
     /// Build a `HashMap` of message component index to messages that reply to that component
     pub fn get_replies(&self, db: &Connection) -> Result<HashMap<usize, Vec<Self>>, TableError> {
+        self.get_filtered_replies(db, &QueryContext::default())
+    }
+
+    /// Build a `HashMap` of message component index to messages that reply to that component,
+    /// keeping only the replies that match the SQL filters in the [`QueryContext`].
+    ///
+    /// Filters that need the decoded message are not applied here, use [`matches_filters()`](Self::matches_filters) for those.
+    pub fn get_filtered_replies(
+        &self,
+        db: &Connection,
+        context: &QueryContext,
+    ) -> Result<HashMap<usize, Vec<Self>>, TableError> {
         let mut out_h: HashMap<usize, Vec<Self>> = HashMap::new();
 
         // No need to hit the DB if we know we don't have replies
         if self.has_replies() {
-            let filters = format!("WHERE m.thread_originator_guid = \"{}\"", self.guid);
+            let thread = format!("m.thread_originator_guid = \"{}\"", self.guid);
+            let filters = |include_recoverable| {
+                let filters = Self::generate_filter_statement(context, include_recoverable);
+                if filters.is_empty() {
+                    format!("WHERE {thread}")
+                } else {
+                    format!("{filters} AND {thread}")
+                }
+            };
 
             // No iOS 13 and prior used here because `thread_originator_guid` is not present in that schema
             let mut statement = db
-                .prepare(&ios_16_newer_query(Some(&filters)))
-                .or_else(|_| db.prepare(&ios_14_15_query(Some(&filters))))?;
+                .prepare(&ios_16_newer_query(Some(&filters(true))))
+                .or_else(|_| db.prepare(&ios_14_15_query(Some(&filters(false)))))?;
 
             let iter = statement.query_map([], |row| Ok(Message::from_row(row)))?;
 
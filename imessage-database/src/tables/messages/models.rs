@@ -19,6 +19,11 @@ use crate::{
 ///
 /// A single iMessage contains data that may be represented across multiple bubbles.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum BubbleComponent {
     /// A text message with associated formatting, generally representing ranges present in a `NSAttributedString`
     Text(Vec<TextAttributes>),
@@ -33,13 +38,21 @@ pub enum BubbleComponent {
 // MARK: Service
 /// Defines different types of [services](https://support.apple.com/en-us/104972) we can receive messages from.
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Service<'a> {
     /// An iMessage
     #[allow(non_camel_case_types)]
+    #[cfg_attr(feature = "serde", serde(rename = "imessage"))]
     iMessage,
     /// A message sent as SMS
+    #[cfg_attr(feature = "serde", serde(rename = "sms"))]
     SMS,
     /// A message sent as RCS
+    #[cfg_attr(feature = "serde", serde(rename = "rcs"))]
     RCS,
     /// A message sent via [satellite](https://support.apple.com/en-us/120930)
     Satellite,
@@ -101,6 +114,7 @@ impl Display for Service<'_> {
 /// ])];
 /// ```
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextAttributes {
     /// The start index of the affected range of message text
     pub start: usize,
@@ -125,6 +139,7 @@ impl TextAttributes {
 // MARK: AttachmentMeta
 /// Representation of attachment metadata used for rendering message body in a conversation feed.
 #[derive(Debug, PartialEq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AttachmentMeta {
     /// GUID of the attachment in the `attachment` table
     pub guid: Option<String>,
@@ -179,6 +194,11 @@ impl AttachmentMeta {
 // MARK: GroupAction
 /// Represents different types of group message actions that can occur in a chat system
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum GroupAction<'a> {
     /// A new participant has been added to the group
     ParticipantAdded(i32),
@@ -0,0 +1,196 @@
+#[cfg(test)]
+mod tests {
+    use std::env::current_dir;
+
+    use rusqlite::Connection;
+
+    use crate::{
+        message_types::edited::{EditStatus, EditedMessage, EditedMessagePart},
+        tables::{messages::Message, table::get_connection},
+        util::query_context::QueryContext,
+    };
+
+    fn db() -> Connection {
+        let db_path = current_dir()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("imessage-database/test_data/db/test.db");
+        get_connection(&db_path).unwrap()
+    }
+
+    fn edited_message(status: EditStatus) -> Message {
+        let mut m = Message::blank();
+        m.date_edited = 1;
+        m.edited_parts = Some(EditedMessage {
+            parts: vec![
+                EditedMessagePart {
+                    status: EditStatus::Original,
+                    edit_history: vec![],
+                },
+                EditedMessagePart {
+                    status,
+                    edit_history: vec![],
+                },
+            ],
+        });
+        m
+    }
+
+    #[test]
+    fn can_match_without_filters() {
+        let m = Message::blank();
+        assert!(m.matches_filters(&db(), &QueryContext::default()));
+    }
+
+    #[test]
+    fn can_match_text() {
+        let mut context = QueryContext::default();
+        context.set_text("invoice");
+
+        let mut m = Message::blank();
+        m.text = Some("Your INVOICE is ready".to_string());
+        assert!(m.matches_filters(&db(), &context));
+
+        m.text = Some("Your receipt is ready".to_string());
+        assert!(!m.matches_filters(&db(), &context));
+
+        m.text = None;
+        assert!(!m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_text_regex() {
+        let mut context = QueryContext::default();
+        context.set_text_regex(r"#\d{3}\b").unwrap();
+
+        let mut m = Message::blank();
+        m.text = Some("Order #123 shipped".to_string());
+        assert!(m.matches_filters(&db(), &context));
+
+        m.text = Some("Order #12 shipped".to_string());
+        assert!(!m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_kind_edited() {
+        let mut context = QueryContext::default();
+        context.set_selected_kinds("edited").unwrap();
+
+        assert!(edited_message(EditStatus::Edited).matches_filters(&db(), &context));
+        assert!(!edited_message(EditStatus::Unsent).matches_filters(&db(), &context));
+        assert!(!Message::blank().matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_kind_app() {
+        let mut context = QueryContext::default();
+        context.set_selected_kinds("app").unwrap();
+
+        let mut m = Message::blank();
+        m.associated_message_type = Some(0);
+        m.balloon_bundle_id = Some("com.apple.messages.URLBalloonProvider".to_string());
+        assert!(m.matches_filters(&db(), &context));
+
+        m.balloon_bundle_id = None;
+        assert!(!m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_kind_shareplay() {
+        let mut context = QueryContext::default();
+        context
+            .set_selected_kinds("shareplay,announcement")
+            .unwrap();
+
+        let mut m = Message::blank();
+        m.item_type = 6;
+        assert!(m.matches_filters(&db(), &context));
+
+        m.item_type = 0;
+        assert!(!m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_status() {
+        let mut context = QueryContext::default();
+        context.set_selected_statuses("unsent,deleted").unwrap();
+
+        assert!(edited_message(EditStatus::Unsent).matches_filters(&db(), &context));
+        assert!(!edited_message(EditStatus::Edited).matches_filters(&db(), &context));
+
+        let mut m = Message::blank();
+        m.deleted_from = Some(1);
+        assert!(m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_all_filters() {
+        let mut context = QueryContext::default();
+        context.set_text("hello");
+        context.set_selected_kinds("edited").unwrap();
+
+        let mut m = edited_message(EditStatus::Edited);
+        m.text = Some("Hello world".to_string());
+        assert!(m.matches_filters(&db(), &context));
+
+        m.text = Some("Goodbye world".to_string());
+        assert!(!m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_expression() {
+        let mut context = QueryContext::default();
+        context
+            .set_expression("(text:\"lunch\" OR kind:edited) AND NOT status:unsent")
+            .unwrap();
+
+        let mut m = Message::blank();
+        m.text = Some("Lunch tomorrow?".to_string());
+        assert!(m.matches_filters(&db(), &context));
+
+        let mut m = edited_message(EditStatus::Edited);
+        m.text = Some("Dinner tomorrow?".to_string());
+        assert!(m.matches_filters(&db(), &context));
+
+        let mut m = edited_message(EditStatus::Unsent);
+        m.text = Some("Lunch tomorrow?".to_string());
+        assert!(!m.matches_filters(&db(), &context));
+
+        let mut m = Message::blank();
+        m.text = Some("Dinner tomorrow?".to_string());
+        assert!(!m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_match_expression_sql_terms() {
+        let mut context = QueryContext::default();
+        context
+            .set_expression("text:lunch AND (from:me OR service:sms) AND date>=2020-01-01")
+            .unwrap();
+
+        let mut m = Message::blank();
+        m.text = Some("lunch".to_string());
+        m.is_from_me = true;
+        m.date = i64::MAX;
+        assert!(m.matches_filters(&db(), &context));
+
+        m.is_from_me = false;
+        assert!(!m.matches_filters(&db(), &context));
+
+        m.service = Some("SMS".to_string());
+        assert!(m.matches_filters(&db(), &context));
+
+        m.date = 0;
+        assert!(!m.matches_filters(&db(), &context));
+    }
+
+    #[test]
+    fn can_skip_expression_without_decoding_terms() {
+        let mut context = QueryContext::default();
+        context.set_expression("from:me").unwrap();
+
+        // Checked by the SQL query instead
+        assert!(Message::blank().matches_filters(&db(), &context));
+    }
+}
@@ -83,4 +83,25 @@ mod tests {
             m.clean_associated_guid()
         );
     }
+
+    #[test]
+    fn can_get_reply_index() {
+        let mut m = Message::blank();
+        m.thread_originator_guid = Some("A44CE9D7-AAAA-BBBB-CCCC-23C54E1A9B6A".to_string());
+        m.thread_originator_part = Some("2:0:12".to_string());
+        assert_eq!(m.get_reply_index(), 2);
+    }
+
+    #[test]
+    fn can_get_reply_index_invalid() {
+        let mut m = Message::blank();
+        m.thread_originator_part = Some("invalid".to_string());
+        assert_eq!(m.get_reply_index(), 0);
+    }
+
+    #[test]
+    fn can_get_reply_index_none() {
+        let m = Message::blank();
+        assert_eq!(m.get_reply_index(), 0);
+    }
 }
@@ -2,6 +2,8 @@ mod announcement;
 mod date_tests;
 mod edited_tests;
 mod expressive_tests;
+mod filter_tests;
 mod guid_tests;
 mod query_tests;
+mod serde_tests;
 mod variant;
@@ -95,6 +95,124 @@ mod exclude_recoverable_tests {
         assert_eq!(statement, "");
     }
 
+    #[test]
+    fn can_generate_filter_statement_from_me() {
+        let mut context = QueryContext::default();
+        context.set_from_me(true);
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(statement, "WHERE  (m.is_from_me = 1)");
+    }
+
+    #[test]
+    fn can_generate_filter_statement_senders() {
+        let mut context = QueryContext::default();
+        context.set_from_me(true);
+        context.set_selected_sender_ids(BTreeSet::from([1, 2]));
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(
+            statement,
+            "WHERE  (m.is_from_me = 1 OR (m.is_from_me = 0 AND m.handle_id IN (1, 2)))"
+        );
+    }
+
+    #[test]
+    fn can_generate_filter_statement_services() {
+        let mut context = QueryContext::default();
+        context.set_selected_services("sms,satellite").unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(statement, "WHERE  m.service IN ('SMS', 'iMessageLite')");
+    }
+
+    #[test]
+    fn can_generate_filter_statement_attachments() {
+        let mut context = QueryContext::default();
+        context.set_has_attachments(true);
+        context.set_selected_mime_types("image,video").unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(
+            statement,
+            "WHERE  m.ROWID IN (SELECT message_id FROM message_attachment_join) AND  m.ROWID IN (SELECT aj.message_id FROM message_attachment_join as aj JOIN attachment as at ON aj.attachment_id = at.ROWID WHERE at.mime_type LIKE 'image/%' OR at.mime_type LIKE 'video/%')"
+        );
+    }
+
+    #[test]
+    fn can_generate_filter_statement_start_services_attachments() {
+        let mut context = QueryContext::default();
+        context.set_start("2020-01-01").unwrap();
+        context.set_selected_services("sms").unwrap();
+        context.set_selected_mime_types("image").unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(
+            statement,
+            "WHERE  m.date >= 599558400000000000 AND  m.service IN ('SMS') AND  m.ROWID IN (SELECT aj.message_id FROM message_attachment_join as aj JOIN attachment as at ON aj.attachment_id = at.ROWID WHERE at.mime_type LIKE 'image/%')"
+        );
+    }
+
+    #[test]
+    fn can_generate_filter_statement_decoded_filters_only() {
+        let mut context = QueryContext::default();
+        context.set_text("invoice");
+        context.set_selected_kinds("edited").unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(statement, "");
+    }
+
+    #[test]
+    fn can_generate_filter_statement_expression() {
+        let mut context = QueryContext::default();
+        context
+            .set_expression("from:me AND NOT (service:sms OR attachment:image)")
+            .unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(
+            statement,
+            "WHERE  ((m.is_from_me = 1) AND NOT ((m.service IN ('SMS')) OR (m.ROWID IN (SELECT aj.message_id FROM message_attachment_join as aj JOIN attachment as at ON aj.attachment_id = at.ROWID WHERE at.mime_type LIKE 'image/%'))))"
+        );
+    }
+
+    #[test]
+    fn can_generate_filter_statement_expression_date() {
+        let mut context = QueryContext::default();
+        context.set_expression("date=2020-01-01").unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(
+            statement,
+            "WHERE  ((m.date >= 599558400000000000) AND (m.date < 599644800000000000))"
+        );
+    }
+
+    #[test]
+    fn can_generate_filter_statement_expression_decoded_terms() {
+        let mut context = QueryContext::default();
+        context
+            .set_expression("(text:lunch OR from:me) AND has:attachment AND NOT kind:edited")
+            .unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(
+            statement,
+            "WHERE  (m.ROWID IN (SELECT message_id FROM message_attachment_join))"
+        );
+    }
+
+    #[test]
+    fn can_generate_filter_statement_expression_with_chat_ids() {
+        let mut context = QueryContext::default();
+        context.set_selected_chat_ids(BTreeSet::from([1]));
+        context.set_expression("text:lunch").unwrap();
+
+        let statement = Message::generate_filter_statement(&context, false);
+        assert_eq!(statement, "WHERE  c.chat_id IN (1)");
+    }
+
     #[test]
     fn can_generate_filter_statement_boundary_dates() {
         let mut context = QueryContext::default();
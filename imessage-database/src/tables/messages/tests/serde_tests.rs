@@ -0,0 +1,148 @@
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use serde_json::{Value, json};
+
+    use crate::{
+        message_types::{
+            edited::{EditStatus, EditedEvent, EditedMessage, EditedMessagePart},
+            text_effects::{Style, TextEffect},
+            variants::{CustomBalloon, Tapback, TapbackAction, Variant},
+        },
+        tables::messages::{
+            Message,
+            models::{AttachmentMeta, BubbleComponent, Service, TextAttributes},
+        },
+        util::platform::Platform,
+    };
+
+    #[test]
+    fn can_serialize_message() {
+        let mut message = Message::blank();
+        message.guid = "guid".to_string();
+        message.text = Some("Hello world".to_string());
+
+        let actual = serde_json::to_value(&message).unwrap();
+
+        assert_eq!(actual["guid"], "guid");
+        assert_eq!(actual["text"], "Hello world");
+        // The raw `service` column is kept as-is, while the parsed service uses the enum's name
+        assert_eq!(actual["service"], "iMessage");
+        assert_eq!(serde_json::to_value(message.service()).unwrap(), "imessage");
+        assert_eq!(actual["is_from_me"], false);
+        assert_eq!(actual["components"], json!([]));
+        assert_eq!(actual["edited_parts"], Value::Null);
+    }
+
+    #[test]
+    fn can_round_trip_message() {
+        let mut message = Message::blank();
+        message.text = Some("Hello \u{FFFC}".to_string());
+        message.components = vec![
+            BubbleComponent::Text(vec![TextAttributes::new(
+                0,
+                5,
+                vec![TextEffect::Styles(vec![Style::Bold]), TextEffect::OTP],
+            )]),
+            BubbleComponent::Attachment(AttachmentMeta {
+                guid: Some("attachment".to_string()),
+                ..Default::default()
+            }),
+            BubbleComponent::Retracted,
+        ];
+        message.edited_parts = Some(EditedMessage {
+            parts: vec![EditedMessagePart {
+                status: EditStatus::Edited,
+                edit_history: vec![EditedEvent {
+                    date: 674526582885055488,
+                    text: Some("Hello".to_string()),
+                    components: vec![],
+                    guid: None,
+                }],
+            }],
+        });
+
+        let serialized = serde_json::to_string(&message).unwrap();
+        let deserialized: Message = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(deserialized.text, message.text);
+        assert_eq!(deserialized.components, message.components);
+        assert_eq!(deserialized.edited_parts, message.edited_parts);
+    }
+
+    #[test]
+    fn can_serialize_components() {
+        let components = vec![
+            BubbleComponent::Text(vec![TextAttributes::new(
+                0,
+                5,
+                vec![
+                    TextEffect::Mention("+5558675309".to_string()),
+                    TextEffect::OTP,
+                ],
+            )]),
+            BubbleComponent::App,
+        ];
+
+        let actual = serde_json::to_value(&components).unwrap();
+        let expected = json!([
+            {"text": [{"start": 0, "end": 5, "effects": [{"mention": "+5558675309"}, "otp"]}]},
+            "app",
+        ]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_serialize_variants() {
+        let tapback = Variant::Tapback(1, TapbackAction::Removed, Tapback::Emoji(Some("☕️")));
+        let url = Variant::App(CustomBalloon::URL);
+        let app = Variant::App(CustomBalloon::Application("com.example.app"));
+
+        assert_eq!(
+            serde_json::to_value(&tapback).unwrap(),
+            json!({"tapback": [1, "removed", {"emoji": "☕️"}]})
+        );
+        assert_eq!(serde_json::to_value(&url).unwrap(), json!({"app": "url"}));
+        assert_eq!(
+            serde_json::to_value(&app).unwrap(),
+            json!({"app": {"application": "com.example.app"}})
+        );
+        assert_eq!(serde_json::to_value(Variant::Normal).unwrap(), "normal");
+    }
+
+    #[test]
+    fn can_serialize_services() {
+        let actual: Vec<Value> = [
+            Service::iMessage,
+            Service::SMS,
+            Service::RCS,
+            Service::Satellite,
+            Service::Other("Other"),
+            Service::Unknown,
+        ]
+        .iter()
+        .map(|service| serde_json::to_value(service).unwrap())
+        .collect();
+
+        assert_eq!(
+            actual,
+            vec![
+                json!("imessage"),
+                json!("sms"),
+                json!("rcs"),
+                json!("satellite"),
+                json!({"other": "Other"}),
+                json!("unknown"),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_round_trip_platform() {
+        assert_eq!(serde_json::to_value(Platform::macOS).unwrap(), "macos");
+        assert_eq!(
+            serde_json::from_value::<Platform>(json!("ios")).unwrap(),
+            Platform::iOS
+        );
+    }
+}
@@ -0,0 +1,624 @@
+/*!
+ Contains the parser for filter expressions, a small boolean language that selects messages.
+
+ Terms are combined with `and`, `or`, `not`, and parentheses, i.e.
+
+ ```text
+ from:me and service:sms and date>=2023-01-01 and (text~"invoice" or has:attachment)
+ ```
+
+ | Term | Selects |
+ |---|---|
+ | `from:me` | Messages sent by the database owner |
+ | `from:<handle>` | Messages sent by a phone number or email address |
+ | `service:<service>` | Messages sent with `imessage`, `sms`, `rcs`, or `satellite` |
+ | `date<op><YYYY-MM-DD>` | Messages sent relative to a date, where `<op>` is `=`, `<`, `<=`, `>`, or `>=` |
+ | `has:attachment` | Messages that have attachments |
+ | `attachment:<type>` | Messages with an attachment of a MIME type family, i.e. `image` |
+ | `kind:<kind>` | `edited`, `app`, `shareplay`, or `announcement` messages |
+ | `status:<status>` | `unsent` or `deleted` messages |
+ | `text:<text>` | Messages whose text contains a string, ignoring case |
+ | `text~<pattern>` | Messages whose text matches a regular expression |
+
+ `not` binds most tightly, followed by `and`, then `or`. Values that contain spaces, parentheses, or operators must be quoted.
+
+ Terms that only depend on database columns are compiled to SQL. Terms like `text` only exist once a message is decoded,
+ so expressions that contain them are checked again by [`Message::matches_filters()`](crate::tables::messages::Message::matches_filters).
+*/
+
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::{
+    error::query_context::QueryContextError,
+    util::query_context::{
+        MessageKind, MessageStatus, QueryContext, SUPPORTED_KINDS, SUPPORTED_MIME_TYPES,
+        SUPPORTED_SERVICES, SUPPORTED_STATUSES, TextFilter, mime_type_family, service_values,
+    },
+};
+
+/// Operators that separate a field from its value, longest first so `>=` is not read as `>`
+const OPERATORS: [&str; 7] = [">=", "<=", ">", "<", "=", ":", "~"];
+
+// MARK: Expression
+/// A boolean expression of [`Term`]s
+#[derive(Debug, PartialEq, Eq)]
+pub enum Expression {
+    /// Both expressions match
+    And(Box<Expression>, Box<Expression>),
+    /// Either expression matches
+    Or(Box<Expression>, Box<Expression>),
+    /// The expression does not match
+    Not(Box<Expression>),
+    /// A single condition
+    Term(Term),
+}
+
+/// A single condition in an [`Expression`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Term {
+    /// Messages sent by the database owner
+    FromMe,
+    /// Messages sent by handles that match a participant string
+    FromHandles {
+        /// The participant string from the expression
+        filter: String,
+        /// Handle IDs that match the filter, populated by [`Expression::resolve_senders()`]
+        handle_ids: BTreeSet<i32>,
+    },
+    /// Messages sent with one of these values in the `service` column
+    Service(&'static [&'static str]),
+    /// Messages sent on or after this timestamp
+    OnOrAfter(i64),
+    /// Messages sent before this timestamp
+    Before(i64),
+    /// Messages that have attachments
+    HasAttachment,
+    /// Messages with an attachment in this MIME type family, i.e. `image`
+    MimeType(&'static str),
+    /// Messages whose decoded text matches
+    Text(TextFilter),
+    /// Messages of this kind
+    Kind(MessageKind),
+    /// Messages with this status
+    Status(MessageStatus),
+}
+
+impl Term {
+    /// `true` if the term can only be checked once the message is decoded, else `false`
+    #[must_use]
+    pub fn requires_decoding(&self) -> bool {
+        matches!(self, Term::Text(_) | Term::Kind(_) | Term::Status(_))
+    }
+}
+
+impl Expression {
+    /// Parse a filter expression
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::expression::Expression;
+    ///
+    /// let expression = Expression::parse(r#"from:me and (text~"invoice" or has:attachment)"#).unwrap();
+    /// assert!(expression.requires_decoding());
+    /// ```
+    pub fn parse(input: &str) -> Result<Self, QueryContextError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            len: input.len(),
+        };
+        let expression = parser.parse_or()?;
+        match parser.tokens.get(parser.index) {
+            None => Ok(expression),
+            Some((position, token)) => Err(QueryContextError::InvalidExpression(format!(
+                "unexpected {} at position {position}",
+                token.describe()
+            ))),
+        }
+    }
+
+    /// `true` if any term can only be checked once the message is decoded, else `false`
+    ///
+    /// If `false`, the compiled SQL selects exactly the messages that match.
+    #[must_use]
+    pub fn requires_decoding(&self) -> bool {
+        match self {
+            Expression::And(left, right) | Expression::Or(left, right) => {
+                left.requires_decoding() || right.requires_decoding()
+            }
+            Expression::Not(expression) => expression.requires_decoding(),
+            Expression::Term(term) => term.requires_decoding(),
+        }
+    }
+
+    /// Populate every `from:<handle>` term with the handle IDs that match its participant string
+    ///
+    /// Terms that are not resolved match no messages.
+    pub fn resolve_senders(&mut self, resolve: &impl Fn(&str) -> BTreeSet<i32>) {
+        match self {
+            Expression::And(left, right) | Expression::Or(left, right) => {
+                left.resolve_senders(resolve);
+                right.resolve_senders(resolve);
+            }
+            Expression::Not(expression) => expression.resolve_senders(resolve),
+            Expression::Term(Term::FromHandles { filter, handle_ids }) => {
+                *handle_ids = resolve(filter);
+            }
+            Expression::Term(_) => {}
+        }
+    }
+
+    /// Get the participant strings of `from:<handle>` terms that do not match any handles
+    #[must_use]
+    pub fn unmatched_senders(&self) -> Vec<&str> {
+        match self {
+            Expression::And(left, right) | Expression::Or(left, right) => {
+                let mut senders = left.unmatched_senders();
+                senders.extend(right.unmatched_senders());
+                senders
+            }
+            Expression::Not(expression) => expression.unmatched_senders(),
+            Expression::Term(Term::FromHandles { filter, handle_ids }) if handle_ids.is_empty() => {
+                vec![filter.as_str()]
+            }
+            Expression::Term(_) => vec![],
+        }
+    }
+}
+
+// MARK: Tokens
+/// A piece of a filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+    Quoted(String),
+    Operator(&'static str),
+}
+
+impl Token {
+    /// Describe the token for error messages
+    fn describe(&self) -> String {
+        match self {
+            Token::Open => String::from("`(`"),
+            Token::Close => String::from("`)`"),
+            Token::Word(word) => format!("`{word}`"),
+            Token::Quoted(text) => format!("\"{text}\""),
+            Token::Operator(operator) => format!("`{operator}`"),
+        }
+    }
+}
+
+/// Split a filter expression into tokens, each with the byte position it starts at
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryContextError> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(position, char)) = chars.peek() {
+        if char.is_whitespace() {
+            chars.next();
+        } else if char == '(' {
+            chars.next();
+            tokens.push((position, Token::Open));
+        } else if char == ')' {
+            chars.next();
+            tokens.push((position, Token::Close));
+        } else if char == '"' {
+            chars.next();
+            let mut text = String::new();
+            let mut closed = false;
+            while let Some((_, char)) = chars.next() {
+                match char {
+                    '"' => {
+                        closed = true;
+                        break;
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, escaped @ ('"' | '\\'))) => text.push(escaped),
+                        Some((_, other)) => {
+                            text.push('\\');
+                            text.push(other);
+                        }
+                        None => text.push('\\'),
+                    },
+                    _ => text.push(char),
+                }
+            }
+            if !closed {
+                return Err(QueryContextError::InvalidExpression(format!(
+                    "unclosed quote at position {position}"
+                )));
+            }
+            tokens.push((position, Token::Quoted(text)));
+        } else if let Some(operator) = OPERATORS
+            .iter()
+            .find(|operator| input[position..].starts_with(*operator))
+        {
+            for _ in 0..operator.len() {
+                chars.next();
+            }
+            tokens.push((position, Token::Operator(operator)));
+        } else {
+            let mut word = String::new();
+            while let Some(&(_, char)) = chars.peek() {
+                if char.is_whitespace() || "()\":~<>=".contains(char) {
+                    break;
+                }
+                word.push(char);
+                chars.next();
+            }
+            tokens.push((position, Token::Word(word)));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// MARK: Parser
+/// Recursive descent parser over a list of tokens
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    /// Length of the input, used to report errors at the end of the expression
+    len: usize,
+}
+
+impl Parser {
+    /// `or` has the lowest precedence
+    fn parse_or(&mut self) -> Result<Expression, QueryContextError> {
+        let mut expression = self.parse_and()?;
+        while self.next_keyword("or") {
+            let right = self.parse_and()?;
+            expression = Expression::Or(Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, QueryContextError> {
+        let mut expression = self.parse_not()?;
+        while self.next_keyword("and") {
+            let right = self.parse_not()?;
+            expression = Expression::And(Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, QueryContextError> {
+        if self.next_keyword("not") {
+            return Ok(Expression::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    /// A parenthesized expression or a single term
+    fn parse_primary(&mut self) -> Result<Expression, QueryContextError> {
+        match self.next() {
+            Some((_, Token::Open)) => {
+                let expression = self.parse_or()?;
+                match self.next() {
+                    Some((_, Token::Close)) => Ok(expression),
+                    Some((position, token)) => Err(QueryContextError::InvalidExpression(format!(
+                        "expected `)` but found {} at position {position}",
+                        token.describe()
+                    ))),
+                    None => Err(self.unexpected_end("`)`")),
+                }
+            }
+            Some((position, Token::Word(field))) => {
+                let operator = match self.next() {
+                    Some((_, Token::Operator(operator))) => operator,
+                    Some((position, token)) => {
+                        return Err(QueryContextError::InvalidExpression(format!(
+                            "expected an operator after `{field}` but found {} at position {position}",
+                            token.describe()
+                        )));
+                    }
+                    None => return Err(self.unexpected_end("an operator")),
+                };
+                let value = match self.next() {
+                    Some((_, Token::Word(value) | Token::Quoted(value))) => value,
+                    Some((position, token)) => {
+                        return Err(QueryContextError::InvalidExpression(format!(
+                            "expected a value after `{field}{operator}` but found {} at position {position}",
+                            token.describe()
+                        )));
+                    }
+                    None => return Err(self.unexpected_end("a value")),
+                };
+                parse_term(&field, operator, &value, position)
+            }
+            Some((position, token)) => Err(QueryContextError::InvalidExpression(format!(
+                "expected a filter but found {} at position {position}",
+                token.describe()
+            ))),
+            None => Err(self.unexpected_end("a filter")),
+        }
+    }
+
+    /// Get the next token, advancing the parser
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    /// Consume the next token if it is the keyword, ignoring case
+    fn next_keyword(&mut self, keyword: &str) -> bool {
+        if let Some((_, Token::Word(word))) = self.tokens.get(self.index)
+            && word.eq_ignore_ascii_case(keyword)
+        {
+            self.index += 1;
+            return true;
+        }
+        false
+    }
+
+    fn unexpected_end(&self, expected: &str) -> QueryContextError {
+        QueryContextError::InvalidExpression(format!(
+            "expected {expected} at position {}",
+            self.len
+        ))
+    }
+}
+
+/// Build the expression for a single `field`, `operator`, and `value`
+fn parse_term(
+    field: &str,
+    operator: &str,
+    value: &str,
+    position: usize,
+) -> Result<Expression, QueryContextError> {
+    let invalid =
+        |supported: &'static str| QueryContextError::InvalidFilter(value.to_string(), supported);
+
+    let term = match (field.to_lowercase().as_str(), operator) {
+        ("from", ":") if value.eq_ignore_ascii_case("me") => Term::FromMe,
+        ("from", ":") => Term::FromHandles {
+            filter: value.to_string(),
+            handle_ids: BTreeSet::new(),
+        },
+        ("service", ":") => {
+            Term::Service(service_values(value).ok_or_else(|| invalid(SUPPORTED_SERVICES))?)
+        }
+        ("has", ":")
+            if value.eq_ignore_ascii_case("attachment")
+                || value.eq_ignore_ascii_case("attachments") =>
+        {
+            Term::HasAttachment
+        }
+        ("has", ":") => return Err(invalid("attachment")),
+        ("attachment", ":") => {
+            Term::MimeType(mime_type_family(value).ok_or_else(|| invalid(SUPPORTED_MIME_TYPES))?)
+        }
+        ("kind", ":") => {
+            Term::Kind(MessageKind::from_cli(value).ok_or_else(|| invalid(SUPPORTED_KINDS))?)
+        }
+        ("status", ":") => {
+            Term::Status(MessageStatus::from_cli(value).ok_or_else(|| invalid(SUPPORTED_STATUSES))?)
+        }
+        ("text", ":") => Term::Text(TextFilter::Contains(value.to_lowercase())),
+        ("text", "~") => Term::Text(TextFilter::Regex(Regex::new(value).map_err(|why| {
+            QueryContextError::InvalidRegex(value.to_string(), why.to_string())
+        })?)),
+        ("date", _) => return parse_date(operator, value),
+        (field, operator) => {
+            return Err(QueryContextError::InvalidExpression(format!(
+                "unknown filter `{field}{operator}` at position {position}"
+            )));
+        }
+    };
+    Ok(Expression::Term(term))
+}
+
+/// Build the expression for a date comparison, where a date covers the entire day
+fn parse_date(operator: &str, date: &str) -> Result<Expression, QueryContextError> {
+    let invalid = || QueryContextError::InvalidDate(date.to_string());
+    let start = QueryContext::sanitize_date(date).ok_or_else(invalid)?;
+    let next_day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|day| day.succ_opt())
+        .ok_or_else(invalid)?;
+    let end = QueryContext::sanitize_date(&next_day.format("%Y-%m-%d").to_string())
+        .ok_or_else(invalid)?;
+
+    Ok(match operator {
+        ">=" => Expression::Term(Term::OnOrAfter(start)),
+        ">" => Expression::Term(Term::OnOrAfter(end)),
+        "<" => Expression::Term(Term::Before(start)),
+        "<=" => Expression::Term(Term::Before(end)),
+        "=" => Expression::And(
+            Box::new(Expression::Term(Term::OnOrAfter(start))),
+            Box::new(Expression::Term(Term::Before(end))),
+        ),
+        operator => {
+            return Err(QueryContextError::InvalidExpression(format!(
+                "`date{operator}` is not a comparison; use =, <, <=, >, or >="
+            )));
+        }
+    })
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::util::{
+        expression::{Expression, Term},
+        query_context::{MessageKind, QueryContext, TextFilter},
+    };
+
+    fn term(term: Term) -> Box<Expression> {
+        Box::new(Expression::Term(term))
+    }
+
+    #[test]
+    fn can_parse_term() {
+        assert_eq!(
+            Expression::parse("from:me").unwrap(),
+            Expression::Term(Term::FromMe)
+        );
+        assert_eq!(
+            Expression::parse("service:SMS").unwrap(),
+            Expression::Term(Term::Service(&["SMS"]))
+        );
+        assert_eq!(
+            Expression::parse("attachment:image").unwrap(),
+            Expression::Term(Term::MimeType("image"))
+        );
+    }
+
+    #[test]
+    fn can_parse_precedence() {
+        // `and` binds more tightly than `or`
+        assert_eq!(
+            Expression::parse("from:me or has:attachment and kind:edited").unwrap(),
+            Expression::Or(
+                term(Term::FromMe),
+                Box::new(Expression::And(
+                    term(Term::HasAttachment),
+                    term(Term::Kind(MessageKind::Edited))
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn can_parse_parentheses_and_not() {
+        assert_eq!(
+            Expression::parse("NOT (from:me OR has:attachment)").unwrap(),
+            Expression::Not(Box::new(Expression::Or(
+                term(Term::FromMe),
+                term(Term::HasAttachment)
+            )))
+        );
+    }
+
+    #[test]
+    fn can_parse_example() {
+        let expression = Expression::parse(
+            r#"from:me and service:sms and date>=2023-01-01 and (text~"invoice" or has:attachment)"#,
+        )
+        .unwrap();
+
+        let mut context = QueryContext::default();
+        context.set_start("2023-01-01").unwrap();
+
+        assert_eq!(
+            expression,
+            Expression::And(
+                Box::new(Expression::And(
+                    Box::new(Expression::And(
+                        term(Term::FromMe),
+                        term(Term::Service(&["SMS"]))
+                    )),
+                    term(Term::OnOrAfter(context.start.unwrap()))
+                )),
+                Box::new(Expression::Or(
+                    term(Term::Text(TextFilter::Regex(
+                        regex::Regex::new("invoice").unwrap()
+                    ))),
+                    term(Term::HasAttachment)
+                ))
+            )
+        );
+        assert!(expression.requires_decoding());
+    }
+
+    #[test]
+    fn can_parse_quoted_value() {
+        assert_eq!(
+            Expression::parse(r#"text:"See You \"Soon\"""#).unwrap(),
+            Expression::Term(Term::Text(TextFilter::Contains(
+                "see you \"soon\"".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn can_parse_dates() {
+        let mut start = QueryContext::default();
+        start.set_start("2023-01-01").unwrap();
+        let mut end = QueryContext::default();
+        end.set_start("2023-01-02").unwrap();
+        let (start, end) = (start.start.unwrap(), end.start.unwrap());
+
+        assert_eq!(
+            Expression::parse("date>2023-01-01").unwrap(),
+            Expression::Term(Term::OnOrAfter(end))
+        );
+        assert_eq!(
+            Expression::parse("date<2023-01-01").unwrap(),
+            Expression::Term(Term::Before(start))
+        );
+        assert_eq!(
+            Expression::parse("date<=2023-01-01").unwrap(),
+            Expression::Term(Term::Before(end))
+        );
+        assert_eq!(
+            Expression::parse("date=2023-01-01").unwrap(),
+            Expression::And(term(Term::OnOrAfter(start)), term(Term::Before(end)))
+        );
+    }
+
+    #[test]
+    fn can_resolve_senders() {
+        let mut expression =
+            Expression::parse("from:steve@apple.com or from:5558675309 or from:me").unwrap();
+        assert_eq!(
+            expression.unmatched_senders(),
+            vec!["steve@apple.com", "5558675309"]
+        );
+
+        expression.resolve_senders(&|filter| {
+            if filter == "5558675309" {
+                BTreeSet::from([1, 2])
+            } else {
+                BTreeSet::new()
+            }
+        });
+
+        assert_eq!(expression.unmatched_senders(), vec!["steve@apple.com"]);
+    }
+
+    #[test]
+    fn cant_parse_invalid() {
+        for input in [
+            "",
+            "from:me and",
+            "from:me or or has:attachment",
+            "(from:me",
+            "from:me)",
+            "from",
+            "from:",
+            "sender:me",
+            "service:pager",
+            "has:replies",
+            "kind:tapback",
+            "status:read",
+            "attachment:image/png",
+            "date>=01-01-2023",
+            "date:2023-01-01",
+            "text~\"(unclosed\"",
+            "text:\"unclosed",
+            "from:me has:attachment",
+        ] {
+            assert!(Expression::parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn can_report_position() {
+        let error = Expression::parse("from:me and (has:attachment").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid expression provided: expected `)` at position 27!"
+        );
+    }
+}
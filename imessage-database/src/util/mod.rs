@@ -5,6 +5,8 @@
 pub mod bundle_id;
 pub mod dates;
 pub mod dirs;
+pub mod expression;
+pub mod normalize;
 pub mod output;
 pub mod platform;
 pub mod plist;
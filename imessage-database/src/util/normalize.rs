@@ -0,0 +1,336 @@
+/*!
+ Contains functions that normalize contact handles so the same phone number or email address compares equal regardless of how it was stored.
+
+ Handles in the `handle` table may appear as `+15551234567`, `5551234567`, `(555) 123-4567`, or `tel:` URIs depending on the service that created them.
+ Phone numbers are normalized to [E.164](https://en.wikipedia.org/wiki/E.164) using a default region for numbers saved without a country code,
+ and email addresses are lowercased.
+*/
+
+use std::fmt::Display;
+
+/// Longest phone number allowed by E.164, excluding the leading `+`
+const MAX_E164_DIGITS: usize = 15;
+/// Longest number that is treated as a short code instead of a national number
+const MAX_SHORT_CODE_DIGITS: usize = 6;
+/// URI schemes that can prefix a handle
+const SCHEMES: [&str; 3] = ["tel:", "sms:", "mailto:"];
+
+// MARK: Region
+/// Describes how phone numbers are dialed in a country or region
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Region {
+    /// The ISO 3166-1 alpha-2 code for the region, i.e. `US`
+    pub code: &'static str,
+    /// The country calling code, i.e. `1` for `+1`
+    pub calling_code: &'static str,
+    /// The digits dialed before a national number, i.e. the `0` in `07700 900123`
+    pub trunk_prefix: Option<&'static str>,
+    /// The digits dialed before an international number, i.e. `011` in North America
+    pub international_prefix: &'static str,
+}
+
+/// Regions that can be used to normalize phone numbers saved without a country code
+pub const REGIONS: &[Region] = &[
+    Region::new("US", "1", Some("1"), "011"),
+    Region::new("CA", "1", Some("1"), "011"),
+    Region::new("GB", "44", Some("0"), "00"),
+    Region::new("IE", "353", Some("0"), "00"),
+    Region::new("AU", "61", Some("0"), "0011"),
+    Region::new("NZ", "64", Some("0"), "00"),
+    Region::new("DE", "49", Some("0"), "00"),
+    Region::new("FR", "33", Some("0"), "00"),
+    Region::new("NL", "31", Some("0"), "00"),
+    Region::new("BE", "32", Some("0"), "00"),
+    Region::new("CH", "41", Some("0"), "00"),
+    Region::new("AT", "43", Some("0"), "00"),
+    Region::new("SE", "46", Some("0"), "00"),
+    Region::new("NO", "47", None, "00"),
+    Region::new("DK", "45", None, "00"),
+    Region::new("FI", "358", Some("0"), "00"),
+    Region::new("ES", "34", None, "00"),
+    Region::new("PT", "351", None, "00"),
+    Region::new("IT", "39", None, "00"),
+    Region::new("PL", "48", None, "00"),
+    Region::new("IL", "972", Some("0"), "00"),
+    Region::new("IN", "91", Some("0"), "00"),
+    Region::new("CN", "86", Some("0"), "00"),
+    Region::new("HK", "852", None, "001"),
+    Region::new("SG", "65", None, "000"),
+    Region::new("JP", "81", Some("0"), "010"),
+    Region::new("KR", "82", Some("0"), "00"),
+    Region::new("BR", "55", Some("0"), "00"),
+    Region::new("MX", "52", None, "00"),
+    Region::new("ZA", "27", Some("0"), "00"),
+];
+
+impl Region {
+    const fn new(
+        code: &'static str,
+        calling_code: &'static str,
+        trunk_prefix: Option<&'static str>,
+        international_prefix: &'static str,
+    ) -> Self {
+        Self {
+            code,
+            calling_code,
+            trunk_prefix,
+            international_prefix,
+        }
+    }
+
+    /// Given user's input, return a region if the input matches its code
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::normalize::Region;
+    ///
+    /// let region = Region::from_cli("gb").unwrap();
+    /// assert_eq!(region.calling_code, "44");
+    /// ```
+    #[must_use]
+    pub fn from_cli(code: &str) -> Option<Self> {
+        REGIONS
+            .iter()
+            .find(|region| region.code.eq_ignore_ascii_case(code.trim()))
+            .copied()
+    }
+}
+
+impl Default for Region {
+    /// The default Region is `US`.
+    fn default() -> Self {
+        REGIONS[0]
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "{}", self.code)
+    }
+}
+
+// MARK: Normalize
+/// Normalize a phone number, email address, or other handle so differently formatted copies compare equal
+///
+/// Phone numbers become E.164, emails are lowercased, and anything else is returned trimmed.
+///
+/// # Example:
+///
+/// ```
+/// use imessage_database::util::normalize::{Region, normalize_handle};
+///
+/// let region = Region::default();
+/// assert_eq!(normalize_handle("(555) 123-4567", &region), "+15551234567");
+/// assert_eq!(normalize_handle("tel:+1 555 123 4567", &region), "+15551234567");
+/// assert_eq!(normalize_handle("Steve@Apple.com", &region), "steve@apple.com");
+/// ```
+#[must_use]
+pub fn normalize_handle(handle: &str, region: &Region) -> String {
+    normalize_email(handle)
+        .or_else(|| normalize_phone(handle, region))
+        .unwrap_or_else(|| strip_scheme(handle).to_string())
+}
+
+/// Normalize an email address by removing any `mailto:` scheme and lowercasing it
+///
+/// Returns [`None`] if the handle is not an email address.
+#[must_use]
+pub fn normalize_email(handle: &str) -> Option<String> {
+    let email = strip_scheme(handle);
+    let (user, domain) = email.split_once('@')?;
+    if user.is_empty() || domain.is_empty() || email.chars().any(char::is_whitespace) {
+        return None;
+    }
+    Some(email.to_lowercase())
+}
+
+/// Normalize a phone number to E.164, using the region for numbers saved without a country code
+///
+/// Short codes, i.e. `12345`, have no country code, so they are returned as bare digits.
+/// Returns [`None`] if the handle is not a phone number, or is too long to be valid E.164.
+///
+/// # Example:
+///
+/// ```
+/// use imessage_database::util::normalize::{Region, normalize_phone};
+///
+/// let region = Region::from_cli("GB").unwrap();
+/// assert_eq!(normalize_phone("07700 900123", &region), Some("+447700900123".to_string()));
+/// assert_eq!(normalize_phone("+1 (555) 123-4567", &region), Some("+15551234567".to_string()));
+/// assert_eq!(normalize_phone("steve@apple.com", &region), None);
+/// ```
+#[must_use]
+pub fn normalize_phone(handle: &str, region: &Region) -> Option<String> {
+    let number = strip_scheme(handle);
+    let is_international = number.starts_with('+');
+
+    let mut digits = String::with_capacity(number.len());
+    for char in number.trim_start_matches('+').chars() {
+        match char {
+            '0'..='9' => digits.push(char),
+            // Visual separators
+            ' ' | '-' | '.' | '(' | ')' | '/' | '\u{a0}' => {}
+            _ => return None,
+        }
+    }
+    if digits.is_empty() {
+        return None;
+    }
+
+    let international = if is_international {
+        digits
+    } else if let Some(international) = digits.strip_prefix(region.international_prefix) {
+        international.to_string()
+    } else if digits.len() <= MAX_SHORT_CODE_DIGITS {
+        return Some(digits);
+    } else {
+        let national = region
+            .trunk_prefix
+            .and_then(|prefix| digits.strip_prefix(prefix))
+            .unwrap_or(&digits);
+        format!("{}{national}", region.calling_code)
+    };
+
+    if international.len() > MAX_E164_DIGITS {
+        return None;
+    }
+    Some(format!("+{international}"))
+}
+
+/// Remove a URI scheme, i.e. `tel:`, and surrounding whitespace from a handle
+fn strip_scheme(handle: &str) -> &str {
+    let handle = handle.trim();
+    SCHEMES
+        .iter()
+        .find_map(|scheme| {
+            handle
+                .get(..scheme.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(scheme))
+                .map(|_| handle[scheme.len()..].trim())
+        })
+        .unwrap_or(handle)
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use crate::util::normalize::{Region, normalize_email, normalize_handle, normalize_phone};
+
+    fn region(code: &str) -> Region {
+        Region::from_cli(code).unwrap()
+    }
+
+    #[test]
+    fn can_normalize_us_formats() {
+        let us = Region::default();
+        let expected = Some("+15551234567".to_string());
+
+        assert_eq!(normalize_phone("+15551234567", &us), expected);
+        assert_eq!(normalize_phone("5551234567", &us), expected);
+        assert_eq!(normalize_phone("15551234567", &us), expected);
+        assert_eq!(normalize_phone("(555) 123-4567", &us), expected);
+        assert_eq!(normalize_phone("555.123.4567", &us), expected);
+        assert_eq!(normalize_phone("tel:+1-555-123-4567", &us), expected);
+        assert_eq!(normalize_phone("011 1 555 123 4567", &us), expected);
+    }
+
+    #[test]
+    fn can_normalize_with_trunk_prefix() {
+        assert_eq!(
+            normalize_phone("07700 900123", &region("GB")),
+            Some("+447700900123".to_string())
+        );
+        assert_eq!(
+            normalize_phone("0044 7700 900123", &region("GB")),
+            Some("+447700900123".to_string())
+        );
+    }
+
+    #[test]
+    fn can_normalize_without_trunk_prefix() {
+        assert_eq!(
+            normalize_phone("06 12345678", &region("IT")),
+            Some("+390612345678".to_string())
+        );
+    }
+
+    #[test]
+    fn can_normalize_international_in_other_region() {
+        assert_eq!(
+            normalize_phone("+44 7700 900123", &Region::default()),
+            Some("+447700900123".to_string())
+        );
+    }
+
+    #[test]
+    fn can_normalize_short_code() {
+        assert_eq!(
+            normalize_phone("12345", &Region::default()),
+            Some("12345".to_string())
+        );
+    }
+
+    #[test]
+    fn cant_normalize_non_phone() {
+        assert_eq!(normalize_phone("steve@apple.com", &Region::default()), None);
+        assert_eq!(normalize_phone("urn:biz:1234", &Region::default()), None);
+        assert_eq!(normalize_phone("", &Region::default()), None);
+        assert_eq!(normalize_phone("+", &Region::default()), None);
+    }
+
+    #[test]
+    fn cant_normalize_long_phone() {
+        let us = Region::default();
+        assert_eq!(normalize_phone("+1234567890123456", &us), None);
+        assert_eq!(normalize_phone("1234567890123456", &us), None);
+        assert_eq!(
+            normalize_handle("tel:1234567890123456", &us),
+            "1234567890123456"
+        );
+    }
+
+    #[test]
+    fn can_normalize_email() {
+        assert_eq!(
+            normalize_email(" Steve@Apple.COM "),
+            Some("steve@apple.com".to_string())
+        );
+        assert_eq!(
+            normalize_email("mailto:steve@apple.com"),
+            Some("steve@apple.com".to_string())
+        );
+    }
+
+    #[test]
+    fn cant_normalize_non_email() {
+        assert_eq!(normalize_email("+15551234567"), None);
+        assert_eq!(normalize_email("@apple.com"), None);
+        assert_eq!(normalize_email("steve@"), None);
+    }
+
+    #[test]
+    fn can_normalize_other_handle() {
+        assert_eq!(
+            normalize_handle(" urn:biz:1234 ", &Region::default()),
+            "urn:biz:1234"
+        );
+    }
+
+    #[test]
+    fn can_parse_region_any_case() {
+        assert_eq!(Region::from_cli("us"), Some(Region::default()));
+        assert_eq!(Region::from_cli("Gb").map(|region| region.code), Some("GB"));
+    }
+
+    #[test]
+    fn cant_parse_invalid_region() {
+        assert!(Region::from_cli("XX").is_none());
+        assert!(Region::from_cli("").is_none());
+    }
+
+    #[test]
+    fn can_display_region() {
+        assert_eq!(Region::default().to_string(), "US");
+    }
+}
@@ -11,12 +11,15 @@ use crate::{
 
 /// Represents the platform that created the database this library connects to
 #[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Platform {
     /// macOS-sourced data
     #[allow(non_camel_case_types)]
+    #[cfg_attr(feature = "serde", serde(rename = "macos"))]
     macOS,
     /// iOS-sourced data
     #[allow(non_camel_case_types)]
+    #[cfg_attr(feature = "serde", serde(rename = "ios"))]
     iOS,
 }
 
@@ -4,12 +4,127 @@
 use std::collections::BTreeSet;
 
 use chrono::prelude::*;
+use regex::Regex;
 
 use crate::{
     error::query_context::QueryContextError,
-    util::dates::{TIMESTAMP_FACTOR, get_offset},
+    util::{
+        dates::{TIMESTAMP_FACTOR, get_offset},
+        expression::Expression,
+    },
 };
 
+/// Services that can be selected, as `(name, values stored in the service column)`
+const SERVICES: [(&str, &[&str]); 4] = [
+    ("imessage", &["iMessage"]),
+    ("sms", &["SMS"]),
+    ("rcs", &["rcs", "RCS"]),
+    ("satellite", &["iMessageLite"]),
+];
+/// Attachment MIME type families that can be selected
+const MIME_TYPES: [&str; 5] = ["image", "video", "audio", "text", "application"];
+
+/// Supported values for [`QueryContext::set_selected_services()`]
+pub const SUPPORTED_SERVICES: &str = "imessage, sms, rcs, satellite";
+/// Supported values for [`QueryContext::set_selected_mime_types()`]
+pub const SUPPORTED_MIME_TYPES: &str = "image, video, audio, text, application";
+/// Supported values for [`QueryContext::set_selected_kinds()`]
+pub const SUPPORTED_KINDS: &str = "edited, app, shareplay, announcement";
+/// Supported values for [`QueryContext::set_selected_statuses()`]
+pub const SUPPORTED_STATUSES: &str = "unsent, deleted";
+
+// MARK: Text
+/// Matches the decoded text of a message
+#[derive(Debug)]
+pub enum TextFilter {
+    /// Text that contains this lowercase substring, ignoring case
+    Contains(String),
+    /// Text that matches this regular expression
+    Regex(Regex),
+}
+
+impl TextFilter {
+    /// `true` if the text matches the filter, else `false`
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::TextFilter;
+    ///
+    /// let filter = TextFilter::Contains("invoice".to_string());
+    /// assert!(filter.matches("Your Invoice is attached"));
+    /// ```
+    #[must_use]
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            TextFilter::Contains(substring) => text.to_lowercase().contains(substring),
+            TextFilter::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+impl PartialEq for TextFilter {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TextFilter::Contains(a), TextFilter::Contains(b)) => a == b,
+            (TextFilter::Regex(a), TextFilter::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TextFilter {}
+
+// MARK: Kinds
+/// Kinds of messages that can be selected
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum MessageKind {
+    /// Messages with at least one edited part
+    Edited,
+    /// Messages sent by an iMessage app, including URL previews
+    App,
+    /// SharePlay and FaceTime messages
+    SharePlay,
+    /// Announcements, i.e. group name changes or participants joining
+    Announcement,
+}
+
+impl MessageKind {
+    /// Given user's input, return a variant if the input matches one
+    #[must_use]
+    pub fn from_cli(kind: &str) -> Option<Self> {
+        match kind.to_lowercase().as_str() {
+            "edited" => Some(Self::Edited),
+            "app" => Some(Self::App),
+            "shareplay" => Some(Self::SharePlay),
+            "announcement" => Some(Self::Announcement),
+            _ => None,
+        }
+    }
+}
+
+/// Statuses of messages that can be selected
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum MessageStatus {
+    /// Messages with at least one unsent part
+    Unsent,
+    /// Messages that were deleted but are still recoverable
+    Deleted,
+}
+
+impl MessageStatus {
+    /// Given user's input, return a variant if the input matches one
+    #[must_use]
+    pub fn from_cli(status: &str) -> Option<Self> {
+        match status.to_lowercase().as_str() {
+            "unsent" => Some(Self::Unsent),
+            "deleted" => Some(Self::Deleted),
+            _ => None,
+        }
+    }
+}
+
+// MARK: Context
 #[derive(Debug, Default, PartialEq, Eq)]
 /// Represents filter configurations for a SQL query.
 pub struct QueryContext {
@@ -21,6 +136,24 @@ pub struct QueryContext {
     pub selected_handle_ids: Option<BTreeSet<i32>>,
     /// Selected chat IDs
     pub selected_chat_ids: Option<BTreeSet<i32>>,
+    /// Only include messages whose decoded text matches this filter
+    pub text: Option<TextFilter>,
+    /// Only include messages sent by the database owner, or by a selected sender if there are any
+    pub from_me: bool,
+    /// Only include messages sent by these handle IDs, or by the database owner if `from_me` is set
+    pub selected_sender_ids: Option<BTreeSet<i32>>,
+    /// Only include messages sent with these values in the `service` column
+    pub selected_services: Option<BTreeSet<&'static str>>,
+    /// Only include messages that have attachments
+    pub has_attachments: bool,
+    /// Only include messages with an attachment in these MIME type families, i.e. `image`
+    pub selected_mime_types: Option<BTreeSet<&'static str>>,
+    /// Only include messages of these kinds
+    pub selected_kinds: Option<BTreeSet<MessageKind>>,
+    /// Only include messages with these statuses
+    pub selected_statuses: Option<BTreeSet<MessageStatus>>,
+    /// Only include messages that match this filter expression
+    pub expression: Option<Expression>,
 }
 
 impl QueryContext {
@@ -86,8 +219,163 @@ impl QueryContext {
         self.selected_chat_ids = (!selected_chat_ids.is_empty()).then_some(selected_chat_ids);
     }
 
+    /// Populate a [`QueryContext`] with a case-insensitive substring to find in message text
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_text("invoice");
+    /// ```
+    pub fn set_text(&mut self, text: &str) {
+        self.text = Some(TextFilter::Contains(text.to_lowercase()));
+    }
+
+    /// Populate a [`QueryContext`] with a regular expression to match against message text
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_text_regex(r"(?i)invoice #\d+");
+    /// ```
+    pub fn set_text_regex(&mut self, pattern: &str) -> Result<(), QueryContextError> {
+        let regex = Regex::new(pattern)
+            .map_err(|why| QueryContextError::InvalidRegex(pattern.to_string(), why.to_string()))?;
+        self.text = Some(TextFilter::Regex(regex));
+        Ok(())
+    }
+
+    /// Populate a [`QueryContext`] to select messages sent by the database owner
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_from_me(true);
+    /// ```
+    pub fn set_from_me(&mut self, from_me: bool) {
+        self.from_me = from_me;
+    }
+
+    /// Populate a [`QueryContext`] with a list of sender handle IDs to select
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_selected_sender_ids(BTreeSet::from([1, 2, 3]));
+    /// ```
+    pub fn set_selected_sender_ids(&mut self, selected_sender_ids: BTreeSet<i32>) {
+        self.selected_sender_ids = (!selected_sender_ids.is_empty()).then_some(selected_sender_ids);
+    }
+
+    /// Populate a [`QueryContext`] with a comma separated list of services to select
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_selected_services("sms,rcs").unwrap();
+    /// ```
+    pub fn set_selected_services(&mut self, services: &str) -> Result<(), QueryContextError> {
+        let selected = parse_list(services, SUPPORTED_SERVICES, service_values)?;
+        self.selected_services = Some(selected.into_iter().flatten().copied().collect());
+        Ok(())
+    }
+
+    /// Populate a [`QueryContext`] to select messages that have attachments
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_has_attachments(true);
+    /// ```
+    pub fn set_has_attachments(&mut self, has_attachments: bool) {
+        self.has_attachments = has_attachments;
+    }
+
+    /// Populate a [`QueryContext`] with a comma separated list of attachment MIME type families to select
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_selected_mime_types("image,video").unwrap();
+    /// ```
+    pub fn set_selected_mime_types(&mut self, mime_types: &str) -> Result<(), QueryContextError> {
+        let selected = parse_list(mime_types, SUPPORTED_MIME_TYPES, mime_type_family)?;
+        self.selected_mime_types = Some(selected);
+        Ok(())
+    }
+
+    /// Populate a [`QueryContext`] with a comma separated list of message kinds to select
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_selected_kinds("edited,app").unwrap();
+    /// ```
+    pub fn set_selected_kinds(&mut self, kinds: &str) -> Result<(), QueryContextError> {
+        self.selected_kinds = Some(parse_list(kinds, SUPPORTED_KINDS, MessageKind::from_cli)?);
+        Ok(())
+    }
+
+    /// Populate a [`QueryContext`] with a comma separated list of message statuses to select
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_selected_statuses("unsent").unwrap();
+    /// ```
+    pub fn set_selected_statuses(&mut self, statuses: &str) -> Result<(), QueryContextError> {
+        self.selected_statuses = Some(parse_list(
+            statuses,
+            SUPPORTED_STATUSES,
+            MessageStatus::from_cli,
+        )?);
+        Ok(())
+    }
+
+    /// Populate a [`QueryContext`] with a filter expression, see [`expression`](crate::util::expression) for the syntax
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_expression("from:me and (service:sms or has:attachment)").unwrap();
+    /// ```
+    pub fn set_expression(&mut self, expression: &str) -> Result<(), QueryContextError> {
+        self.expression = Some(Expression::parse(expression)?);
+        Ok(())
+    }
+
     /// Ensure a date string is valid
-    fn sanitize_date(date: &str) -> Option<i64> {
+    pub(crate) fn sanitize_date(date: &str) -> Option<i64> {
         if date.len() < 9 {
             return None;
         }
@@ -136,7 +424,67 @@ impl QueryContext {
             || self.end.is_some()
             || self.selected_chat_ids.is_some()
             || self.selected_handle_ids.is_some()
+            || self.has_message_filters()
     }
+
+    /// Determine if the current `QueryContext` filters messages by their sender, content, or status
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use imessage_database::util::query_context::QueryContext;
+    ///
+    /// let mut context = QueryContext::default();
+    /// context.set_start("2023-01-01");
+    /// assert!(!context.has_message_filters());
+    /// context.set_has_attachments(true);
+    /// assert!(context.has_message_filters());
+    /// ```
+    #[must_use]
+    pub fn has_message_filters(&self) -> bool {
+        self.text.is_some()
+            || self.from_me
+            || self.selected_sender_ids.is_some()
+            || self.selected_services.is_some()
+            || self.has_attachments
+            || self.selected_mime_types.is_some()
+            || self.selected_kinds.is_some()
+            || self.selected_statuses.is_some()
+            || self.expression.is_some()
+    }
+}
+
+/// Get the values stored in the `service` column for a service name
+pub(crate) fn service_values(service: &str) -> Option<&'static [&'static str]> {
+    SERVICES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(service))
+        .map(|(_, values)| *values)
+}
+
+/// Get the MIME type family that matches the user's input
+pub(crate) fn mime_type_family(mime_type: &str) -> Option<&'static str> {
+    MIME_TYPES
+        .iter()
+        .find(|family| family.eq_ignore_ascii_case(mime_type))
+        .copied()
+}
+
+/// Parse a comma separated list of filter values, failing on the first value that is not supported
+fn parse_list<T: Ord>(
+    values: &str,
+    supported: &'static str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<BTreeSet<T>, QueryContextError> {
+    values
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            parse(value)
+                .ok_or_else(|| QueryContextError::InvalidFilter(value.to_string(), supported))
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -283,6 +631,133 @@ mod id_tests {
     }
 }
 
+#[cfg(test)]
+mod message_filter_tests {
+    use std::collections::BTreeSet;
+
+    use crate::util::query_context::{MessageKind, MessageStatus, QueryContext, TextFilter};
+
+    #[test]
+    fn can_set_text() {
+        let mut qc = QueryContext::default();
+        qc.set_text("Invoice");
+
+        assert_eq!(qc.text, Some(TextFilter::Contains("invoice".to_string())));
+        assert!(qc.has_filters());
+        assert!(qc.has_message_filters());
+    }
+
+    #[test]
+    fn can_match_text() {
+        let contains = TextFilter::Contains("invoice".to_string());
+        assert!(contains.matches("Here is the INVOICE"));
+        assert!(!contains.matches("Here is the receipt"));
+
+        let mut qc = QueryContext::default();
+        qc.set_text_regex(r"^Invoice #\d+$").unwrap();
+        let regex = qc.text.unwrap();
+        assert!(regex.matches("Invoice #123"));
+        assert!(!regex.matches("invoice #123"));
+    }
+
+    #[test]
+    fn cant_set_invalid_regex() {
+        let mut qc = QueryContext::default();
+        assert!(qc.set_text_regex("(unclosed").is_err());
+        assert!(!qc.has_filters());
+    }
+
+    #[test]
+    fn can_set_selected_services() {
+        let mut qc = QueryContext::default();
+        qc.set_selected_services("SMS, rcs").unwrap();
+
+        assert_eq!(
+            qc.selected_services,
+            Some(BTreeSet::from(["RCS", "SMS", "rcs"]))
+        );
+        assert!(qc.has_filters());
+    }
+
+    #[test]
+    fn cant_set_invalid_service() {
+        let mut qc = QueryContext::default();
+        assert!(qc.set_selected_services("sms,pager").is_err());
+        assert!(qc.selected_services.is_none());
+    }
+
+    #[test]
+    fn can_set_selected_mime_types() {
+        let mut qc = QueryContext::default();
+        qc.set_selected_mime_types("Image,video").unwrap();
+
+        assert_eq!(
+            qc.selected_mime_types,
+            Some(BTreeSet::from(["image", "video"]))
+        );
+    }
+
+    #[test]
+    fn cant_set_invalid_mime_type() {
+        let mut qc = QueryContext::default();
+        assert!(qc.set_selected_mime_types("image/png").is_err());
+    }
+
+    #[test]
+    fn can_set_selected_kinds_and_statuses() {
+        let mut qc = QueryContext::default();
+        qc.set_selected_kinds("shareplay,edited").unwrap();
+        qc.set_selected_statuses("deleted").unwrap();
+
+        assert_eq!(
+            qc.selected_kinds,
+            Some(BTreeSet::from([
+                MessageKind::Edited,
+                MessageKind::SharePlay
+            ]))
+        );
+        assert_eq!(
+            qc.selected_statuses,
+            Some(BTreeSet::from([MessageStatus::Deleted]))
+        );
+        assert!(qc.has_message_filters());
+    }
+
+    #[test]
+    fn cant_set_invalid_kind() {
+        let mut qc = QueryContext::default();
+        assert!(qc.set_selected_kinds("tapback").is_err());
+        assert!(qc.set_selected_statuses("read").is_err());
+    }
+
+    #[test]
+    fn can_set_expression() {
+        let mut qc = QueryContext::default();
+        qc.set_expression("from:me and has:attachment").unwrap();
+
+        assert!(qc.expression.is_some());
+        assert!(qc.has_message_filters());
+    }
+
+    #[test]
+    fn cant_set_invalid_expression() {
+        let mut qc = QueryContext::default();
+        assert!(qc.set_expression("from:me and").is_err());
+        assert!(!qc.has_filters());
+    }
+
+    #[test]
+    fn can_set_sender_filters() {
+        let mut qc = QueryContext::default();
+        qc.set_from_me(true);
+        qc.set_selected_sender_ids(BTreeSet::from([1, 2]));
+
+        assert!(qc.from_me);
+        assert_eq!(qc.selected_sender_ids, Some(BTreeSet::from([1, 2])));
+        assert!(qc.has_message_filters());
+    }
+}
+
 #[cfg(test)]
 mod sanitize_tests {
     use crate::util::query_context::QueryContext;
@@ -0,0 +1,230 @@
+/*!
+ Reads and writes the file that assigns display names to participants and titles to conversations.
+
+ Aliases make exports readable when no address book is available, i.e. when exporting on a Linux server.
+*/
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{read_to_string, write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use imessage_database::util::normalize::{Region, normalize_handle};
+
+use crate::app::error::RuntimeError;
+
+// MARK: Aliases
+/// Display names for participants and titles for conversations, read from a JSON file
+///
+/// Empty values are ignored, so a template written by `--dump-handles` can be filled in gradually.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Aliases {
+    /// Map of phone number or email address to a display name
+    #[serde(default)]
+    pub handles: BTreeMap<String, String>,
+    /// Map of `person_centric_id` to a display name, which applies to every handle that belongs to the person
+    #[serde(default)]
+    pub people: BTreeMap<String, String>,
+    /// Map of chat identifier to a conversation title
+    #[serde(default)]
+    pub chats: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    /// Read aliases from a JSON file, normalizing handles so they match regardless of format
+    pub fn from_path(path: &Path, region: &Region) -> Result<Self, RuntimeError> {
+        let aliases: Aliases = serde_json::from_str(&read_to_string(path)?).map_err(|why| {
+            RuntimeError::InvalidOptions(format!(
+                "Unable to read aliases from {}: {why}",
+                path.display()
+            ))
+        })?;
+        Ok(aliases.normalize(region))
+    }
+
+    /// Normalize every handle and chat identifier, dropping entries without a value
+    fn normalize(self, region: &Region) -> Self {
+        let normalize_keys = |map: BTreeMap<String, String>| {
+            map.into_iter()
+                .map(|(key, value)| (normalize_handle(&key, region), value.trim().to_string()))
+                .filter(|(_, value)| !value.is_empty())
+                .collect()
+        };
+        Aliases {
+            handles: normalize_keys(self.handles),
+            people: self
+                .people
+                .into_iter()
+                .map(|(key, value)| (key, value.trim().to_string()))
+                .filter(|(_, value)| !value.is_empty())
+                .collect(),
+            chats: normalize_keys(self.chats),
+        }
+    }
+
+    /// Map each participant to their alias
+    ///
+    /// Aliases for a `person_centric_id` take precedence over aliases for an individual handle.
+    /// Deduplicated participants list each of their handles separated by spaces, so every handle is tried in order.
+    pub fn resolve(
+        &self,
+        participants: &HashMap<i32, String>,
+        person_centric_ids: &HashMap<i32, String>,
+        region: &Region,
+    ) -> HashMap<i32, String> {
+        participants
+            .iter()
+            .filter_map(|(handle_id, handles)| {
+                person_centric_ids
+                    .get(handle_id)
+                    .and_then(|person| self.people.get(person))
+                    .or_else(|| {
+                        handles
+                            .split_whitespace()
+                            .find_map(|handle| self.handles.get(&normalize_handle(handle, region)))
+                    })
+                    .map(|name| (*handle_id, name.to_owned()))
+            })
+            .collect()
+    }
+
+    /// Get the title assigned to a conversation, if any
+    pub fn chat_title(&self, chat_identifier: &str, region: &Region) -> Option<&str> {
+        self.chats
+            .get(&normalize_handle(chat_identifier, region))
+            .map(String::as_str)
+    }
+
+    /// Write the aliases as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<(), RuntimeError> {
+        let json = serde_json::to_string_pretty(self).map_err(|why| {
+            RuntimeError::InvalidOptions(format!("Unable to serialize aliases: {why}"))
+        })?;
+        write(path, json)?;
+        Ok(())
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        env::temp_dir,
+        fs::{create_dir_all, write},
+    };
+
+    use imessage_database::util::normalize::Region;
+
+    use crate::app::aliases::Aliases;
+
+    fn fake_aliases() -> Aliases {
+        Aliases {
+            handles: BTreeMap::from([
+                ("(555) 867-5309".to_string(), "Jenny".to_string()),
+                ("Steve@Apple.com".to_string(), "Steve".to_string()),
+                ("+15551234567".to_string(), String::new()),
+            ]),
+            people: BTreeMap::from([("ABC-123".to_string(), "Tim".to_string())]),
+            chats: BTreeMap::from([("chat123456".to_string(), "Family".to_string())]),
+        }
+        .normalize(&Region::default())
+    }
+
+    #[test]
+    fn can_normalize() {
+        let aliases = fake_aliases();
+        assert_eq!(
+            aliases.handles,
+            BTreeMap::from([
+                ("+15558675309".to_string(), "Jenny".to_string()),
+                ("steve@apple.com".to_string(), "Steve".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn can_resolve_handles() {
+        let aliases = fake_aliases();
+        let mut participants = HashMap::new();
+        participants.insert(1, "+15558675309".to_string());
+        participants.insert(2, "+15550000000 steve@apple.com".to_string());
+        participants.insert(3, "+15551234567".to_string());
+
+        let resolved = aliases.resolve(&participants, &HashMap::new(), &Region::default());
+
+        assert_eq!(resolved.get(&1).map(String::as_str), Some("Jenny"));
+        assert_eq!(resolved.get(&2).map(String::as_str), Some("Steve"));
+        assert!(!resolved.contains_key(&3));
+    }
+
+    #[test]
+    fn can_resolve_person_centric_id() {
+        let aliases = fake_aliases();
+        let mut participants = HashMap::new();
+        participants.insert(1, "+15558675309".to_string());
+        let mut person_centric_ids = HashMap::new();
+        person_centric_ids.insert(1, "ABC-123".to_string());
+
+        let resolved = aliases.resolve(&participants, &person_centric_ids, &Region::default());
+
+        assert_eq!(resolved.get(&1).map(String::as_str), Some("Tim"));
+    }
+
+    #[test]
+    fn can_get_chat_title() {
+        let aliases = fake_aliases();
+        assert_eq!(
+            aliases.chat_title("chat123456", &Region::default()),
+            Some("Family")
+        );
+        assert_eq!(aliases.chat_title("chat654321", &Region::default()), None);
+    }
+
+    #[test]
+    fn can_read_partial_file() {
+        let dir = temp_dir().join("imessage-exporter-aliases");
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("aliases.json");
+        write(&path, r#"{"handles": {"5558675309": "Jenny"}}"#).unwrap();
+
+        let aliases = Aliases::from_path(&path, &Region::default()).unwrap();
+
+        assert_eq!(
+            aliases.handles.get("+15558675309").map(String::as_str),
+            Some("Jenny")
+        );
+        assert!(aliases.people.is_empty());
+        assert!(aliases.chats.is_empty());
+    }
+
+    #[test]
+    fn cant_read_invalid_file() {
+        let dir = temp_dir().join("imessage-exporter-aliases");
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("invalid.json");
+        write(&path, r#"{"names": {"5558675309": "Jenny"}}"#).unwrap();
+
+        assert!(Aliases::from_path(&path, &Region::default()).is_err());
+    }
+
+    #[test]
+    fn can_write_template() {
+        let dir = temp_dir().join("imessage-exporter-aliases");
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("template.json");
+
+        let aliases = Aliases {
+            handles: BTreeMap::from([("+15558675309".to_string(), String::new())]),
+            ..Default::default()
+        };
+        aliases.write(&path).unwrap();
+
+        let expected = "{\n  \"handles\": {\n    \"+15558675309\": \"\"\n  },\n  \"people\": {},\n  \"chats\": {}\n}";
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+    }
+}
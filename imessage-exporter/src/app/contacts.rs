@@ -0,0 +1,611 @@
+/*!
+ Resolves contact handles to the names stored in an address book.
+*/
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::Connection;
+
+use imessage_database::{
+    error::table::TableError,
+    tables::table::get_connection,
+    util::normalize::{Region, normalize_handle},
+};
+
+use crate::app::error::RuntimeError;
+
+// MARK: Constants
+/// Default location of the macOS address book, relative to the user's home directory
+pub const DEFAULT_PATH_ADDRESS_BOOK_MACOS: &str = "Library/Application Support/AddressBook";
+/// Name of the macOS address book database
+pub const ADDRESS_BOOK_MACOS: &str = "AddressBook-v22.abcddb";
+/// Name of the address book database in an iOS backup
+pub const ADDRESS_BOOK_IOS: &str = "AddressBook.sqlitedb";
+/// Directory that holds the address book for each account synced to macOS
+const SOURCES_DIR: &str = "Sources";
+
+/// Queries that read names, phone numbers, and emails from an address book database
+struct Schema {
+    /// Table that identifies this kind of address book
+    table: &'static str,
+    /// Selects the ID, first name, last name, organization, and nickname of every person
+    records: &'static str,
+    /// Selects the owner ID and value of every phone number
+    phone_numbers: &'static str,
+    /// Selects the owner ID and value of every email address
+    emails: &'static str,
+}
+
+/// Core Data store written by the macOS Contacts app
+const SCHEMA_MACOS: Schema = Schema {
+    table: "ZABCDRECORD",
+    records: "SELECT Z_PK, ZFIRSTNAME, ZLASTNAME, ZORGANIZATION, ZNICKNAME FROM ZABCDRECORD",
+    phone_numbers: "SELECT ZOWNER, ZFULLNUMBER FROM ZABCDPHONENUMBER",
+    emails: "SELECT ZOWNER, ZADDRESS FROM ZABCDEMAILADDRESS",
+};
+
+/// Database written by the iOS Contacts app; multi-values with property 3 are phone numbers and 4 are emails
+const SCHEMA_IOS: Schema = Schema {
+    table: "ABPerson",
+    records: "SELECT ROWID, First, Last, Organization, Nickname FROM ABPerson",
+    phone_numbers: "SELECT record_id, value FROM ABMultiValue WHERE property = 3",
+    emails: "SELECT record_id, value FROM ABMultiValue WHERE property = 4",
+};
+
+// MARK: Contact
+/// A person from an address book
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Contact {
+    /// The name to show in exports
+    pub name: String,
+    /// Every phone number listed for the person
+    pub phone_numbers: Vec<String>,
+    /// Every email address listed for the person
+    pub emails: Vec<String>,
+}
+
+impl Contact {
+    /// Build a name from its parts, preferring the person's full name over their organization or nickname
+    fn display_name(
+        first: Option<&str>,
+        last: Option<&str>,
+        organization: Option<&str>,
+        nickname: Option<&str>,
+    ) -> String {
+        let full_name = [first, last]
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        if full_name.is_empty() {
+            return organization
+                .or(nickname)
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_string();
+        }
+        full_name
+    }
+}
+
+// MARK: Contacts
+/// Maps phone numbers and email addresses to the names of the people they belong to
+#[derive(Debug, Default)]
+pub struct Contacts {
+    /// Map of normalized phone number or email address to a contact's name
+    names: HashMap<String, String>,
+    /// Region used to normalize phone numbers saved without a country code
+    region: Region,
+}
+
+impl Contacts {
+    /// Read contacts from a vCard file, an address book database, or a directory of address books
+    ///
+    /// Directories are searched for the macOS address book and the address book of every synced account.
+    pub fn from_path(path: &Path, region: &Region) -> Result<Self, RuntimeError> {
+        let mut contacts = vec![];
+
+        if path.is_dir() {
+            let books = address_books(path)?;
+            if books.is_empty() {
+                return Err(RuntimeError::InvalidOptions(format!(
+                    "No {ADDRESS_BOOK_MACOS} or {ADDRESS_BOOK_IOS} found in {}",
+                    path.display()
+                )));
+            }
+            for book in books {
+                contacts.extend(read_address_book(&get_connection(&book)?)?);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("vcf") || ext.eq_ignore_ascii_case("vcard"))
+        {
+            contacts.extend(read_vcard(&read_to_string(path)?));
+        } else {
+            contacts.extend(read_address_book(&get_connection(path)?)?);
+        }
+
+        Ok(Contacts::from_contacts(contacts, region))
+    }
+
+    /// Index a list of contacts by their phone numbers and email addresses
+    ///
+    /// If more than one contact lists the same handle, the first one wins.
+    pub fn from_contacts(contacts: Vec<Contact>, region: &Region) -> Self {
+        let mut out = Contacts {
+            names: HashMap::new(),
+            region: *region,
+        };
+        for contact in contacts {
+            if contact.name.is_empty() {
+                continue;
+            }
+            for handle in contact.phone_numbers.iter().chain(&contact.emails) {
+                let normalized = normalize_handle(handle, region);
+                if normalized.is_empty() {
+                    continue;
+                }
+                out.names
+                    .entry(normalized)
+                    .or_insert_with(|| contact.name.clone());
+            }
+        }
+        out
+    }
+
+    /// Get the name of the contact that owns a phone number or email address
+    pub fn name(&self, handle: &str) -> Option<&str> {
+        self.names
+            .get(&normalize_handle(handle, &self.region))
+            .map(String::as_str)
+    }
+
+    /// Map each participant to the name of their contact
+    ///
+    /// Deduplicated participants list each of their handles separated by spaces, so every handle is tried in order.
+    pub fn resolve(&self, participants: &HashMap<i32, String>) -> HashMap<i32, String> {
+        participants
+            .iter()
+            .filter_map(|(handle_id, handles)| {
+                handles
+                    .split_whitespace()
+                    .find_map(|handle| self.name(handle))
+                    .map(|name| (*handle_id, name.to_string()))
+            })
+            .collect()
+    }
+
+    /// Returns `true` if no contacts have a phone number or email address
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+// MARK: Address Book
+/// Find every address book database in a directory
+fn address_books(dir: &Path) -> Result<Vec<PathBuf>, RuntimeError> {
+    let mut books = vec![];
+    for name in [ADDRESS_BOOK_MACOS, ADDRESS_BOOK_IOS] {
+        let path = dir.join(name);
+        if path.is_file() {
+            books.push(path);
+        }
+    }
+
+    // macOS keeps a separate address book for each account, i.e. iCloud or Exchange
+    let sources = dir.join(SOURCES_DIR);
+    if sources.is_dir() {
+        let mut accounts: Vec<PathBuf> = sources
+            .read_dir()?
+            .flatten()
+            .map(|entry| entry.path().join(ADDRESS_BOOK_MACOS))
+            .filter(|path| path.is_file())
+            .collect();
+        accounts.sort();
+        books.extend(accounts);
+    }
+
+    Ok(books)
+}
+
+/// Read every contact from a macOS or iOS address book database
+fn read_address_book(db: &Connection) -> Result<Vec<Contact>, RuntimeError> {
+    for schema in [SCHEMA_MACOS, SCHEMA_IOS] {
+        let exists: bool = db
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [schema.table],
+                |row| row.get(0),
+            )
+            .map_err(TableError::QueryError)?;
+        if exists {
+            return read_schema(db, &schema).map_err(RuntimeError::DatabaseError);
+        }
+    }
+
+    Err(RuntimeError::InvalidOptions(format!(
+        "{} is not a macOS or iOS address book!",
+        db.path().unwrap_or_default()
+    )))
+}
+
+/// Read every contact using the queries for a specific address book layout
+fn read_schema(db: &Connection, schema: &Schema) -> Result<Vec<Contact>, TableError> {
+    // Ordered so contacts are indexed deterministically
+    let mut contacts: BTreeMap<i64, Contact> = BTreeMap::new();
+
+    let mut statement = db.prepare(schema.records)?;
+    let records = statement.query_map([], |row| {
+        let id: i64 = row.get(0)?;
+        let first: Option<String> = row.get(1)?;
+        let last: Option<String> = row.get(2)?;
+        let organization: Option<String> = row.get(3)?;
+        let nickname: Option<String> = row.get(4)?;
+        Ok((
+            id,
+            Contact::display_name(
+                first.as_deref(),
+                last.as_deref(),
+                organization.as_deref(),
+                nickname.as_deref(),
+            ),
+        ))
+    })?;
+    for record in records {
+        let (id, name) = record?;
+        contacts.insert(
+            id,
+            Contact {
+                name,
+                ..Default::default()
+            },
+        );
+    }
+
+    for (query, is_phone) in [(schema.phone_numbers, true), (schema.emails, false)] {
+        let mut statement = db.prepare(query)?;
+        let values = statement.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?;
+        for value in values {
+            if let (owner, Some(value)) = value?
+                && let Some(contact) = contacts.get_mut(&owner)
+            {
+                if is_phone {
+                    contact.phone_numbers.push(value);
+                } else {
+                    contact.emails.push(value);
+                }
+            }
+        }
+    }
+
+    Ok(contacts.into_values().collect())
+}
+
+// MARK: vCard
+/// Read every contact from the contents of a vCard file
+fn read_vcard(vcard: &str) -> Vec<Contact> {
+    let mut contacts = vec![];
+    let mut current: Option<Contact> = None;
+    // The structured `N` property is only used if there is no formatted `FN` property
+    let mut structured_name = String::new();
+
+    for line in unfold(vcard) {
+        let Some((property, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Drop parameters, i.e. `TEL;TYPE=CELL`, and groups, i.e. `item1.EMAIL`
+        let name = property.split(';').next().unwrap_or_default();
+        let name = name.rsplit('.').next().unwrap_or_default().to_uppercase();
+
+        match name.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VCARD") => {
+                current = Some(Contact::default());
+                structured_name.clear();
+            }
+            "END" if value.eq_ignore_ascii_case("VCARD") => {
+                if let Some(mut contact) = current.take() {
+                    if contact.name.is_empty() {
+                        contact.name = structured_name.clone();
+                    }
+                    contacts.push(contact);
+                }
+            }
+            _ => {
+                let Some(contact) = current.as_mut() else {
+                    continue;
+                };
+                match name.as_str() {
+                    "FN" => contact.name = unescape(value).trim().to_string(),
+                    "N" => {
+                        // Family; Given; Additional; Prefix; Suffix
+                        let parts: Vec<String> = split_unescaped(value);
+                        structured_name = Contact::display_name(
+                            parts.get(1).map(String::as_str),
+                            parts.first().map(String::as_str),
+                            None,
+                            None,
+                        );
+                    }
+                    "ORG" | "NICKNAME" if structured_name.is_empty() => {
+                        structured_name = split_unescaped(value)
+                            .into_iter()
+                            .next()
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string();
+                    }
+                    "TEL" => contact.phone_numbers.push(unescape(value)),
+                    "EMAIL" => contact.emails.push(unescape(value)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    contacts
+}
+
+/// Join lines that vCard folded by starting the continuation with a space or tab
+fn unfold(vcard: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in vcard.lines() {
+        match line.strip_prefix([' ', '\t']) {
+            Some(continuation) if !lines.is_empty() => {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(continuation);
+                }
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Split a structured vCard value on its unescaped semicolons
+fn split_unescaped(value: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => {
+                part.push(char);
+                if let Some(next) = chars.next() {
+                    part.push(next);
+                }
+            }
+            ';' => parts.push(unescape(&std::mem::take(&mut part))),
+            _ => part.push(char),
+        }
+    }
+    parts.push(unescape(&part));
+    parts
+}
+
+/// Remove vCard escapes from a value
+fn unescape(value: &str) -> String {
+    let mut out_s = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        if char == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => out_s.push(' '),
+                Some(next) => out_s.push(next),
+                None => {}
+            }
+        } else {
+            out_s.push(char);
+        }
+    }
+    out_s
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        env::temp_dir,
+        fs::{create_dir_all, write},
+    };
+
+    use rusqlite::Connection;
+
+    use crate::app::contacts::{
+        Contact, Contacts, SCHEMA_IOS, SCHEMA_MACOS, read_schema, read_vcard,
+    };
+    use imessage_database::util::normalize::Region;
+
+    fn fake_contacts() -> Contacts {
+        Contacts::from_contacts(
+            vec![
+                Contact {
+                    name: "Jane Appleseed".to_string(),
+                    phone_numbers: vec!["+1 (555) 123-4567".to_string()],
+                    emails: vec!["Jane@Example.com".to_string()],
+                },
+                Contact {
+                    name: "John Doe".to_string(),
+                    phone_numbers: vec!["555.987.6543".to_string()],
+                    emails: vec![],
+                },
+            ],
+            &Region::default(),
+        )
+    }
+
+    #[test]
+    fn can_get_name() {
+        let contacts = fake_contacts();
+        assert_eq!(contacts.name("+15551234567"), Some("Jane Appleseed"));
+        assert_eq!(contacts.name("jane@example.com"), Some("Jane Appleseed"));
+        assert_eq!(contacts.name("+15550001111"), None);
+    }
+
+    #[test]
+    fn can_get_name_without_country_code() {
+        let contacts = fake_contacts();
+        assert_eq!(contacts.name("5551234567"), Some("Jane Appleseed"));
+    }
+
+    #[test]
+    fn can_get_name_in_other_region() {
+        let contacts = Contacts::from_contacts(
+            vec![Contact {
+                name: "Jane Appleseed".to_string(),
+                phone_numbers: vec!["07700 900123".to_string()],
+                emails: vec![],
+            }],
+            &Region::from_cli("GB").unwrap(),
+        );
+        assert_eq!(contacts.name("+447700900123"), Some("Jane Appleseed"));
+    }
+
+    #[test]
+    fn can_get_name_with_country_code() {
+        let contacts = fake_contacts();
+        assert_eq!(contacts.name("+15559876543"), Some("John Doe"));
+    }
+
+    #[test]
+    fn can_resolve_participants() {
+        let contacts = fake_contacts();
+        let mut participants = HashMap::new();
+        participants.insert(1, "+15551234567".to_string());
+        // Deduplicated handles list every ID
+        participants.insert(2, "5550000000 jane@example.com".to_string());
+        participants.insert(3, "+15550000000".to_string());
+
+        let resolved = contacts.resolve(&participants);
+
+        assert_eq!(resolved.get(&1).map(String::as_str), Some("Jane Appleseed"));
+        assert_eq!(resolved.get(&2).map(String::as_str), Some("Jane Appleseed"));
+        assert!(!resolved.contains_key(&3));
+    }
+
+    #[test]
+    fn can_read_vcard() {
+        let vcard = concat!(
+            "BEGIN:VCARD\r\n",
+            "VERSION:3.0\r\n",
+            "N:Appleseed;Jane;;;\r\n",
+            "FN:Jane Appleseed\r\n",
+            "item1.TEL;type=CELL;type=pref:+1 (555) 123-4567\r\n",
+            "EMAIL;type=INTERNET:jane@exam\r\n",
+            " ple.com\r\n",
+            "END:VCARD\r\n",
+            "BEGIN:VCARD\r\n",
+            "VERSION:3.0\r\n",
+            "N:Doe;John;;;\r\n",
+            "TEL:555-987-6543\r\n",
+            "END:VCARD\r\n",
+            "BEGIN:VCARD\r\n",
+            "VERSION:3.0\r\n",
+            "ORG:Example\\, Inc.;Sales\r\n",
+            "TEL:555-000-0000\r\n",
+            "END:VCARD\r\n",
+        );
+
+        let expected = vec![
+            Contact {
+                name: "Jane Appleseed".to_string(),
+                phone_numbers: vec!["+1 (555) 123-4567".to_string()],
+                emails: vec!["jane@example.com".to_string()],
+            },
+            Contact {
+                name: "John Doe".to_string(),
+                phone_numbers: vec!["555-987-6543".to_string()],
+                emails: vec![],
+            },
+            Contact {
+                name: "Example, Inc.".to_string(),
+                phone_numbers: vec!["555-000-0000".to_string()],
+                emails: vec![],
+            },
+        ];
+
+        assert_eq!(read_vcard(vcard), expected);
+    }
+
+    #[test]
+    fn can_read_macos_address_book() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(concat!(
+            "CREATE TABLE ZABCDRECORD (Z_PK INTEGER, ZFIRSTNAME TEXT, ZLASTNAME TEXT, ZORGANIZATION TEXT, ZNICKNAME TEXT);",
+            "CREATE TABLE ZABCDPHONENUMBER (ZOWNER INTEGER, ZFULLNUMBER TEXT);",
+            "CREATE TABLE ZABCDEMAILADDRESS (ZOWNER INTEGER, ZADDRESS TEXT);",
+            "INSERT INTO ZABCDRECORD VALUES (1, 'Jane', 'Appleseed', NULL, NULL), (2, NULL, NULL, 'Example Inc.', NULL);",
+            "INSERT INTO ZABCDPHONENUMBER VALUES (1, '+15551234567'), (2, '5550000000');",
+            "INSERT INTO ZABCDEMAILADDRESS VALUES (1, 'jane@example.com');",
+        ))
+        .unwrap();
+
+        let expected = vec![
+            Contact {
+                name: "Jane Appleseed".to_string(),
+                phone_numbers: vec!["+15551234567".to_string()],
+                emails: vec!["jane@example.com".to_string()],
+            },
+            Contact {
+                name: "Example Inc.".to_string(),
+                phone_numbers: vec!["5550000000".to_string()],
+                emails: vec![],
+            },
+        ];
+
+        assert_eq!(read_schema(&db, &SCHEMA_MACOS).unwrap(), expected);
+    }
+
+    #[test]
+    fn can_read_ios_address_book() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(concat!(
+            "CREATE TABLE ABPerson (ROWID INTEGER, First TEXT, Last TEXT, Organization TEXT, Nickname TEXT);",
+            "CREATE TABLE ABMultiValue (record_id INTEGER, property INTEGER, value TEXT);",
+            "INSERT INTO ABPerson VALUES (1, 'Jane', NULL, NULL, 'Janie');",
+            "INSERT INTO ABMultiValue VALUES (1, 3, '+15551234567'), (1, 4, 'jane@example.com'), (1, 5, 'https://example.com');",
+        ))
+        .unwrap();
+
+        let expected = vec![Contact {
+            name: "Jane".to_string(),
+            phone_numbers: vec!["+15551234567".to_string()],
+            emails: vec!["jane@example.com".to_string()],
+        }];
+
+        assert_eq!(read_schema(&db, &SCHEMA_IOS).unwrap(), expected);
+    }
+
+    #[test]
+    fn can_read_contacts_from_vcard_file() {
+        let dir = temp_dir().join("imessage-exporter-contacts");
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("contacts.vcf");
+        write(
+            &path,
+            "BEGIN:VCARD\nVERSION:3.0\nFN:Jenny\nTEL:555-867-5309\nEND:VCARD\n",
+        )
+        .unwrap();
+
+        let contacts = Contacts::from_path(&path, &Region::default()).unwrap();
+
+        assert_eq!(contacts.name("+15558675309"), Some("Jenny"));
+    }
+
+    #[test]
+    fn cant_read_contacts_from_empty_dir() {
+        let dir = temp_dir().join("imessage-exporter-contacts-empty");
+        create_dir_all(&dir).unwrap();
+
+        assert!(Contacts::from_path(&dir, &Region::default()).is_err());
+    }
+}
@@ -21,6 +21,7 @@ pub enum RuntimeError {
     BackupError(BackupError),
     NotEnoughAvailableSpace(u64, u64),
     FileNameError,
+    SerializationError(serde_json::Error),
 }
 
 impl Display for RuntimeError {
@@ -39,6 +40,7 @@ impl Display for RuntimeError {
             }
             RuntimeError::BackupError(why) => write!(fmt, "{why}"),
             RuntimeError::FileNameError => write!(fmt, "Invalid file name!"),
+            RuntimeError::SerializationError(why) => write!(fmt, "Unable to serialize: {why}"),
         }
     }
 }
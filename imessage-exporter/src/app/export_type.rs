@@ -13,6 +13,20 @@ pub enum ExportType {
     Txt,
     /// JSON file export
     Json,
+    /// Newline-delimited JSON export, one message object per line
+    NdJson,
+    /// Markdown file export
+    Markdown,
+    /// Comma-separated tables of messages, attachments, chats, and handles
+    Csv,
+    /// Tab-separated tables of messages, attachments, chats, and handles
+    Tsv,
+    /// Normalized SQLite database with full text search
+    Sqlite,
+    /// Mailbox of emails, one file per conversation
+    Mbox,
+    /// Android SMS Backup & Restore XML
+    SmsBackup,
 }
 
 impl ExportType {
@@ -22,6 +36,13 @@ impl ExportType {
             "txt" => Some(Self::Txt),
             "html" => Some(Self::Html),
             "json" => Some(Self::Json),
+            "ndjson" | "jsonl" => Some(Self::NdJson),
+            "md" | "markdown" => Some(Self::Markdown),
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            "sqlite" | "db" => Some(Self::Sqlite),
+            "mbox" => Some(Self::Mbox),
+            "xml" | "android" => Some(Self::SmsBackup),
             _ => None,
         }
     }
@@ -32,6 +53,13 @@ impl ExportType {
             ExportType::Html => ".html",
             ExportType::Txt => ".txt",
             ExportType::Json => ".json",
+            ExportType::NdJson => ".ndjson",
+            ExportType::Markdown => ".md",
+            ExportType::Csv => ".csv",
+            ExportType::Tsv => ".tsv",
+            ExportType::Sqlite => ".sqlite",
+            ExportType::Mbox => ".mbox",
+            ExportType::SmsBackup => ".xml",
         }
     }
 }
@@ -42,6 +70,13 @@ impl Display for ExportType {
             ExportType::Txt => write!(fmt, "txt"),
             ExportType::Html => write!(fmt, "html"),
             ExportType::Json => write!(fmt, "json"),
+            ExportType::NdJson => write!(fmt, "ndjson"),
+            ExportType::Markdown => write!(fmt, "md"),
+            ExportType::Csv => write!(fmt, "csv"),
+            ExportType::Tsv => write!(fmt, "tsv"),
+            ExportType::Sqlite => write!(fmt, "sqlite"),
+            ExportType::Mbox => write!(fmt, "mbox"),
+            ExportType::SmsBackup => write!(fmt, "xml"),
         }
     }
 }
@@ -73,10 +108,92 @@ mod tests {
         assert!(matches!(ExportType::from_cli("tXt"), Some(ExportType::Txt)));
     }
 
+    #[test]
+    fn can_parse_json_any_case() {
+        assert!(matches!(
+            ExportType::from_cli("json"),
+            Some(ExportType::Json)
+        ));
+        assert!(matches!(
+            ExportType::from_cli("JSON"),
+            Some(ExportType::Json)
+        ));
+        assert!(matches!(
+            ExportType::from_cli("JsOn"),
+            Some(ExportType::Json)
+        ));
+    }
+
+    #[test]
+    fn can_parse_ndjson_aliases() {
+        assert!(matches!(
+            ExportType::from_cli("ndjson"),
+            Some(ExportType::NdJson)
+        ));
+        assert!(matches!(
+            ExportType::from_cli("JSONL"),
+            Some(ExportType::NdJson)
+        ));
+    }
+
+    #[test]
+    fn can_parse_markdown_aliases() {
+        assert!(matches!(
+            ExportType::from_cli("md"),
+            Some(ExportType::Markdown)
+        ));
+        assert!(matches!(
+            ExportType::from_cli("Markdown"),
+            Some(ExportType::Markdown)
+        ));
+    }
+
+    #[test]
+    fn can_parse_csv_any_case() {
+        assert!(matches!(ExportType::from_cli("csv"), Some(ExportType::Csv)));
+        assert!(matches!(ExportType::from_cli("CSV"), Some(ExportType::Csv)));
+        assert!(matches!(ExportType::from_cli("TsV"), Some(ExportType::Tsv)));
+    }
+
+    #[test]
+    fn can_parse_sqlite_aliases() {
+        assert!(matches!(
+            ExportType::from_cli("sqlite"),
+            Some(ExportType::Sqlite)
+        ));
+        assert!(matches!(
+            ExportType::from_cli("DB"),
+            Some(ExportType::Sqlite)
+        ));
+    }
+
+    #[test]
+    fn can_parse_mbox_any_case() {
+        assert!(matches!(
+            ExportType::from_cli("mbox"),
+            Some(ExportType::Mbox)
+        ));
+        assert!(matches!(
+            ExportType::from_cli("MBOX"),
+            Some(ExportType::Mbox)
+        ));
+    }
+
+    #[test]
+    fn can_parse_sms_backup_any_case() {
+        assert!(matches!(
+            ExportType::from_cli("xml"),
+            Some(ExportType::SmsBackup)
+        ));
+        assert!(matches!(
+            ExportType::from_cli("Android"),
+            Some(ExportType::SmsBackup)
+        ));
+    }
+
     #[test]
     fn cant_parse_invalid() {
         assert!(ExportType::from_cli("pdf").is_none());
-        assert!(ExportType::from_cli("json").is_none());
         assert!(ExportType::from_cli("").is_none());
     }
 }
@@ -1,7 +1,10 @@
+pub mod aliases;
 pub mod compatibility;
+pub mod contacts;
 pub mod error;
 pub mod export_type;
 pub mod options;
+pub mod pagination;
 pub mod progress;
 pub mod runtime;
 pub mod sanitizers;
@@ -10,20 +10,28 @@ use imessage_database::{
     tables::{attachment::DEFAULT_ATTACHMENT_ROOT, table::DEFAULT_PATH_IOS},
     util::{
         dirs::{default_db_path, home},
+        normalize::{REGIONS, Region},
         platform::Platform,
-        query_context::QueryContext,
+        query_context::{
+            QueryContext, SUPPORTED_KINDS, SUPPORTED_MIME_TYPES, SUPPORTED_SERVICES,
+            SUPPORTED_STATUSES,
+        },
     },
 };
 
 use crate::app::{
     compatibility::attachment_manager::{AttachmentManager, AttachmentManagerMode},
+    contacts::{ADDRESS_BOOK_IOS, ADDRESS_BOOK_MACOS, DEFAULT_PATH_ADDRESS_BOOK_MACOS},
     error::RuntimeError,
     export_type::ExportType,
+    pagination::Pagination,
 };
 
 // MARK: Constants
 /// Default export directory name
 pub const DEFAULT_OUTPUT_DIR: &str = "imessage_export";
+/// Default size limit, in megabytes, for attachments embedded into standalone HTML exports
+pub const DEFAULT_EMBED_LIMIT_MB: u64 = 25;
 
 // CLI Arg Names
 pub const OPTION_DB_PATH: &str = "db-path";
@@ -41,9 +49,30 @@ pub const OPTION_BYPASS_FREE_SPACE_CHECK: &str = "ignore-disk-warning";
 pub const OPTION_USE_CALLER_ID: &str = "use-caller-id";
 pub const OPTION_CONVERSATION_FILTER: &str = "conversation-filter";
 pub const OPTION_CLEARTEXT_PASSWORD: &str = "cleartext-password";
+pub const OPTION_STDOUT: &str = "stdout";
+pub const OPTION_PRINT_SCHEMA: &str = "print-schema";
+pub const OPTION_NEST_REPLIES: &str = "nest-replies";
+pub const OPTION_STANDALONE: &str = "standalone";
+pub const OPTION_EMBED_LIMIT: &str = "embed-limit";
+pub const OPTION_PAGINATE: &str = "paginate";
+pub const OPTION_SEARCH: &str = "search";
+pub const OPTION_TEMPLATE_DIR: &str = "template-dir";
+pub const OPTION_CONTACTS: &str = "contacts";
+pub const OPTION_REGION: &str = "region";
+pub const OPTION_ALIASES: &str = "aliases";
+pub const OPTION_DUMP_HANDLES: &str = "dump-handles";
+pub const OPTION_TEXT: &str = "text";
+pub const OPTION_TEXT_REGEX: &str = "text-regex";
+pub const OPTION_SENDER_FILTER: &str = "from";
+pub const OPTION_SERVICE: &str = "service";
+pub const OPTION_HAS_ATTACHMENTS: &str = "has-attachments";
+pub const OPTION_ATTACHMENT_TYPE: &str = "attachment-type";
+pub const OPTION_KIND: &str = "kind";
+pub const OPTION_STATUS: &str = "status";
+pub const OPTION_WHERE: &str = "where";
 
 // Other CLI Text
-pub const SUPPORTED_FILE_TYPES: &str = "txt, html, json";
+pub const SUPPORTED_FILE_TYPES: &str = "txt, html, json, ndjson, md, csv, tsv, sqlite, mbox, xml";
 pub const SUPPORTED_PLATFORMS: &str = "macOS, iOS";
 pub const SUPPORTED_ATTACHMENT_MANAGER_MODES: &str = "clone, basic, full, disabled";
 pub const ABOUT: &str = concat!(
@@ -81,8 +110,30 @@ pub struct Options {
     pub ignore_disk_space: bool,
     /// An optional filter for conversation participants
     pub conversation_filter: Option<String>,
+    /// An optional filter for message senders, excluding the database owner
+    pub sender_filter: Option<String>,
     /// An optional password for encrypted backups
     pub cleartext_password: Option<String>,
+    /// If true, stream all exported records to stdout instead of per-chat files
+    pub stdout: bool,
+    /// If true, write threaded replies inside the JSON record of the message they reply to
+    pub nest_replies: bool,
+    /// If set, embed attachments up to this many bytes so each HTML file stands alone
+    pub standalone: Option<u64>,
+    /// If set, split each HTML conversation into pages
+    pub paginate: Option<Pagination>,
+    /// If true, write a page that searches every exported HTML message offline
+    pub search: bool,
+    /// Directory of templates and styles that customize HTML exports
+    pub template_dir: Option<PathBuf>,
+    /// Address book or vCard file used to show contact names instead of phone numbers and emails
+    pub contacts: Option<PathBuf>,
+    /// Region used to add a country code to phone numbers saved without one
+    pub region: Region,
+    /// JSON file that assigns display names to participants and titles to conversations
+    pub aliases: Option<PathBuf>,
+    /// If set, write a template alias file listing every handle here instead of exporting
+    pub dump_handles: Option<PathBuf>,
 }
 
 // MARK: Validation
@@ -103,6 +154,31 @@ impl Options {
         let ignore_disk_space = args.get_flag(OPTION_BYPASS_FREE_SPACE_CHECK);
         let conversation_filter: Option<&String> = args.get_one(OPTION_CONVERSATION_FILTER);
         let cleartext_password: Option<&String> = args.get_one(OPTION_CLEARTEXT_PASSWORD);
+        let stdout = args.get_flag(OPTION_STDOUT);
+        let nest_replies = args.get_flag(OPTION_NEST_REPLIES);
+        let standalone = args.get_flag(OPTION_STANDALONE);
+        let embed_limit: Option<&String> = args.get_one(OPTION_EMBED_LIMIT);
+        let paginate: Option<&String> = args.get_one(OPTION_PAGINATE);
+        let search = args.get_flag(OPTION_SEARCH);
+        let template_dir: Option<&String> = args.get_one(OPTION_TEMPLATE_DIR);
+        let region: Option<&String> = args.get_one(OPTION_REGION);
+        let aliases: Option<&String> = args.get_one(OPTION_ALIASES);
+        let dump_handles: Option<&String> = args.get_one(OPTION_DUMP_HANDLES);
+        let text: Option<&String> = args.get_one(OPTION_TEXT);
+        let text_regex: Option<&String> = args.get_one(OPTION_TEXT_REGEX);
+        let sender_filter: Option<&String> = args.get_one(OPTION_SENDER_FILTER);
+        let service: Option<&String> = args.get_one(OPTION_SERVICE);
+        let has_attachments = args.get_flag(OPTION_HAS_ATTACHMENTS);
+        let attachment_type: Option<&String> = args.get_one(OPTION_ATTACHMENT_TYPE);
+        let kind: Option<&String> = args.get_one(OPTION_KIND);
+        let status: Option<&String> = args.get_one(OPTION_STATUS);
+        let where_expression: Option<&String> = args.get_one(OPTION_WHERE);
+        // The address book path is optional, so the flag alone selects the default macOS address book
+        let contacts: Option<String> = args.contains_id(OPTION_CONTACTS).then(|| {
+            args.get_one::<String>(OPTION_CONTACTS)
+                .cloned()
+                .unwrap_or_else(|| format!("{}/{DEFAULT_PATH_ADDRESS_BOOK_MACOS}", home()))
+        });
 
         // Build the export type
         let export_type: Option<ExportType> = match export_file_type {
@@ -125,6 +201,22 @@ impl Options {
                 (custom_name.is_some(), OPTION_CUSTOM_NAME),
                 (use_caller_id, OPTION_USE_CALLER_ID),
                 (conversation_filter.is_some(), OPTION_CONVERSATION_FILTER),
+                (stdout, OPTION_STDOUT),
+                (nest_replies, OPTION_NEST_REPLIES),
+                (standalone, OPTION_STANDALONE),
+                (paginate.is_some(), OPTION_PAGINATE),
+                (search, OPTION_SEARCH),
+                (template_dir.is_some(), OPTION_TEMPLATE_DIR),
+                (contacts.is_some(), OPTION_CONTACTS),
+                (text.is_some(), OPTION_TEXT),
+                (text_regex.is_some(), OPTION_TEXT_REGEX),
+                (sender_filter.is_some(), OPTION_SENDER_FILTER),
+                (service.is_some(), OPTION_SERVICE),
+                (has_attachments, OPTION_HAS_ATTACHMENTS),
+                (attachment_type.is_some(), OPTION_ATTACHMENT_TYPE),
+                (kind.is_some(), OPTION_KIND),
+                (status.is_some(), OPTION_STATUS),
+                (where_expression.is_some(), OPTION_WHERE),
             ];
             for (set, opt) in format_deps {
                 if set {
@@ -146,6 +238,24 @@ impl Options {
             (use_caller_id, OPTION_USE_CALLER_ID),
             (custom_name.is_some(), OPTION_CUSTOM_NAME),
             (conversation_filter.is_some(), OPTION_CONVERSATION_FILTER),
+            (stdout, OPTION_STDOUT),
+            (nest_replies, OPTION_NEST_REPLIES),
+            (standalone, OPTION_STANDALONE),
+            (paginate.is_some(), OPTION_PAGINATE),
+            (search, OPTION_SEARCH),
+            (template_dir.is_some(), OPTION_TEMPLATE_DIR),
+            (contacts.is_some(), OPTION_CONTACTS),
+            (aliases.is_some(), OPTION_ALIASES),
+            (dump_handles.is_some(), OPTION_DUMP_HANDLES),
+            (text.is_some(), OPTION_TEXT),
+            (text_regex.is_some(), OPTION_TEXT_REGEX),
+            (sender_filter.is_some(), OPTION_SENDER_FILTER),
+            (service.is_some(), OPTION_SERVICE),
+            (has_attachments, OPTION_HAS_ATTACHMENTS),
+            (attachment_type.is_some(), OPTION_ATTACHMENT_TYPE),
+            (kind.is_some(), OPTION_KIND),
+            (status.is_some(), OPTION_STATUS),
+            (where_expression.is_some(), OPTION_WHERE),
         ];
         for (set, opt) in diag_conflicts {
             if diagnostic && set {
@@ -155,6 +265,13 @@ impl Options {
             }
         }
 
+        // Prevent text vs. text_regex collision
+        if text.is_some() && text_regex.is_some() {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "--{OPTION_TEXT} is enabled; --{OPTION_TEXT_REGEX} is disallowed"
+            )));
+        }
+
         // Prevent custom_name vs. use_caller_id collision
         if custom_name.is_some() && use_caller_id {
             return Err(RuntimeError::InvalidOptions(format!(
@@ -162,6 +279,139 @@ impl Options {
             )));
         }
 
+        // Only line-oriented records can be combined into a single stream
+        if stdout && !matches!(export_type, Some(ExportType::NdJson)) {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "--{OPTION_STDOUT} is enabled, which requires --{OPTION_EXPORT_TYPE} {}",
+                ExportType::NdJson
+            )));
+        }
+
+        // Only JSON records can contain other messages
+        if nest_replies && !matches!(export_type, Some(ExportType::Json | ExportType::NdJson)) {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "--{OPTION_NEST_REPLIES} is enabled, which requires --{OPTION_EXPORT_TYPE} {} or {}",
+                ExportType::Json,
+                ExportType::NdJson
+            )));
+        }
+
+        // Only HTML documents can embed their attachments
+        if standalone && !matches!(export_type, Some(ExportType::Html)) {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "--{OPTION_STANDALONE} is enabled, which requires --{OPTION_EXPORT_TYPE} {}",
+                ExportType::Html
+            )));
+        }
+
+        // Only HTML documents can be searched from a browser
+        if search && !matches!(export_type, Some(ExportType::Html)) {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "--{OPTION_SEARCH} is enabled, which requires --{OPTION_EXPORT_TYPE} {}",
+                ExportType::Html
+            )));
+        }
+
+        // Only HTML documents use templates
+        if template_dir.is_some() && !matches!(export_type, Some(ExportType::Html)) {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "--{OPTION_TEMPLATE_DIR} is enabled, which requires --{OPTION_EXPORT_TYPE} {}",
+                ExportType::Html
+            )));
+        }
+
+        // Validate that the template directory exists, if provided
+        if let Some(path) = template_dir
+            && !PathBuf::from(path).is_dir()
+        {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "Supplied {OPTION_TEMPLATE_DIR} `{path}` is not a directory!"
+            )));
+        }
+
+        // Validate that the address book exists, if provided
+        if let Some(path) = &contacts
+            && !PathBuf::from(path).exists()
+        {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "Supplied {OPTION_CONTACTS} `{path}` does not exist!"
+            )));
+        }
+
+        // Validate that the alias file exists, if provided
+        if let Some(path) = aliases
+            && !PathBuf::from(path).is_file()
+        {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "Supplied {OPTION_ALIASES} `{path}` is not a file!"
+            )));
+        }
+
+        // Dumping handles replaces the export, and must not overwrite an alias file that was already filled in
+        if let Some(path) = dump_handles {
+            if export_file_type.is_some() {
+                return Err(RuntimeError::InvalidOptions(format!(
+                    "--{OPTION_DUMP_HANDLES} is enabled; --{OPTION_EXPORT_TYPE} is disallowed"
+                )));
+            }
+            if PathBuf::from(path).exists() {
+                return Err(RuntimeError::InvalidOptions(format!(
+                    "Supplied {OPTION_DUMP_HANDLES} `{path}` already exists!"
+                )));
+            }
+        }
+
+        // Prevent embed_limit from being set without standalone
+        if embed_limit.is_some() && !standalone {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "Option --{OPTION_EMBED_LIMIT} is enabled, which requires --{OPTION_STANDALONE}"
+            )));
+        }
+
+        // Build the size limit for embedded attachments, in bytes
+        let standalone = match (standalone, embed_limit) {
+            (true, Some(limit)) => Some(
+                limit
+                    .parse::<u64>()
+                    .map_err(|_| {
+                        RuntimeError::InvalidOptions(format!(
+                            "{limit} is not a valid embed limit! Must be a whole number of megabytes"
+                        ))
+                    })?
+                    .saturating_mul(1024 * 1024),
+            ),
+            (true, None) => Some(DEFAULT_EMBED_LIMIT_MB * 1024 * 1024),
+            (false, _) => None,
+        };
+
+        // Build the pagination, which only HTML documents can use
+        let paginate = match paginate {
+            Some(pagination_str) => {
+                if !matches!(export_type, Some(ExportType::Html)) {
+                    return Err(RuntimeError::InvalidOptions(format!(
+                        "--{OPTION_PAGINATE} is enabled, which requires --{OPTION_EXPORT_TYPE} {}",
+                        ExportType::Html
+                    )));
+                }
+                Some(Pagination::from_cli(pagination_str).ok_or(RuntimeError::InvalidOptions(format!(
+                    "{pagination_str} is not a valid pagination! Must be a number of messages or `{}`",
+                    Pagination::Month
+                )))?)
+            }
+            None => None,
+        };
+
+        // Build the region used to normalize phone numbers
+        let region = match region {
+            Some(region_str) => {
+                Region::from_cli(region_str).ok_or(RuntimeError::InvalidOptions(format!(
+                    "{region_str} is not a valid region! Must be one of <{}>",
+                    supported_regions()
+                )))?
+            }
+            None => Region::default(),
+        };
+
         // Build query context
         let mut query_context = QueryContext::default();
         if let Some(start) = start_date
@@ -174,6 +424,50 @@ impl Options {
         {
             return Err(RuntimeError::InvalidOptions(format!("{why}")));
         }
+        if let Some(text) = text {
+            query_context.set_text(text);
+        }
+        if let Some(pattern) = text_regex
+            && let Err(why) = query_context.set_text_regex(pattern)
+        {
+            return Err(RuntimeError::InvalidOptions(format!("{why}")));
+        }
+        if let Some(services) = service
+            && let Err(why) = query_context.set_selected_services(services)
+        {
+            return Err(RuntimeError::InvalidOptions(format!("{why}")));
+        }
+        query_context.set_has_attachments(has_attachments);
+        if let Some(mime_types) = attachment_type
+            && let Err(why) = query_context.set_selected_mime_types(mime_types)
+        {
+            return Err(RuntimeError::InvalidOptions(format!("{why}")));
+        }
+        if let Some(kinds) = kind
+            && let Err(why) = query_context.set_selected_kinds(kinds)
+        {
+            return Err(RuntimeError::InvalidOptions(format!("{why}")));
+        }
+        if let Some(statuses) = status
+            && let Err(why) = query_context.set_selected_statuses(statuses)
+        {
+            return Err(RuntimeError::InvalidOptions(format!("{why}")));
+        }
+        if let Some(expression) = where_expression
+            && let Err(why) = query_context.set_expression(expression)
+        {
+            return Err(RuntimeError::InvalidOptions(format!("{why}")));
+        }
+
+        // `me` selects the database owner, and any other senders are resolved to handles once the database is loaded
+        let (me, senders): (Vec<&str>, Vec<&str>) = sender_filter
+            .into_iter()
+            .flat_map(|filter| filter.split(','))
+            .map(str::trim)
+            .filter(|sender| !sender.is_empty())
+            .partition(|sender| sender.eq_ignore_ascii_case("me"));
+        query_context.set_from_me(!me.is_empty());
+        let sender_filter = (!senders.is_empty()).then(|| senders.join(","));
 
         // We have to allocate a PathBuf here because it can be created from data owned by this function in the default state
         let db_path = match user_path {
@@ -243,7 +537,18 @@ impl Options {
             platform,
             ignore_disk_space,
             conversation_filter: conversation_filter.cloned(),
+            sender_filter,
             cleartext_password: cleartext_password.cloned(),
+            stdout,
+            nest_replies,
+            standalone,
+            paginate,
+            search,
+            template_dir: template_dir.map(PathBuf::from),
+            contacts: contacts.map(PathBuf::from),
+            region,
+            aliases: aliases.map(PathBuf::from),
+            dump_handles: dump_handles.map(PathBuf::from),
         })
     }
 
@@ -430,6 +735,186 @@ fn get_command() -> Command {
                 .display_order(14)
                 .value_name("password"),
         )
+        .arg(
+            Arg::new(OPTION_STDOUT)
+                .long(OPTION_STDOUT)
+                .help(format!("Write a single combined stream of records to stdout instead of one file per conversation\nRequires --{OPTION_EXPORT_TYPE} {}\n", ExportType::NdJson))
+                .action(ArgAction::SetTrue)
+                .display_order(15)
+        )
+        .arg(
+            Arg::new(OPTION_PRINT_SCHEMA)
+                .long(OPTION_PRINT_SCHEMA)
+                .help(format!("Print the JSON Schema that exported files conform to and exit\nUses the schema for --{OPTION_EXPORT_TYPE} {} if it is selected, otherwise {}\n", ExportType::NdJson, ExportType::Json))
+                .action(ArgAction::SetTrue)
+                .display_order(16)
+        )
+        .arg(
+            Arg::new(OPTION_NEST_REPLIES)
+                .long(OPTION_NEST_REPLIES)
+                .help(format!("Write threaded replies inside the record of the message they reply to instead of as separate records\nReplies to messages that are not exported are still written as separate records\nRequires --{OPTION_EXPORT_TYPE} {} or {}\n", ExportType::Json, ExportType::NdJson))
+                .action(ArgAction::SetTrue)
+                .display_order(17)
+        )
+        .arg(
+            Arg::new(OPTION_STANDALONE)
+                .long(OPTION_STANDALONE)
+                .help(format!("Embed images, audio, and video into each HTML file so it can be shared on its own
+Attachments larger than --{OPTION_EMBED_LIMIT} are linked instead
+Requires --{OPTION_EXPORT_TYPE} {}
+", ExportType::Html))
+                .action(ArgAction::SetTrue)
+                .display_order(18)
+        )
+        .arg(
+            Arg::new(OPTION_EMBED_LIMIT)
+                .long(OPTION_EMBED_LIMIT)
+                .help(format!("The largest attachment, in megabytes, to embed into a standalone HTML file
+If omitted, the default is {DEFAULT_EMBED_LIMIT_MB}
+"))
+                .display_order(19)
+                .value_name("megabytes"),
+        )
+        .arg(
+            Arg::new(OPTION_PAGINATE)
+                .long(OPTION_PAGINATE)
+                .help(format!("Split each HTML conversation into pages, linked from a table of contents
+Pass a number of messages per page, or `{}` for one page per calendar month
+Requires --{OPTION_EXPORT_TYPE} {}
+", Pagination::Month, ExportType::Html))
+                .display_order(20)
+                .value_name("messages|month"),
+        )
+        .arg(
+            Arg::new(OPTION_SEARCH)
+                .long(OPTION_SEARCH)
+                .help(format!("Write a search page that finds messages across every exported conversation without a network connection
+Requires --{OPTION_EXPORT_TYPE} {}
+", ExportType::Html))
+                .action(ArgAction::SetTrue)
+                .display_order(21)
+        )
+        .arg(
+            Arg::new(OPTION_TEMPLATE_DIR)
+                .long(OPTION_TEMPLATE_DIR)
+                .help(format!("Specify a directory of templates that customize HTML exports
+May contain page.html, message.html, balloon.html, tapback.html, and style.css
+Missing files use the built-in markup and styles
+Requires --{OPTION_EXPORT_TYPE} {}
+", ExportType::Html))
+                .display_order(22)
+                .value_name("path/to/templates"),
+        )
+        .arg(
+            Arg::new(OPTION_CONTACTS)
+                .long(OPTION_CONTACTS)
+                .help(format!("Show contact names instead of phone numbers and email addresses
+Accepts a macOS {ADDRESS_BOOK_MACOS}, an iOS {ADDRESS_BOOK_IOS}, a vCard (.vcf) file, or a directory that contains address books
+If the path is omitted, defaults to the macOS address book:
+`$HOME/{DEFAULT_PATH_ADDRESS_BOOK_MACOS}`
+"))
+                .num_args(0..=1)
+                .display_order(23)
+                .value_name("path/to/contacts"),
+        )
+        .arg(
+            Arg::new(OPTION_REGION)
+                .long(OPTION_REGION)
+                .help(format!("Specify the region used to add a country code to phone numbers saved without one
+Phone numbers are compared in international format, so the same person is combined across services
+If omitted, the default is `{}`
+Supported regions: {}
+", Region::default(), supported_regions()))
+                .display_order(24)
+                .value_name("code"),
+        )
+        .arg(
+            Arg::new(OPTION_ALIASES)
+                .long(OPTION_ALIASES)
+                .help(format!("Specify a JSON file that assigns display names to participants and titles to conversations
+Names can be assigned to phone numbers and emails under `handles` or to `person_centric_id`s under `people`
+Titles can be assigned to chat identifiers under `chats`
+Aliases take precedence over names from --{OPTION_CONTACTS}
+"))
+                .display_order(25)
+                .value_name("path/to/aliases.json"),
+        )
+        .arg(
+            Arg::new(OPTION_DUMP_HANDLES)
+                .long(OPTION_DUMP_HANDLES)
+                .help(format!("Instead of exporting, write a template for --{OPTION_ALIASES} that lists every handle and group chat
+Names that are already known are filled in
+"))
+                .display_order(26)
+                .value_name("path/to/aliases.json"),
+        )
+        .arg(
+            Arg::new(OPTION_TEXT)
+                .long(OPTION_TEXT)
+                .help(format!("Only export messages whose text contains this string, ignoring case\nConflicts with --{OPTION_TEXT_REGEX}\n"))
+                .display_order(27)
+                .value_name("text"),
+        )
+        .arg(
+            Arg::new(OPTION_TEXT_REGEX)
+                .long(OPTION_TEXT_REGEX)
+                .help("Only export messages whose text matches this regular expression\nExample: `--text-regex \"(?i)invoice #\\d+\"`\n")
+                .display_order(28)
+                .value_name("pattern"),
+        )
+        .arg(
+            Arg::new(OPTION_SENDER_FILTER)
+                .long(OPTION_SENDER_FILTER)
+                .help("Only export messages sent by these contact numbers or emails\nUse `me` for messages sent by the database owner\nTo provide multiple senders, use a comma-separated string\nExample: `--from me,steve@apple.com`\n")
+                .display_order(29)
+                .value_name("senders"),
+        )
+        .arg(
+            Arg::new(OPTION_SERVICE)
+                .long(OPTION_SERVICE)
+                .help("Only export messages sent with these services\nTo provide multiple services, use a comma-separated string\n")
+                .display_order(30)
+                .value_name(SUPPORTED_SERVICES),
+        )
+        .arg(
+            Arg::new(OPTION_HAS_ATTACHMENTS)
+                .long(OPTION_HAS_ATTACHMENTS)
+                .help("Only export messages that have attachments\n")
+                .action(ArgAction::SetTrue)
+                .display_order(31),
+        )
+        .arg(
+            Arg::new(OPTION_ATTACHMENT_TYPE)
+                .long(OPTION_ATTACHMENT_TYPE)
+                .help("Only export messages with attachments of these MIME types\nTo provide multiple types, use a comma-separated string\n")
+                .display_order(32)
+                .value_name(SUPPORTED_MIME_TYPES),
+        )
+        .arg(
+            Arg::new(OPTION_KIND)
+                .long(OPTION_KIND)
+                .help("Only export messages of these kinds\nTo provide multiple kinds, use a comma-separated string\n")
+                .display_order(33)
+                .value_name(SUPPORTED_KINDS),
+        )
+        .arg(
+            Arg::new(OPTION_STATUS)
+                .long(OPTION_STATUS)
+                .help("Only export messages that were unsent or deleted\nTo provide multiple statuses, use a comma-separated string\n")
+                .display_order(34)
+                .value_name(SUPPORTED_STATUSES),
+        )
+        .arg(
+            Arg::new(OPTION_WHERE)
+                .long(OPTION_WHERE)
+                .help("Only export messages that match a filter expression
+Combine filters with `AND`, `OR`, `NOT`, and parentheses
+Filters: `from:`, `service:`, `date`, `has:attachment`, `attachment:`, `text:`, `text~`, `kind:`, `status:`
+Example: `--where '(from:me OR service:sms) AND date>=2023-01-01 AND NOT kind:app'`
+")
+                .display_order(35)
+                .value_name("expression"),
+        )
 }
 
 #[cfg(test)]
@@ -453,11 +938,31 @@ impl Options {
             platform: Platform::macOS,
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         }
     }
 }
 
+/// List the codes of every region that can normalize phone numbers
+fn supported_regions() -> String {
+    REGIONS
+        .iter()
+        .map(|region| region.code)
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
 /// Parse arguments from the command line
 pub fn from_command_line() -> ArgMatches {
     get_command().get_matches()
@@ -468,13 +973,16 @@ mod arg_tests {
     use std::fs;
 
     use imessage_database::util::{
-        dirs::default_db_path, platform::Platform, query_context::QueryContext,
+        dirs::default_db_path, normalize::Region, platform::Platform, query_context::QueryContext,
     };
 
     use crate::app::{
         compatibility::attachment_manager::{AttachmentManager, AttachmentManagerMode},
+        contacts::DEFAULT_PATH_ADDRESS_BOOK_MACOS,
+        error::RuntimeError,
         export_type::ExportType,
         options::{Options, get_command, validate_path},
+        pagination::Pagination,
     };
 
     #[test]
@@ -501,7 +1009,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -583,7 +1102,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -616,7 +1146,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -695,7 +1236,18 @@ mod arg_tests {
             platform: Platform::iOS,
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -733,7 +1285,18 @@ mod arg_tests {
             platform: Platform::iOS,
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: Some("password".to_string()),
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -787,7 +1350,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -817,7 +1391,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -848,7 +1433,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: Some(String::from("steve@apple.com")),
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -878,7 +1474,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -908,7 +1515,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: false,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -980,7 +1598,18 @@ mod arg_tests {
             platform: Platform::default(),
             ignore_disk_space: true,
             conversation_filter: None,
+            sender_filter: None,
             cleartext_password: None,
+            stdout: false,
+            nest_replies: false,
+            standalone: None,
+            paginate: None,
+            search: false,
+            template_dir: None,
+            contacts: None,
+            region: Region::default(),
+            aliases: None,
+            dump_handles: None,
         };
 
         assert_eq!(actual, expected);
@@ -991,6 +1620,467 @@ mod arg_tests {
         let args = get_command().get_matches_from(["imessage-exporter", "-r", "/does/not/exist"]);
         assert!(Options::from_args(&args).is_err());
     }
+
+    #[test]
+    fn can_build_option_stdout() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "ndjson", "--stdout"]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(actual.export_type, Some(ExportType::NdJson));
+        assert!(actual.stdout);
+    }
+
+    #[test]
+    fn cant_build_option_stdout_no_export_type() {
+        let args = get_command().get_matches_from(["imessage-exporter", "--stdout"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_stdout_with_document_format() {
+        let args = get_command().get_matches_from(["imessage-exporter", "-f", "json", "--stdout"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_nest_replies() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "json", "--nest-replies"]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert!(actual.nest_replies);
+    }
+
+    #[test]
+    fn cant_build_option_nest_replies_with_html() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "html", "--nest-replies"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_standalone() {
+        let _ = fs::remove_file("/tmp/orphaned.html");
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "-o",
+            "/tmp",
+            "--standalone",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(actual.standalone, Some(25 * 1024 * 1024));
+    }
+
+    #[test]
+    fn can_build_option_standalone_embed_limit() {
+        let _ = fs::remove_file("/tmp/orphaned.html");
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "-o",
+            "/tmp",
+            "--standalone",
+            "--embed-limit",
+            "5",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(actual.standalone, Some(5 * 1024 * 1024));
+    }
+
+    #[test]
+    fn cant_build_option_standalone_with_other_format() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "txt", "--standalone"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_embed_limit_without_standalone() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "--embed-limit",
+            "5",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_paginate() {
+        let _ = fs::remove_file("/tmp/orphaned.html");
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "-o",
+            "/tmp",
+            "--paginate",
+            "month",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(actual.paginate, Some(Pagination::Month));
+    }
+
+    #[test]
+    fn cant_build_option_paginate_with_other_format() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "txt",
+            "--paginate",
+            "1000",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_invalid_paginate() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "html", "--paginate", "0"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_search() {
+        let _ = fs::remove_file("/tmp/orphaned.html");
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "-o",
+            "/tmp",
+            "--search",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert!(actual.search);
+    }
+
+    #[test]
+    fn cant_build_option_search_with_other_format() {
+        let args = get_command().get_matches_from(["imessage-exporter", "-f", "json", "--search"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_template_dir() {
+        let _ = fs::remove_file("/tmp/orphaned.html");
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "-o",
+            "/tmp",
+            "--template-dir",
+            "/tmp",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(actual.template_dir, Some(std::path::PathBuf::from("/tmp")));
+    }
+
+    #[test]
+    fn cant_build_option_missing_template_dir() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "--template-dir",
+            "/does/not/exist",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_contacts() {
+        let _ = fs::remove_file("/tmp/orphaned.html");
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "-o",
+            "/tmp",
+            "--contacts",
+            "/tmp",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(actual.contacts, Some(std::path::PathBuf::from("/tmp")));
+    }
+
+    #[test]
+    fn can_build_option_contacts_default() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "html", "--contacts"]);
+
+        // The default address book does not exist in the test environment, but the error names it
+        match Options::from_args(&args) {
+            Err(RuntimeError::InvalidOptions(why)) => {
+                assert!(why.contains(DEFAULT_PATH_ADDRESS_BOOK_MACOS));
+            }
+            other => panic!("Expected an invalid options error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn cant_build_option_missing_contacts() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "--contacts",
+            "/does/not/exist.vcf",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_contacts_no_export() {
+        let args = get_command().get_matches_from(["imessage-exporter", "--contacts", "/tmp"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_region() {
+        let _ = fs::remove_file("/tmp/orphaned.html");
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "-o",
+            "/tmp",
+            "--region",
+            "gb",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(actual.region, Region::from_cli("GB").unwrap());
+    }
+
+    #[test]
+    fn cant_build_option_invalid_region() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "html", "--region", "XX"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_message_filters() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "--text",
+            "Invoice",
+            "--from",
+            "me,steve@apple.com, 5558675309",
+            "--service",
+            "sms",
+            "--has-attachments",
+            "--attachment-type",
+            "image",
+            "--kind",
+            "edited",
+            "--status",
+            "unsent",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        let mut expected_context = QueryContext::default();
+        expected_context.set_text("Invoice");
+        expected_context.set_from_me(true);
+        expected_context.set_selected_services("sms").unwrap();
+        expected_context.set_has_attachments(true);
+        expected_context.set_selected_mime_types("image").unwrap();
+        expected_context.set_selected_kinds("edited").unwrap();
+        expected_context.set_selected_statuses("unsent").unwrap();
+
+        assert_eq!(actual.query_context, expected_context);
+        assert_eq!(
+            actual.sender_filter,
+            Some("steve@apple.com,5558675309".to_string())
+        );
+    }
+
+    #[test]
+    fn can_build_option_from_me_only() {
+        let args =
+            get_command().get_matches_from(["imessage-exporter", "-f", "txt", "--from", "Me"]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert!(actual.query_context.from_me);
+        assert!(actual.sender_filter.is_none());
+    }
+
+    #[test]
+    fn cant_build_option_text_with_text_regex() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "txt",
+            "--text",
+            "invoice",
+            "--text-regex",
+            "invoice",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_invalid_filters() {
+        for (option, value) in [
+            ("--text-regex", "(unclosed"),
+            ("--service", "pager"),
+            ("--attachment-type", "image/png"),
+            ("--kind", "tapback"),
+            ("--status", "read"),
+            ("--where", "from:me AND (service:sms"),
+            ("--where", "kind:tapback"),
+        ] {
+            let args =
+                get_command().get_matches_from(["imessage-exporter", "-f", "txt", option, value]);
+            assert!(Options::from_args(&args).is_err(), "{option} {value}");
+        }
+    }
+
+    #[test]
+    fn cant_build_option_message_filters_without_format() {
+        let args = get_command().get_matches_from(["imessage-exporter", "--has-attachments"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_where() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "txt",
+            "--where",
+            "(from:me OR service:sms) AND date>=2023-01-01 AND NOT kind:app",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        let mut expected_context = QueryContext::default();
+        expected_context
+            .set_expression("(from:me OR service:sms) AND date>=2023-01-01 AND NOT kind:app")
+            .unwrap();
+
+        assert_eq!(actual.query_context, expected_context);
+        assert!(actual.query_context.has_message_filters());
+    }
+
+    #[test]
+    fn cant_build_option_where_without_format() {
+        let args = get_command().get_matches_from(["imessage-exporter", "--where", "from:me"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn can_build_option_dump_handles() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "--dump-handles",
+            "/tmp/imessage-exporter-dump-handles.json",
+        ]);
+
+        // Build the Options
+        let actual = Options::from_args(&args).unwrap();
+
+        assert_eq!(
+            actual.dump_handles,
+            Some(std::path::PathBuf::from(
+                "/tmp/imessage-exporter-dump-handles.json"
+            ))
+        );
+        assert!(actual.export_type.is_none());
+    }
+
+    #[test]
+    fn cant_build_option_dump_handles_with_export_type() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "txt",
+            "--dump-handles",
+            "/tmp/imessage-exporter-dump-handles.json",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_dump_handles_existing() {
+        let args = get_command().get_matches_from(["imessage-exporter", "--dump-handles", "/tmp"]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_missing_aliases() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "--aliases",
+            "/does/not/exist.json",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_template_dir_with_other_format() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "txt",
+            "--template-dir",
+            "/tmp",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn cant_build_option_invalid_embed_limit() {
+        let args = get_command().get_matches_from([
+            "imessage-exporter",
+            "-f",
+            "html",
+            "--standalone",
+            "--embed-limit",
+            "big",
+        ]);
+        assert!(Options::from_args(&args).is_err());
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,145 @@
+/*!
+ Contains data structures used to split large conversations into pages.
+*/
+
+use std::fmt::Display;
+
+use chrono::{DateTime, Datelike, Local};
+
+/// Represents how a conversation is divided into pages
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Pagination {
+    /// Start a new page after this many messages
+    Messages(usize),
+    /// Start a new page for every calendar month
+    Month,
+}
+
+impl Pagination {
+    /// Given user's input, return a variant if the input matches one
+    pub fn from_cli(pagination: &str) -> Option<Self> {
+        match pagination.to_lowercase().as_str() {
+            "month" | "monthly" => Some(Self::Month),
+            size => match size.parse::<usize>() {
+                Ok(0) | Err(_) => None,
+                Ok(size) => Some(Self::Messages(size)),
+            },
+        }
+    }
+}
+
+impl Display for Pagination {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pagination::Messages(size) => write!(fmt, "{size}"),
+            Pagination::Month => write!(fmt, "month"),
+        }
+    }
+}
+
+/// Tracks which page the next message in a conversation belongs on
+#[derive(Debug, Default)]
+pub struct PageCursor {
+    /// The current page number, starting at 1
+    page: usize,
+    /// Number of messages placed so far
+    count: usize,
+    /// The year and month of the current page
+    month: Option<(i32, u32)>,
+}
+
+impl PageCursor {
+    /// Place the next message, returning the page number it belongs on
+    ///
+    /// Messages without a valid date stay on the current page, which takes the month of the next dated message.
+    pub fn advance(&mut self, pagination: &Pagination, date: Option<&DateTime<Local>>) -> usize {
+        match pagination {
+            Pagination::Messages(size) => {
+                if self.count.is_multiple_of(*size) {
+                    self.page += 1;
+                }
+            }
+            Pagination::Month => {
+                let month = date.map(|date| (date.year(), date.month()));
+                if self.page == 0
+                    || (month.is_some() && self.month.is_some() && month != self.month)
+                {
+                    self.page += 1;
+                }
+                if month.is_some() {
+                    self.month = month;
+                }
+            }
+        }
+        self.count += 1;
+        self.page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use crate::app::pagination::{PageCursor, Pagination};
+
+    #[test]
+    fn can_parse_month_any_case() {
+        assert_eq!(Pagination::from_cli("month"), Some(Pagination::Month));
+        assert_eq!(Pagination::from_cli("MONTHLY"), Some(Pagination::Month));
+    }
+
+    #[test]
+    fn can_parse_size() {
+        assert_eq!(
+            Pagination::from_cli("5000"),
+            Some(Pagination::Messages(5000))
+        );
+    }
+
+    #[test]
+    fn cant_parse_invalid() {
+        assert!(Pagination::from_cli("0").is_none());
+        assert!(Pagination::from_cli("-1").is_none());
+        assert!(Pagination::from_cli("week").is_none());
+        assert!(Pagination::from_cli("").is_none());
+    }
+
+    #[test]
+    fn can_advance_by_size() {
+        let mut cursor = PageCursor::default();
+        let pages: Vec<usize> = (0..5)
+            .map(|_| cursor.advance(&Pagination::Messages(2), None))
+            .collect();
+        assert_eq!(pages, vec![1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn can_advance_by_month() {
+        let may = Local.with_ymd_and_hms(2022, 5, 17, 12, 0, 0).unwrap();
+        let may_end = Local.with_ymd_and_hms(2022, 5, 31, 12, 0, 0).unwrap();
+        let june = Local.with_ymd_and_hms(2022, 6, 1, 12, 0, 0).unwrap();
+        let may_next_year = Local.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+
+        let mut cursor = PageCursor::default();
+        assert_eq!(cursor.advance(&Pagination::Month, Some(&may)), 1);
+        assert_eq!(cursor.advance(&Pagination::Month, Some(&may_end)), 1);
+        assert_eq!(cursor.advance(&Pagination::Month, None), 1);
+        assert_eq!(cursor.advance(&Pagination::Month, Some(&june)), 2);
+        assert_eq!(cursor.advance(&Pagination::Month, Some(&may_next_year)), 3);
+    }
+
+    #[test]
+    fn can_advance_by_month_without_date() {
+        let may = Local.with_ymd_and_hms(2022, 5, 17, 12, 0, 0).unwrap();
+
+        let mut cursor = PageCursor::default();
+        assert_eq!(cursor.advance(&Pagination::Month, None), 1);
+        assert_eq!(cursor.advance(&Pagination::Month, Some(&may)), 1);
+    }
+
+    #[test]
+    fn can_display() {
+        assert_eq!(Pagination::Messages(100).to_string(), "100");
+        assert_eq!(Pagination::Month.to_string(), "month");
+    }
+}
@@ -4,7 +4,7 @@
 
 use std::{
     cmp::min,
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs::{create_dir_all, remove_file},
     path::{Path, PathBuf},
 };
@@ -15,12 +15,14 @@ use fs2::available_space;
 use rusqlite::Connection;
 
 use crate::{
-    Exporter, HTML, TXT, JSON,
+    CSV, Exporter, HTML, JSON, Markdown, Mbox, SQLite, SmsBackup, TXT,
     app::{
+        aliases::Aliases,
         compatibility::{
             attachment_manager::AttachmentManagerMode,
             backup::{decrypt_backup, get_decrypted_message_database},
         },
+        contacts::Contacts,
         error::RuntimeError,
         export_type::ExportType,
         options::{OPTION_CLEARTEXT_PASSWORD, Options},
@@ -30,6 +32,7 @@ use crate::{
 };
 
 use imessage_database::{
+    error::table::TableError,
     tables::{
         attachment::Attachment,
         chat::Chat,
@@ -37,11 +40,13 @@ use imessage_database::{
         handle::Handle,
         messages::Message,
         table::{
-            ATTACHMENTS_DIR, Cacheable, Deduplicate, Diagnostic, ME, ORPHANED, UNKNOWN,
+            ATTACHMENTS_DIR, Cacheable, Deduplicate, Diagnostic, ME, ORPHANED, Table, UNKNOWN,
             get_connection, get_db_size,
         },
     },
-    util::{dates::get_offset, platform::Platform, size::format_file_size},
+    util::{
+        dates::get_offset, normalize::normalize_handle, platform::Platform, size::format_file_size,
+    },
 };
 
 const MAX_LENGTH: usize = 235;
@@ -59,6 +64,10 @@ pub struct Config {
     pub participants: HashMap<i32, String>,
     /// Map of participant ID to an internal unique participant ID
     pub real_participants: HashMap<i32, i32>,
+    /// Map of participant ID to the name of their contact in the address book or alias file
+    pub contact_names: HashMap<i32, String>,
+    /// Map of chatroom ID to the title assigned in the alias file
+    pub chat_titles: HashMap<i32, String>,
     /// Messages that are tapbacks (reactions) to other messages
     pub tapbacks: HashMap<String, HashMap<usize, Vec<Message>>>,
     /// App configuration options
@@ -147,7 +156,7 @@ impl Config {
     ///
     /// If it does not, first try and make a flat list of its members. Failing that, use the unique `chat_identifier` field.
     pub fn filename(&self, chatroom: &Chat) -> String {
-        let mut filename = match &chatroom.display_name() {
+        let mut filename = match self.chat_title(chatroom) {
             // If there is a display name, use that
             Some(name) => {
                 format!(
@@ -178,6 +187,14 @@ impl Config {
         sanitize_filename(&filename)
     }
 
+    /// Get the title of a chat, preferring a title from the alias file over the name set in Messages
+    pub fn chat_title<'a>(&'a self, chatroom: &'a Chat) -> Option<&'a str> {
+        self.chat_titles
+            .get(&chatroom.rowid)
+            .map(String::as_str)
+            .or_else(|| chatroom.display_name())
+    }
+
     /// Generate a filename from a set of participants, truncating if the name is too long
     ///
     /// - All names:
@@ -254,12 +271,57 @@ impl Config {
         let tapbacks = Message::cache(&conn)?;
         eprintln!("Cache built!");
 
+        let mut contact_names = match &options.contacts {
+            Some(path) => {
+                eprintln!("Reading contacts from {}...", path.display());
+                let contacts = Contacts::from_path(path, &options.region)?;
+                if contacts.is_empty() {
+                    eprintln!("No contacts with phone numbers or email addresses found!");
+                }
+                let contact_names = contacts.resolve(&participants);
+                eprintln!(
+                    "Matched {} of {} handles to contacts",
+                    contact_names.len(),
+                    participants.len()
+                );
+                contact_names
+            }
+            None => HashMap::new(),
+        };
+
+        // Aliases take precedence over names from the address book
+        let mut chat_titles = HashMap::new();
+        if let Some(path) = &options.aliases {
+            eprintln!("Reading aliases from {}...", path.display());
+            let aliases = Aliases::from_path(path, &options.region)?;
+            let person_centric_ids = Config::person_centric_ids(&conn)?;
+            contact_names.extend(aliases.resolve(
+                &participants,
+                &person_centric_ids,
+                &options.region,
+            ));
+            chat_titles.extend(chatrooms.iter().filter_map(|(chat_id, chat)| {
+                aliases
+                    .chat_title(&chat.chat_identifier, &options.region)
+                    .map(|title| (*chat_id, title.to_string()))
+            }));
+        }
+
+        // Handles are compared in a normalized format so the same person is combined across services
+        let real_participants = Handle::dedupe(&Handle::normalize(&participants, &options.region));
+        let real_chatrooms = ChatToHandle::dedupe(&Config::real_chatroom_participants(
+            &chatroom_participants,
+            &real_participants,
+        ));
+
         Ok(Config {
             chatrooms,
-            real_chatrooms: ChatToHandle::dedupe(&chatroom_participants),
+            real_chatrooms,
             chatroom_participants,
-            real_participants: Handle::dedupe(&participants),
+            real_participants,
             participants,
+            contact_names,
+            chat_titles,
             tapbacks,
             options,
             offset: get_offset(),
@@ -268,6 +330,50 @@ impl Config {
         })
     }
 
+    /// Map each handle ID to its `person_centric_id`, if the database has one
+    fn person_centric_ids(db: &Connection) -> Result<HashMap<i32, String>, RuntimeError> {
+        let mut map = HashMap::new();
+        let mut statement = Handle::get(db)?;
+        let handles = statement
+            .query_map([], |row| Ok(Handle::from_row(row)))
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
+        for handle in handles {
+            let handle = Handle::extract(handle)?;
+            if let Some(person_centric_id) = handle.person_centric_id {
+                map.insert(handle.rowid, person_centric_id);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Replace each chatroom's handles with their deduplicated participant IDs
+    ///
+    /// This allows chatrooms with the same people to be combined even if they used different handles,
+    /// i.e. an SMS conversation with a phone number and an iMessage conversation with an email address.
+    fn real_chatroom_participants(
+        chatroom_participants: &HashMap<i32, BTreeSet<i32>>,
+        real_participants: &HashMap<i32, i32>,
+    ) -> HashMap<i32, BTreeSet<i32>> {
+        chatroom_participants
+            .iter()
+            .map(|(chat_id, participants)| {
+                (
+                    *chat_id,
+                    participants
+                        .iter()
+                        // Handles missing from the handle table get a negative ID so they stay distinct
+                        .map(|participant| {
+                            real_participants
+                                .get(participant)
+                                .copied()
+                                .unwrap_or(-1 - participant)
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
     /// Get the current database connection, if it is alive
     ///
     /// # Panics
@@ -284,28 +390,30 @@ impl Config {
 
     // MARK: Filters
     /// Convert comma separated list of participant strings into table chat IDs using
-    ///   1) filter `self.participant` keys based on the values (by comparing to user values)
-    ///   2) get the chat IDs keys from `self.chatroom_participants` for values that contain the selected `handle_ids`
-    ///   3) send those chat and handle IDs to the query context so they are included in the message table filters
+    ///   1) filter `self.participant` keys based on the values (by comparing to user values as-is and normalized)
+    ///   2) add every other handle that belongs to the same deduplicated participant
+    ///   3) get the chat IDs keys from `self.chatroom_participants` for values that contain the selected `handle_ids`
+    ///   4) send those chat and handle IDs to the query context so they are included in the message table filters
+    ///
+    /// Senders selected with `--from` are resolved with the first two steps and sent to the query context on their own.
     pub(crate) fn resolve_filtered_handles(&mut self) {
-        if let Some(conversation_filter) = &self.options.conversation_filter {
-            let parsed_handle_filter = conversation_filter.split(',').collect::<Vec<&str>>();
+        if let Some(sender_filter) = &self.options.sender_filter {
+            let sender_handles = self.match_handles(sender_filter);
+            self.options
+                .query_context
+                .set_selected_sender_ids(sender_handles);
+        }
 
-            let mut included_chatrooms: BTreeSet<i32> = BTreeSet::new();
-            let mut included_handles: BTreeSet<i32> = BTreeSet::new();
+        if let Some(mut expression) = self.options.query_context.expression.take() {
+            expression.resolve_senders(&|filter| self.match_handles(filter));
+            self.options.query_context.expression = Some(expression);
+        }
 
-            // First: Scan the list of participants for included handle IDs
-            self.participants
-                .iter()
-                .for_each(|(handle_id, handle_name)| {
-                    for included_name in &parsed_handle_filter {
-                        if handle_name.contains(included_name) {
-                            included_handles.insert(*handle_id);
-                        }
-                    }
-                });
+        if let Some(conversation_filter) = &self.options.conversation_filter {
+            let included_handles = self.match_handles(conversation_filter);
+            let mut included_chatrooms: BTreeSet<i32> = BTreeSet::new();
 
-            // Second, scan the list of chatrooms for IDs that contain the selected participants
+            // Third, scan the list of chatrooms for IDs that contain the selected participants
             self.chatroom_participants
                 .iter()
                 .for_each(|(chat_id, participants)| {
@@ -326,6 +434,51 @@ impl Config {
         }
     }
 
+    /// Get the handle IDs that match a comma separated list of participant strings, including every handle that belongs to the same participant
+    fn match_handles(&self, filter: &str) -> BTreeSet<i32> {
+        let parsed_handle_filter = filter.split(',').collect::<Vec<&str>>();
+        let normalized_handle_filter = parsed_handle_filter
+            .iter()
+            .map(|included_name| normalize_handle(included_name, &self.options.region))
+            .collect::<Vec<String>>();
+
+        let mut included_handles: BTreeSet<i32> = BTreeSet::new();
+
+        // First: Scan the list of participants for included handle IDs
+        self.participants
+            .iter()
+            .for_each(|(handle_id, handle_name)| {
+                let normalized_names = handle_name
+                    .split_whitespace()
+                    .map(|name| normalize_handle(name, &self.options.region))
+                    .collect::<Vec<String>>();
+                for (included_name, normalized_name) in
+                    parsed_handle_filter.iter().zip(&normalized_handle_filter)
+                {
+                    if handle_name.contains(included_name)
+                        || normalized_names.contains(normalized_name)
+                    {
+                        included_handles.insert(*handle_id);
+                    }
+                }
+            });
+
+        // Second: Include the other handles used by the selected participants, i.e. on other services
+        let included_participants: HashSet<&i32> = included_handles
+            .iter()
+            .filter_map(|handle_id| self.real_participants.get(handle_id))
+            .collect();
+        self.real_participants
+            .iter()
+            .for_each(|(handle_id, participant_id)| {
+                if included_participants.contains(participant_id) {
+                    included_handles.insert(*handle_id);
+                }
+            });
+
+        included_handles
+    }
+
     /// If we set some filtered chatrooms, emit how many will be included in the export
     fn log_filtered_handles_and_chats(&self) {
         if let (Some(selected_handle_ids), Some(selected_chat_ids)) = (
@@ -396,6 +549,58 @@ impl Config {
         Ok(())
     }
 
+    /// Write a template alias file that lists every handle and group chat
+    ///
+    /// Handles are deduplicated by their normalized value, and any names that are already known are filled in.
+    fn dump_handles(&self, path: &Path) -> Result<(), RuntimeError> {
+        let mut handles = BTreeMap::new();
+        for (handle_id, ids) in &self.participants {
+            let name = self
+                .contact_names
+                .get(handle_id)
+                .cloned()
+                .unwrap_or_default();
+            for id in ids.split_whitespace().filter(|id| *id != ME) {
+                let entry = handles
+                    .entry(normalize_handle(id, &self.options.region))
+                    .or_insert_with(String::new);
+                if entry.is_empty() {
+                    entry.clone_from(&name);
+                }
+            }
+        }
+
+        let chats = self
+            .chatrooms
+            .values()
+            .filter(|chat| {
+                self.chatroom_participants
+                    .get(&chat.rowid)
+                    .is_some_and(|participants| participants.len() > 1)
+            })
+            .map(|chat| {
+                (
+                    normalize_handle(&chat.chat_identifier, &self.options.region),
+                    self.chat_title(chat).unwrap_or_default().to_string(),
+                )
+            })
+            .collect();
+
+        let aliases = Aliases {
+            handles,
+            people: BTreeMap::new(),
+            chats,
+        };
+        aliases.write(path)?;
+        eprintln!(
+            "Wrote {} handles and {} group chats to {}",
+            aliases.handles.len(),
+            aliases.chats.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
     /// Handles diagnostic tests for database
     fn run_diagnostic(&self) -> Result<(), RuntimeError> {
         println!("\niMessage Database Diagnostics\n");
@@ -454,15 +659,35 @@ impl Config {
     pub fn start(&self) -> Result<(), RuntimeError> {
         if self.options.diagnostic {
             self.run_diagnostic()?;
+        } else if let Some(path) = &self.options.dump_handles {
+            self.dump_handles(path)?;
         } else if let Some(export_type) = &self.options.export_type {
             // Ensure that if we want to filter on things, we have stuff to filter for
             if let Some(filters) = &self.options.conversation_filter
-                && !self.options.query_context.has_filters()
+                && self.options.query_context.selected_chat_ids.is_none()
             {
                 return Err(RuntimeError::InvalidOptions(format!(
                     "Selected filter `{filters}` does not match any participants!"
                 )));
             }
+            if let Some(senders) = &self.options.sender_filter
+                && self.options.query_context.selected_sender_ids.is_none()
+            {
+                return Err(RuntimeError::InvalidOptions(format!(
+                    "Selected sender `{senders}` does not match any participants!"
+                )));
+            }
+            if let Some(sender) = self
+                .options
+                .query_context
+                .expression
+                .as_ref()
+                .and_then(|expression| expression.unmatched_senders().first().copied())
+            {
+                return Err(RuntimeError::InvalidOptions(format!(
+                    "Selected sender `{sender}` does not match any participants!"
+                )));
+            }
 
             // Ensure the path we want to export to exists
             create_dir_all(&self.options.export_path)?;
@@ -491,12 +716,28 @@ impl Config {
                 ExportType::Txt => {
                     TXT::new(self)?.iter_messages()?;
                 }
-                ExportType::Json => {
+                ExportType::Json | ExportType::NdJson => {
                     JSON::new(self)?.iter_messages()?;
                 }
+                ExportType::Markdown => {
+                    Markdown::new(self)?.iter_messages()?;
+                }
+                ExportType::Csv | ExportType::Tsv => {
+                    CSV::new(self)?.iter_messages()?;
+                }
+                ExportType::Sqlite => {
+                    SQLite::new(self)?.iter_messages()?;
+                }
+                ExportType::Mbox => {
+                    Mbox::new(self)?.iter_messages()?;
+                }
+                ExportType::SmsBackup => {
+                    SmsBackup::new(self)?.iter_messages()?;
+                }
             }
         }
-        println!("Done!");
+        // Status goes to stderr so it cannot corrupt data streamed to stdout
+        eprintln!("Done!");
         Ok(())
     }
 
@@ -513,7 +754,11 @@ impl Config {
             }
             return self.options.custom_name.as_deref().unwrap_or(ME);
         } else if let Some(handle_id) = handle_id {
-            return match self.participants.get(&handle_id) {
+            return match self
+                .contact_names
+                .get(&handle_id)
+                .or_else(|| self.participants.get(&handle_id))
+            {
                 Some(contact) => contact,
                 None => UNKNOWN,
             };
@@ -533,6 +778,8 @@ impl Config {
             chatroom_participants: HashMap::new(),
             participants: HashMap::new(),
             real_participants: HashMap::new(),
+            contact_names: HashMap::new(),
+            chat_titles: HashMap::new(),
             tapbacks: HashMap::new(),
             options,
             offset: get_offset(),
@@ -781,6 +1028,33 @@ mod filename_tests {
         assert_eq!(filename, "🤠 - 0.html");
     }
 
+    #[test]
+    fn can_get_filename_chat_alias() {
+        let options = Options::fake_options(crate::app::export_type::ExportType::Html);
+        let mut app = Config::fake_app(options);
+
+        // Create chat
+        let mut chat = fake_chat();
+        chat.display_name = Some("Test Chat Name".to_string());
+        app.chat_titles.insert(chat.rowid, "Family".to_string());
+
+        // Get filename
+        let filename = app.filename(&chat);
+        assert_eq!(filename, "Family - 0.html");
+    }
+
+    #[test]
+    fn can_get_chat_title_without_alias() {
+        let options = Options::fake_options(crate::app::export_type::ExportType::Html);
+        let app = Config::fake_app(options);
+
+        let mut chat = fake_chat();
+        assert_eq!(app.chat_title(&chat), None);
+
+        chat.display_name = Some("Test Chat Name".to_string());
+        assert_eq!(app.chat_title(&chat), Some("Test Chat Name"));
+    }
+
     #[test]
     fn can_get_filename_chat_participants() {
         let options = Options::fake_options(crate::app::export_type::ExportType::Html);
@@ -804,6 +1078,30 @@ mod filename_tests {
         assert_eq!(filename, "Person 10, Person 11.html");
     }
 
+    #[test]
+    fn can_get_filename_chat_participants_contacts() {
+        let options = Options::fake_options(crate::app::export_type::ExportType::Html);
+        let mut app = Config::fake_app(options);
+
+        // Create chat
+        let chat = fake_chat();
+
+        // Create participant data
+        app.participants.insert(10, "+15558675309".to_string());
+        app.participants.insert(11, "+15551234567".to_string());
+        app.contact_names.insert(10, "Jenny".to_string());
+
+        // Add participants
+        let mut people = BTreeSet::new();
+        people.insert(10);
+        people.insert(11);
+        app.chatroom_participants.insert(chat.rowid, people);
+
+        // Get filename
+        let filename = app.filename(&chat);
+        assert_eq!(filename, "Jenny, +15551234567.html");
+    }
+
     #[test]
     fn can_get_filename_chat_no_participants() {
         let options = Options::fake_options(crate::app::export_type::ExportType::Html);
@@ -845,6 +1143,20 @@ mod who_tests {
         assert_eq!(who, "Person 10".to_string());
     }
 
+    #[test]
+    fn can_get_who_them_contact() {
+        let options = Options::fake_options(crate::app::export_type::ExportType::Html);
+        let mut app = Config::fake_app(options);
+
+        // Create participant data
+        app.participants.insert(10, "+15558675309".to_string());
+        app.contact_names.insert(10, "Jenny".to_string());
+
+        // Get participant name
+        let who = app.who(Some(10), false, &None);
+        assert_eq!(who, "Jenny".to_string());
+    }
+
     #[test]
     fn can_get_who_them_missing() {
         let options = Options::fake_options(crate::app::export_type::ExportType::Html);
@@ -1098,6 +1410,7 @@ mod chat_filter_tests {
     use std::collections::BTreeSet;
 
     use crate::{Config, Options, app::export_type::ExportType};
+    use imessage_database::util::expression::Expression;
 
     #[test]
     fn can_generate_filter_string_multiple() {
@@ -1157,6 +1470,105 @@ mod chat_filter_tests {
         );
     }
 
+    #[test]
+    fn can_generate_filter_string_normalized() {
+        let mut options = Options::fake_options(ExportType::Html);
+        options.conversation_filter = Some(String::from("(555) 867-5309"));
+
+        let mut app = Config::fake_app(options);
+
+        // Add some test data
+        app.participants.insert(10, "+15558675309".to_string()); // Included
+        app.participants.insert(11, "jenny@example.com".to_string()); // Included, same person
+        app.participants.insert(12, "+15551234567".to_string()); // Excluded
+        app.real_participants.insert(10, 0);
+        app.real_participants.insert(11, 0);
+        app.real_participants.insert(12, 1);
+
+        // Chatroom 1: Included
+        app.chatroom_participants.insert(1, BTreeSet::from([10]));
+        // Chatroom 2: Included, since the same person used another handle
+        app.chatroom_participants.insert(2, BTreeSet::from([11]));
+        // Chatroom 3: Excluded
+        app.chatroom_participants.insert(3, BTreeSet::from([12]));
+
+        app.resolve_filtered_handles();
+
+        assert_eq!(
+            app.options.query_context.selected_handle_ids,
+            Some(BTreeSet::from([10, 11]))
+        );
+        assert_eq!(
+            app.options.query_context.selected_chat_ids,
+            Some(BTreeSet::from([1, 2]))
+        );
+    }
+
+    #[test]
+    fn can_generate_sender_filter() {
+        let mut options = Options::fake_options(ExportType::Html);
+        options.sender_filter = Some(String::from("(555) 867-5309"));
+
+        let mut app = Config::fake_app(options);
+
+        // Add some test data
+        app.participants.insert(10, "+15558675309".to_string()); // Included
+        app.participants.insert(11, "jenny@example.com".to_string()); // Included, same person
+        app.participants.insert(12, "+15551234567".to_string()); // Excluded
+        app.real_participants.insert(10, 0);
+        app.real_participants.insert(11, 0);
+        app.real_participants.insert(12, 1);
+        app.chatroom_participants
+            .insert(1, BTreeSet::from([10, 12]));
+
+        app.resolve_filtered_handles();
+
+        assert_eq!(
+            app.options.query_context.selected_sender_ids,
+            Some(BTreeSet::from([10, 11]))
+        );
+        // Senders do not limit which conversations are exported
+        assert!(app.options.query_context.selected_chat_ids.is_none());
+        assert!(app.options.query_context.selected_handle_ids.is_none());
+    }
+
+    #[test]
+    fn can_resolve_expression_senders() {
+        let mut options = Options::fake_options(ExportType::Html);
+        let filter = "from:\"(555) 867-5309\" OR from:nobody@example.com";
+        options.query_context.set_expression(filter).unwrap();
+
+        let mut app = Config::fake_app(options);
+
+        // Add some test data
+        app.participants.insert(10, "+15558675309".to_string()); // Included
+        app.participants.insert(11, "jenny@example.com".to_string()); // Included, same person
+        app.participants.insert(12, "+15551234567".to_string()); // Excluded
+        app.real_participants.insert(10, 0);
+        app.real_participants.insert(11, 0);
+        app.real_participants.insert(12, 1);
+
+        app.resolve_filtered_handles();
+
+        let mut expected = Expression::parse(filter).unwrap();
+        expected.resolve_senders(&|sender| {
+            if sender == "(555) 867-5309" {
+                BTreeSet::from([10, 11])
+            } else {
+                BTreeSet::new()
+            }
+        });
+        assert_eq!(app.options.query_context.expression, Some(expected));
+        assert_eq!(
+            app.options
+                .query_context
+                .expression
+                .as_ref()
+                .map(Expression::unmatched_senders),
+            Some(vec!["nobody@example.com"])
+        );
+    }
+
     #[test]
     fn can_generate_filter_string_single() {
         let mut options = Options::fake_options(ExportType::Html);
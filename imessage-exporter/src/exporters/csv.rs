@@ -0,0 +1,711 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::{
+    app::{
+        compatibility::attachment_manager::AttachmentManagerMode, error::RuntimeError,
+        export_type::ExportType, progress::ExportProgress, runtime::Config,
+    },
+    exporters::exporter::{Exporter, expressive_label, variant_label},
+};
+
+use imessage_database::{
+    error::table::TableError,
+    tables::{
+        attachment::{Attachment, MediaType},
+        chat::Chat,
+        handle::Handle,
+        messages::{Message, models::BubbleComponent},
+        table::Table,
+    },
+    util::dates::get_local_time,
+};
+
+// MARK: CSV
+/// Table of every exported message
+const MESSAGES: &str = "messages";
+/// Table of the attachments that belong to exported messages
+const ATTACHMENTS: &str = "attachments";
+/// Table of every conversation in the database
+const CHATS: &str = "chats";
+/// Table of every contact handle in the database
+const HANDLES: &str = "handles";
+
+/// Header row for the `messages` table
+const MESSAGE_COLUMNS: [&str; 19] = [
+    "rowid",
+    "guid",
+    "chat_id",
+    "chat_name",
+    "handle_id",
+    "sender_handle",
+    "sender_name",
+    "service",
+    "date",
+    "date_read",
+    "date_delivered",
+    "is_from_me",
+    "text",
+    "subject",
+    "variant",
+    "expressive",
+    "num_attachments",
+    "reply_to_guid",
+    "edited",
+];
+
+/// Header row for the `attachments` table
+const ATTACHMENT_COLUMNS: [&str; 14] = [
+    "rowid",
+    "message_rowid",
+    "message_guid",
+    "filename",
+    "transfer_name",
+    "uti",
+    "mime_type",
+    "total_bytes",
+    "is_sticker",
+    "hide_attachment",
+    "emoji_description",
+    "transcription",
+    "resolved_path",
+    "exported_path",
+];
+
+/// Header row for the `chats` table
+const CHAT_COLUMNS: [&str; 7] = [
+    "rowid",
+    "unique_id",
+    "chat_identifier",
+    "service",
+    "display_name",
+    "name",
+    "participants",
+];
+
+/// Header row for the `handles` table
+const HANDLE_COLUMNS: [&str; 5] = ["rowid", "unique_id", "id", "person_centric_id", "name"];
+
+/// Separates the handle IDs listed in a single field
+const LIST_SEPARATOR: &str = ";";
+
+/// Date format that spreadsheets and dataframe libraries parse without configuration
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub struct CSV<'a> {
+    /// Data that is setup from the application's runtime
+    pub config: &'a Config,
+    /// Writer for the `messages` table, shared by every conversation
+    pub messages: Option<BufWriter<File>>,
+    /// Writer for the `attachments` table, shared by every conversation
+    pub attachments: Option<BufWriter<File>>,
+    /// Character used to separate fields, either a comma or a tab
+    delimiter: char,
+    /// Progress Bar model for alerting the user about current export state
+    pb: ExportProgress,
+}
+
+// MARK: Exporter
+impl<'a> Exporter<'a> for CSV<'a> {
+    fn new(config: &'a Config) -> Result<Self, RuntimeError> {
+        Ok(CSV {
+            config,
+            messages: None,
+            attachments: None,
+            delimiter: match config.options.export_type {
+                Some(ExportType::Tsv) => '\t',
+                _ => ',',
+            },
+            pb: ExportProgress::new(),
+        })
+    }
+
+    fn iter_messages(&mut self) -> Result<(), RuntimeError> {
+        // Tell the user what we are doing
+        eprintln!(
+            "Exporting to {} as {}...",
+            self.config.options.export_path.display(),
+            self.export_type()
+        );
+
+        // The lookup tables do not depend on the selected messages
+        self.write_chats()?;
+        self.write_handles()?;
+
+        // Emit the headers even if the export contains no rows
+        self.messages = Some(self.create_table(MESSAGES, &MESSAGE_COLUMNS)?);
+        self.attachments = Some(self.create_table(ATTACHMENTS, &ATTACHMENT_COLUMNS)?);
+
+        // Keep track of current message ROWID
+        let mut current_message_row = -1;
+
+        // Set up progress bar
+        let mut current_message = 0;
+        let total_messages =
+            Message::get_count(self.config.db(), &self.config.options.query_context)?;
+        self.pb.start(total_messages);
+
+        let mut statement =
+            Message::stream_rows(self.config.db(), &self.config.options.query_context)?;
+
+        let messages = statement
+            .query_map([], |row| Ok(Message::from_row(row)))
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
+
+        for message in messages {
+            let mut msg = Message::extract(message)?;
+
+            // Early escape if we try and render the same message GUID twice
+            // See https://github.com/ReagentX/imessage-exporter/issues/135 for rationale
+            if msg.rowid == current_message_row {
+                current_message += 1;
+                continue;
+            }
+            current_message_row = msg.rowid;
+
+            // Generate the text of the message
+            let _ = msg.generate_text(self.config.db());
+
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
+            }
+
+            // Skip tapbacks as they are not messages on their own
+            if !msg.is_tapback() {
+                let row = self.format_message_row(&msg);
+                let file = self.get_or_create_file(&msg)?;
+                CSV::write_to_file(file, &row)?;
+
+                self.write_attachments(&msg)?;
+            }
+
+            current_message += 1;
+            if current_message % 99 == 0 {
+                self.pb.set_position(current_message);
+            }
+        }
+        self.pb.finish();
+
+        Ok(())
+    }
+
+    /// All messages share a single table, regardless of conversation
+    fn get_or_create_file(
+        &mut self,
+        _message: &Message,
+    ) -> Result<&mut BufWriter<File>, RuntimeError> {
+        let buf = match self.messages.take() {
+            Some(buf) => buf,
+            None => self.create_table(MESSAGES, &MESSAGE_COLUMNS)?,
+        };
+        Ok(self.messages.insert(buf))
+    }
+
+    fn write_to_file(file: &mut BufWriter<File>, text: &str) -> Result<(), RuntimeError> {
+        file.write_all(text.as_bytes())
+            .map_err(RuntimeError::DiskError)
+    }
+}
+
+impl CSV<'_> {
+    /// The export type for the current delimiter
+    fn export_type(&self) -> ExportType {
+        match self.delimiter {
+            '\t' => ExportType::Tsv,
+            _ => ExportType::Csv,
+        }
+    }
+
+    /// Create a table file in the export directory and write its header row
+    ///
+    /// Tables are replaced, not appended to, so each export starts from a single header.
+    fn create_table(&self, name: &str, columns: &[&str]) -> Result<BufWriter<File>, RuntimeError> {
+        let mut path = self.config.options.export_path.clone();
+        path.push(name);
+        path.set_extension(self.export_type().to_string());
+
+        let mut buf = BufWriter::new(File::create(path)?);
+        CSV::write_to_file(&mut buf, &self.format_row(columns))?;
+        Ok(buf)
+    }
+
+    /// Get the writer for the `attachments` table, otherwise create a new one
+    fn attachments_file(&mut self) -> Result<&mut BufWriter<File>, RuntimeError> {
+        let buf = match self.attachments.take() {
+            Some(buf) => buf,
+            None => self.create_table(ATTACHMENTS, &ATTACHMENT_COLUMNS)?,
+        };
+        Ok(self.attachments.insert(buf))
+    }
+
+    /// Write every conversation, ordered by ROWID
+    fn write_chats(&self) -> Result<(), RuntimeError> {
+        let mut file = self.create_table(CHATS, &CHAT_COLUMNS)?;
+
+        let mut chats: Vec<&Chat> = self.config.chatrooms.values().collect();
+        chats.sort_unstable_by_key(|chat| chat.rowid);
+        for chat in chats {
+            CSV::write_to_file(&mut file, &self.format_chat_row(chat))?;
+        }
+
+        file.flush().map_err(RuntimeError::DiskError)
+    }
+
+    /// Write every contact handle, ordered by ROWID
+    fn write_handles(&self) -> Result<(), RuntimeError> {
+        let mut file = self.create_table(HANDLES, &HANDLE_COLUMNS)?;
+
+        let mut statement = Handle::get(self.config.db())?;
+        let handles = statement
+            .query_map([], |row| Ok(Handle::from_row(row)))
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
+
+        let mut handles = handles
+            .map(Handle::extract)
+            .collect::<Result<Vec<Handle>, TableError>>()?;
+        handles.sort_unstable_by_key(|handle| handle.rowid);
+        for handle in &handles {
+            CSV::write_to_file(&mut file, &self.format_handle_row(handle))?;
+        }
+
+        file.flush().map_err(RuntimeError::DiskError)
+    }
+
+    /// Write a row for each attachment in a message, copying the files if requested
+    fn write_attachments(&mut self, message: &Message) -> Result<(), RuntimeError> {
+        if message.num_attachments == 0 {
+            return Ok(());
+        }
+
+        let mut attachments =
+            Attachment::from_message(self.config.db(), message).unwrap_or_default();
+        let mut transcriptions =
+            message
+                .components
+                .iter()
+                .filter_map(|component| match component {
+                    BubbleComponent::Attachment(meta) => Some(meta.transcription.as_deref()),
+                    _ => None,
+                });
+
+        for attachment in &mut attachments {
+            self.copy_attachment(attachment, message);
+            let transcription = transcriptions.next().flatten();
+            let row = self.format_attachment_row(attachment, message, transcription);
+            CSV::write_to_file(self.attachments_file()?, &row)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copy an attachment to the export directory, if requested
+    fn copy_attachment(&self, attachment: &mut Attachment, message: &Message) {
+        // When encoding videos, alert the user that the time estimate may be inaccurate
+        let will_encode = matches!(attachment.mime_type(), MediaType::Video(_))
+            && matches!(
+                self.config.options.attachment_manager.mode,
+                AttachmentManagerMode::Full
+            );
+
+        if will_encode {
+            self.pb
+                .set_busy_style("Encoding video, estimates paused...".to_string());
+        }
+
+        // Copy the file, if requested
+        self.config
+            .options
+            .attachment_manager
+            .handle_attachment(message, attachment, self.config);
+
+        if will_encode {
+            self.pb.set_default_style();
+        }
+    }
+
+    /// Build the `messages` row for a message
+    fn format_message_row(&self, message: &Message) -> String {
+        let chat_name = self
+            .config
+            .conversation(message)
+            .map(|(chatroom, _)| self.chat_name(chatroom));
+        let sender_handle = if message.is_from_me() {
+            message.destination_caller_id.as_deref()
+        } else {
+            message
+                .handle_id
+                .and_then(|handle_id| self.config.participants.get(&handle_id))
+                .map(String::as_str)
+        };
+
+        self.format_row(&[
+            Cow::Owned(message.rowid.to_string()),
+            Cow::Borrowed(message.guid.as_str()),
+            optional(message.chat_id.or(message.deleted_from)),
+            Cow::Owned(chat_name.unwrap_or_default()),
+            optional(message.handle_id),
+            Cow::Borrowed(sender_handle.unwrap_or_default()),
+            Cow::Borrowed(self.config.who(
+                message.handle_id,
+                message.is_from_me(),
+                &message.destination_caller_id,
+            )),
+            Cow::Owned(message.service().to_string()),
+            self.format_date(message.date),
+            self.format_date(message.date_read),
+            self.format_date(message.date_delivered),
+            Cow::Owned(message.is_from_me.to_string()),
+            // Attachments and app balloons are represented by placeholder characters
+            Cow::Owned(
+                message
+                    .text
+                    .as_deref()
+                    .unwrap_or_default()
+                    .replace('\u{FFFC}', ""),
+            ),
+            Cow::Borrowed(message.subject.as_deref().unwrap_or_default()),
+            Cow::Borrowed(variant_label(&message.variant())),
+            Cow::Borrowed(expressive_label(&message.get_expressive()).unwrap_or_default()),
+            Cow::Owned(message.num_attachments.to_string()),
+            Cow::Borrowed(
+                message
+                    .thread_originator_guid
+                    .as_deref()
+                    .unwrap_or_default(),
+            ),
+            Cow::Owned(message.is_edited().to_string()),
+        ])
+    }
+
+    /// Build the `attachments` row for an attachment that belongs to a message
+    fn format_attachment_row(
+        &self,
+        attachment: &Attachment,
+        message: &Message,
+        transcription: Option<&str>,
+    ) -> String {
+        self.format_row(&[
+            Cow::Owned(attachment.rowid.to_string()),
+            Cow::Owned(message.rowid.to_string()),
+            Cow::Borrowed(message.guid.as_str()),
+            Cow::Borrowed(attachment.filename.as_deref().unwrap_or_default()),
+            Cow::Borrowed(attachment.transfer_name.as_deref().unwrap_or_default()),
+            Cow::Borrowed(attachment.uti.as_deref().unwrap_or_default()),
+            Cow::Owned(attachment.mime_type().as_mime_type()),
+            Cow::Owned(attachment.total_bytes.to_string()),
+            Cow::Owned(attachment.is_sticker.to_string()),
+            Cow::Owned((attachment.hide_attachment != 0).to_string()),
+            Cow::Borrowed(attachment.emoji_description.as_deref().unwrap_or_default()),
+            Cow::Borrowed(transcription.unwrap_or_default()),
+            Cow::Owned(
+                attachment
+                    .resolved_attachment_path(
+                        &self.config.options.platform,
+                        &self.config.options.db_path,
+                        self.config.options.attachment_root.as_deref(),
+                    )
+                    .unwrap_or_default(),
+            ),
+            Cow::Owned(
+                attachment
+                    .copied_path
+                    .as_ref()
+                    .map(|_| self.config.message_attachment_path(attachment))
+                    .unwrap_or_default(),
+            ),
+        ])
+    }
+
+    /// Build the `chats` row for a conversation
+    fn format_chat_row(&self, chat: &Chat) -> String {
+        let participants = self
+            .config
+            .chatroom_participants
+            .get(&chat.rowid)
+            .map(|handles| {
+                handles
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(LIST_SEPARATOR)
+            })
+            .unwrap_or_default();
+
+        self.format_row(&[
+            Cow::Owned(chat.rowid.to_string()),
+            optional(self.config.real_chatrooms.get(&chat.rowid)),
+            Cow::Borrowed(chat.chat_identifier.as_str()),
+            Cow::Owned(chat.service().to_string()),
+            Cow::Borrowed(chat.display_name().unwrap_or_default()),
+            Cow::Owned(self.chat_name(chat)),
+            Cow::Owned(participants),
+        ])
+    }
+
+    /// Build the `handles` row for a contact handle
+    fn format_handle_row(&self, handle: &Handle) -> String {
+        self.format_row(&[
+            Cow::Owned(handle.rowid.to_string()),
+            optional(self.config.real_participants.get(&handle.rowid)),
+            Cow::Borrowed(handle.id.as_str()),
+            Cow::Borrowed(handle.person_centric_id.as_deref().unwrap_or_default()),
+            Cow::Borrowed(self.config.who(Some(handle.rowid), false, &None)),
+        ])
+    }
+
+    /// Get the name of a conversation, falling back to its participants or its identifier
+    fn chat_name(&self, chat: &Chat) -> String {
+        if let Some(name) = self.config.chat_title(chat) {
+            return name.to_string();
+        }
+        match self.config.chatroom_participants.get(&chat.rowid) {
+            Some(participants) => participants
+                .iter()
+                .map(|handle_id| self.config.who(Some(*handle_id), false, &None))
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => chat.chat_identifier.clone(),
+        }
+    }
+
+    /// Format a timestamp in local time, leaving the field empty if it was never set
+    fn format_date(&self, date: i64) -> Cow<'static, str> {
+        if date == 0 {
+            return Cow::Borrowed("");
+        }
+        match get_local_time(&date, &self.config.offset) {
+            Ok(date) => Cow::Owned(date.format(DATE_FORMAT).to_string()),
+            Err(_) => Cow::Borrowed(""),
+        }
+    }
+
+    /// Join fields into a single record terminated by `CRLF`, as described in [RFC 4180](https://datatracker.ietf.org/doc/html/rfc4180)
+    fn format_row<T: AsRef<str>>(&self, fields: &[T]) -> String {
+        let mut row = fields
+            .iter()
+            .map(|field| escape_field(field.as_ref(), self.delimiter))
+            .collect::<Vec<_>>()
+            .join(&self.delimiter.to_string());
+        row.push_str("\r\n");
+        row
+    }
+}
+
+/// Quote a field if it contains the delimiter, a quote, or a line break, doubling any quotes inside it
+fn escape_field(field: &str, delimiter: char) -> Cow<'_, str> {
+    if field.contains([delimiter, '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Format an optional value, leaving the field empty if it is missing
+fn optional<T: ToString>(value: Option<T>) -> Cow<'static, str> {
+    value.map_or(Cow::Borrowed(""), |value| Cow::Owned(value.to_string()))
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        CSV, Config, Exporter, Options,
+        app::export_type::ExportType,
+        exporters::csv::{ATTACHMENT_COLUMNS, escape_field},
+    };
+    use imessage_database::tables::{chat::Chat, handle::Handle};
+
+    fn fake_chat() -> Chat {
+        Chat {
+            rowid: 1,
+            chat_identifier: "chat123".to_string(),
+            service_name: Some("iMessage".to_string()),
+            display_name: Some("Friends, \"family\"".to_string()),
+        }
+    }
+
+    #[test]
+    fn can_create() {
+        let options = Options::fake_options(ExportType::Csv);
+        let config = Config::fake_app(options);
+        let exporter = CSV::new(&config).unwrap();
+        assert!(exporter.messages.is_none());
+        assert!(exporter.attachments.is_none());
+        assert_eq!(exporter.delimiter, ',');
+    }
+
+    #[test]
+    fn can_create_tsv() {
+        let options = Options::fake_options(ExportType::Tsv);
+        let config = Config::fake_app(options);
+        let exporter = CSV::new(&config).unwrap();
+        assert_eq!(exporter.delimiter, '\t');
+        assert_eq!(exporter.export_type(), ExportType::Tsv);
+    }
+
+    #[test]
+    fn can_escape_plain_field() {
+        assert_eq!(escape_field("Hello world", ','), "Hello world");
+    }
+
+    #[test]
+    fn can_escape_delimiter() {
+        assert_eq!(escape_field("Hello, world", ','), "\"Hello, world\"");
+        assert_eq!(escape_field("Hello, world", '\t'), "Hello, world");
+        assert_eq!(escape_field("Hello\tworld", '\t'), "\"Hello\tworld\"");
+    }
+
+    #[test]
+    fn can_escape_quotes() {
+        assert_eq!(
+            escape_field("She said \"hi\"", ','),
+            "\"She said \"\"hi\"\"\""
+        );
+    }
+
+    #[test]
+    fn can_escape_line_breaks() {
+        assert_eq!(escape_field("Hello\nworld", ','), "\"Hello\nworld\"");
+        assert_eq!(escape_field("Hello\r\nworld", ','), "\"Hello\r\nworld\"");
+    }
+
+    #[test]
+    fn can_format_header_row() {
+        let options = Options::fake_options(ExportType::Csv);
+        let config = Config::fake_app(options);
+        let exporter = CSV::new(&config).unwrap();
+
+        let actual = exporter.format_row(&ATTACHMENT_COLUMNS);
+        let expected = "rowid,message_rowid,message_guid,filename,transfer_name,uti,mime_type,total_bytes,is_sticker,hide_attachment,emoji_description,transcription,resolved_path,exported_path\r\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_message_row() {
+        let options = Options::fake_options(ExportType::Csv);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "+15558675309".to_string());
+        let exporter = CSV::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.rowid = 42;
+        message.guid = "fake_guid".to_string();
+        message.handle_id = Some(999999);
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        // May 17, 2022  9:30:31 PM
+        message.date_read = 674530231992568192;
+        message.text = Some("Hello, \"world\"\nNew line".to_string());
+        message.thread_originator_guid = Some("parent_guid".to_string());
+
+        let actual = exporter.format_message_row(&message);
+        let expected = "42,fake_guid,,,999999,+15558675309,+15558675309,iMessage,2022-05-17 17:29:42,2022-05-17 18:30:31,,false,\"Hello, \"\"world\"\"\nNew line\",,normal,,0,parent_guid,false\r\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_message_row_from_me() {
+        let options = Options::fake_options(ExportType::Tsv);
+        let config = Config::fake_app(options);
+        let exporter = CSV::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+        message.is_from_me = true;
+        message.destination_caller_id = Some("me@example.com".to_string());
+        message.expressive_send_style_id =
+            Some("com.apple.MobileSMS.expressivesend.gentle".to_string());
+        message.text = Some("\u{FFFC}Hello, world".to_string());
+        message.num_attachments = 1;
+
+        let actual = exporter.format_message_row(&message);
+        let expected = "0\tfake_guid\t\t\t0\tme@example.com\tMe\tiMessage\t\t\t\ttrue\tHello, world\t\tnormal\tgentle\t1\t\tfalse\r\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_message_row_labels() {
+        let options = Options::fake_options(ExportType::Csv);
+        let config = Config::fake_app(options);
+        let exporter = CSV::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+        message.associated_message_type = Some(2000);
+        message.associated_message_guid = Some("p:0/parent_guid".to_string());
+        message.expressive_send_style_id =
+            Some("com.apple.messages.effect.CKShootingStarEffect".to_string());
+
+        let actual = exporter.format_message_row(&message);
+        let expected =
+            "0,fake_guid,,,0,,Unknown,iMessage,,,,false,,,tapback,shooting_star,0,,false\r\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_attachment_row() {
+        let options = Options::fake_options(ExportType::Csv);
+        let config = Config::fake_app(options);
+        let exporter = CSV::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.rowid = 42;
+        message.guid = "fake_guid".to_string();
+        let attachment = Config::fake_attachment();
+
+        let actual = exporter.format_attachment_row(&attachment, &message, Some("Hi"));
+        let expected = "0,42,fake_guid,a/b/c/d.jpg,d.jpg,public.png,image/png,100,false,false,,Hi,a/b/c/d.jpg,\r\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_chat_row() {
+        let options = Options::fake_options(ExportType::Csv);
+        let mut config = Config::fake_app(options);
+        config.real_chatrooms.insert(1, 0);
+        config
+            .chatroom_participants
+            .insert(1, BTreeSet::from([2, 3]));
+        let exporter = CSV::new(&config).unwrap();
+
+        let actual = exporter.format_chat_row(&fake_chat());
+        let expected =
+            "1,0,chat123,iMessage,\"Friends, \"\"family\"\"\",\"Friends, \"\"family\"\"\",2;3\r\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_handle_row() {
+        let options = Options::fake_options(ExportType::Csv);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(7, "+15558675309".to_string());
+        config.real_participants.insert(7, 5);
+        let exporter = CSV::new(&config).unwrap();
+
+        let handle = Handle {
+            rowid: 7,
+            id: "+15558675309".to_string(),
+            person_centric_id: None,
+        };
+
+        let actual = exporter.format_handle_row(&handle);
+        let expected = "7,5,+15558675309,,+15558675309\r\n";
+
+        assert_eq!(actual, expected);
+    }
+}
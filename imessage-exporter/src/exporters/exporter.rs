@@ -8,11 +8,13 @@ use imessage_database::{
         collaboration::CollaborationMessage,
         digital_touch::DigitalTouch,
         edited::EditedMessage,
+        expressives::{BubbleEffect, Expressive, ScreenEffect},
         handwriting::HandwrittenMessage,
         music::MusicMessage,
         placemark::PlacemarkMessage,
         text_effects::{Animation, Style, TextEffect, Unit},
         url::URLMessage,
+        variants::Variant,
     },
     tables::{
         attachment::Attachment,
@@ -27,6 +29,48 @@ use crate::app::{error::RuntimeError, runtime::Config};
 
 pub(crate) const ATTACHMENT_NO_FILENAME: &str = "Attachment missing name metadata!";
 
+// MARK: Labels
+/// Get the stable name that tabular exports use for the kind of a message
+///
+/// One of `normal`, `edited`, `tapback`, `app`, `shareplay`, or `unknown`.
+pub(super) fn variant_label(variant: &Variant) -> &'static str {
+    match variant {
+        Variant::Normal => "normal",
+        Variant::Edited => "edited",
+        Variant::Tapback(..) => "tapback",
+        Variant::App(_) => "app",
+        Variant::SharePlay => "shareplay",
+        Variant::Unknown(_) => "unknown",
+    }
+}
+
+/// Get the stable name that tabular exports use for the effect a message was sent with
+///
+/// Effects this version does not recognize keep their raw `expressive_send_style_id`.
+pub(super) fn expressive_label<'b>(expressive: &Expressive<'b>) -> Option<&'b str> {
+    match expressive {
+        Expressive::Bubble(effect) => Some(match effect {
+            BubbleEffect::Slam => "slam",
+            BubbleEffect::Loud => "loud",
+            BubbleEffect::Gentle => "gentle",
+            BubbleEffect::InvisibleInk => "invisible_ink",
+        }),
+        Expressive::Screen(effect) => Some(match effect {
+            ScreenEffect::Confetti => "confetti",
+            ScreenEffect::Echo => "echo",
+            ScreenEffect::Fireworks => "fireworks",
+            ScreenEffect::Balloons => "balloons",
+            ScreenEffect::Heart => "heart",
+            ScreenEffect::Lasers => "lasers",
+            ScreenEffect::ShootingStar => "shooting_star",
+            ScreenEffect::Sparkles => "sparkles",
+            ScreenEffect::Spotlight => "spotlight",
+        }),
+        Expressive::Unknown(effect) => Some(effect),
+        Expressive::None => None,
+    }
+}
+
 // MARK: Exporter
 /// Defines behavior for iterating over messages from the iMessage database and managing export files
 pub trait Exporter<'a> {
@@ -9,17 +9,30 @@ use std::{
         hash_map::Entry::{Occupied, Vacant},
     },
     fmt::Write as FmtWrite,
-    fs::File,
+    fs::{File, read},
     io::{BufWriter, Write},
+    path::PathBuf,
 };
 
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Local};
+
 use crate::{
     app::{
-        compatibility::attachment_manager::AttachmentManagerMode, error::RuntimeError,
-        progress::ExportProgress, runtime::Config, sanitizers::sanitize_html,
+        compatibility::attachment_manager::AttachmentManagerMode,
+        error::RuntimeError,
+        export_type::ExportType,
+        pagination::{PageCursor, Pagination},
+        progress::ExportProgress,
+        runtime::Config,
+        sanitizers::sanitize_html,
     },
-    exporters::exporter::{
-        ATTACHMENT_NO_FILENAME, BalloonFormatter, Exporter, MessageFormatter, TextEffectFormatter,
+    exporters::{
+        exporter::{
+            ATTACHMENT_NO_FILENAME, BalloonFormatter, Exporter, MessageFormatter,
+            TextEffectFormatter,
+        },
+        templates::{Templates, render},
     },
 };
 
@@ -58,9 +71,20 @@ use imessage_database::{
 };
 
 // MARK: HTML
-const HEADER: &str = "<html>\n<head>\n<meta charset=\"UTF-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">";
+const HEADER: &str = "<html>\n<head>\n";
+const META: &str = "<meta charset=\"UTF-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">";
 const FOOTER: &str = "</body></html>";
 const STYLE: &str = include_str!("resources/style.css");
+/// Name of the page that links to every exported conversation
+const INDEX: &str = "index";
+/// Number of characters of the last message shown on the index page
+const PREVIEW_LENGTH: usize = 100;
+/// Name of the page that searches every exported message
+const SEARCH: &str = "search";
+/// Name of the script that lists every searchable message
+const SEARCH_INDEX: &str = "search_index";
+/// Script that runs queries against the search index in the browser
+const SEARCH_SCRIPT: &str = include_str!("resources/search.js");
 
 #[derive(Debug, Clone)]
 /// [`EventType`] is used to track the start and end of HTML text attributes
@@ -80,6 +104,18 @@ pub struct HTML<'a> {
     pub files: HashMap<String, BufWriter<File>>,
     /// Writer instance for orphaned messages
     pub orphaned: BufWriter<File>,
+    /// User-supplied templates that wrap the built-in markup
+    templates: Templates,
+    /// Markup written before and after the messages in every file
+    layout: Layout,
+    /// Layout of paginated conversations, if pagination is enabled
+    pages: Option<Pages>,
+    /// Map of resolved chatroom file location to a summary of the conversation, used to build the index page
+    summaries: HashMap<String, Summary>,
+    /// Number of messages written to the orphaned file
+    orphaned_count: usize,
+    /// Writer for the search index, if search is enabled
+    search_index: Option<BufWriter<File>>,
     /// Progress Bar model for alerting the user about current export state
     pb: ExportProgress,
 }
@@ -92,10 +128,22 @@ impl<'a> Exporter<'a> for HTML<'a> {
         orphaned.set_extension("html");
         let file = File::options().append(true).create(true).open(&orphaned)?;
 
+        let templates = match &config.options.template_dir {
+            Some(dir) => Templates::load(dir)?,
+            None => Templates::default(),
+        };
+        let layout = Layout::new(config, &templates);
+
         Ok(HTML {
             config,
             files: HashMap::new(),
             orphaned: BufWriter::new(file),
+            templates,
+            layout,
+            pages: None,
+            summaries: HashMap::new(),
+            orphaned_count: 0,
+            search_index: None,
             pb: ExportProgress::new(),
         })
     }
@@ -108,7 +156,24 @@ impl<'a> Exporter<'a> for HTML<'a> {
         );
 
         // Write orphaned file headers
-        HTML::write_headers(&mut self.orphaned)?;
+        HTML::write_to_file(&mut self.orphaned, &self.layout.header)?;
+
+        // Pages are planned up front so navigation and threads can link across them
+        if let Some(pagination) = self.config.options.paginate {
+            eprintln!("Planning {pagination} pages...");
+            self.pages = Some(self.plan_pages(pagination)?);
+        }
+
+        // Searchable messages are written as they are exported
+        if self.config.options.search {
+            let mut path = self.config.options.export_path.clone();
+            path.push(SEARCH_INDEX);
+            path.set_extension("js");
+
+            let mut buf = BufWriter::new(File::create(&path)?);
+            HTML::write_to_file(&mut buf, "const SEARCH_INDEX = [\n")?;
+            self.search_index = Some(buf);
+        }
 
         // Keep track of current message ROWID
         let mut current_message_row = -1;
@@ -140,6 +205,12 @@ impl<'a> Exporter<'a> for HTML<'a> {
             // Generate the text of the message
             let _ = msg.generate_text(self.config.db());
 
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
+            }
+
             // Render the announcement in-line
             if msg.is_announcement() {
                 let announcement = self.format_announcement(&msg);
@@ -147,8 +218,10 @@ impl<'a> Exporter<'a> for HTML<'a> {
             }
             // Message replies and tapbacks are rendered in context, so no need to render them separately
             else if !msg.is_tapback() {
-                let message = self.format_message(&msg, 0)?;
+                let message = self.apply_message_template(&msg, self.format_message(&msg, 0)?);
                 HTML::write_to_file(self.get_or_create_file(&msg)?, &message)?;
+                self.summarize(&msg);
+                self.add_to_search_index(&msg)?;
             }
             current_message += 1;
             if current_message % 99 == 0 {
@@ -158,10 +231,36 @@ impl<'a> Exporter<'a> for HTML<'a> {
         self.pb.finish();
 
         eprintln!("Writing HTML footers...");
-        for buf in self.files.values_mut() {
-            HTML::write_to_file(buf, FOOTER)?;
+        for (filename, buf) in &mut self.files {
+            if let Some(pages) = &self.pages {
+                HTML::write_to_file(buf, &pages.format_nav(filename))?;
+            }
+            HTML::write_to_file(buf, &self.layout.footer)?;
+        }
+        HTML::write_to_file(&mut self.orphaned, &self.layout.footer)?;
+
+        if let Some(pages) = &self.pages {
+            eprintln!("Writing tables of contents...");
+            for filename in pages.conversations.keys() {
+                let mut path = self.config.options.export_path.clone();
+                path.push(filename);
+                path.set_extension("html");
+
+                let mut buf = BufWriter::new(File::create(&path)?);
+                HTML::write_to_file(&mut buf, &self.layout.header)?;
+                HTML::write_to_file(&mut buf, &pages.format_contents(filename))?;
+                HTML::write_to_file(&mut buf, &self.layout.footer)?;
+            }
+        }
+
+        eprintln!("Writing index...");
+        self.write_index()?;
+
+        if let Some(mut buf) = self.search_index.take() {
+            eprintln!("Writing search page...");
+            HTML::write_to_file(&mut buf, "];\n")?;
+            self.write_search()?;
         }
-        HTML::write_to_file(&mut self.orphaned, FOOTER)?;
 
         Ok(())
     }
@@ -174,6 +273,15 @@ impl<'a> Exporter<'a> for HTML<'a> {
         match self.config.conversation(message) {
             Some((chatroom, _)) => {
                 let filename = self.config.filename(chatroom);
+                if let Some(pages) = &mut self.pages {
+                    return pages.get_or_create_page(
+                        &mut self.files,
+                        self.config,
+                        &self.layout,
+                        message,
+                        filename,
+                    );
+                }
                 match self.files.entry(filename) {
                     Occupied(entry) => Ok(entry.into_mut()),
                     Vacant(entry) => {
@@ -191,7 +299,7 @@ impl<'a> Exporter<'a> for HTML<'a> {
 
                         // Write headers if the file does not exist
                         if !file_exists {
-                            let _ = HTML::write_headers(&mut buf);
+                            let _ = HTML::write_to_file(&mut buf, &self.layout.header);
                         }
 
                         Ok(entry.insert(buf))
@@ -215,8 +323,8 @@ impl<'a> MessageFormatter<'a> for HTML<'a> {
         let mut formatted_message = String::new();
 
         // Message div
-        if message.is_reply() && indent_size == 0 {
-            // Add an ID for any top-level message so we can link to them in threads
+        if (message.is_reply() || self.config.options.search) && indent_size == 0 {
+            // Add an ID for any top-level message so we can link to them in threads and search results
             self.add_line(
                 &mut formatted_message,
                 &format!("<div class=\"message\", id=\"r-{}\">", message.guid),
@@ -260,7 +368,8 @@ impl<'a> MessageFormatter<'a> for HTML<'a> {
                 self.add_line(
                     &mut formatted_message,
                     &format!(
-                        "<a title=\"View in context\" href=\"#r-{}\">⇲</a>",
+                        "<a title=\"View in context\" href=\"{}#r-{}\">⇲</a>",
+                        self.page_of(&message.guid),
                         message.guid
                     ),
                     "<span class=\"reply_anchor\">",
@@ -271,7 +380,11 @@ impl<'a> MessageFormatter<'a> for HTML<'a> {
                 self.add_line(
                     &mut formatted_message,
                     &format!(
-                        "<a title=\"View in thread\" href=\"#{}\">⇱</a>",
+                        "<a title=\"View in thread\" href=\"{}#{}\">⇱</a>",
+                        message
+                            .thread_originator_guid
+                            .as_deref()
+                            .map_or("", |guid| self.page_of(guid)),
                         message.guid
                     ),
                     "<span class=\"reply_anchor\">",
@@ -443,7 +556,7 @@ impl<'a> MessageFormatter<'a> for HTML<'a> {
                 BubbleComponent::App => match self.format_app(message, &mut attachments, "") {
                     Ok(ok_bubble) => self.add_line(
                         &mut formatted_message,
-                        &ok_bubble,
+                        &self.apply_balloon_template(message, ok_bubble),
                         "<div class=\"app\">",
                         "</div>",
                     ),
@@ -494,7 +607,7 @@ impl<'a> MessageFormatter<'a> for HTML<'a> {
                         if !formatted.is_empty() {
                             self.add_line(
                                 &mut formatted_tapbacks,
-                                &self.format_tapback(tapback)?,
+                                &self.apply_tapback_template(tapback, formatted),
                                 "<div class=\"tapback\">",
                                 "</div>",
                             );
@@ -584,8 +697,13 @@ impl<'a> MessageFormatter<'a> for HTML<'a> {
             self.pb.set_default_style();
         }
 
-        // Build a relative filepath from the fully qualified one on the `Attachment`
-        let embed_path = self.config.message_attachment_path(attachment);
+        // Standalone exports carry the file's data in place of its path
+        let embedded = self.data_uri(attachment);
+        let is_embedded = embedded.is_some();
+
+        // Otherwise, build a relative filepath from the fully qualified one on the `Attachment`
+        let embed_path =
+            embedded.unwrap_or_else(|| self.config.message_attachment_path(attachment));
 
         Ok(match attachment.mime_type() {
             MediaType::Image(_) => {
@@ -595,6 +713,10 @@ impl<'a> MessageFormatter<'a> for HTML<'a> {
                     format!("<img src=\"{embed_path}\" loading=\"lazy\">")
                 }
             }
+            MediaType::Video(_) if is_embedded => {
+                // Embedded data already declares its type, so a second source would only double the file size
+                format!("<video controls> <source src=\"{embed_path}\"> </video>")
+            }
             MediaType::Video(media_type) => {
                 // See https://github.com/ReagentX/imessage-exporter/issues/73 for why duplicate the source tag
                 format!(
@@ -1646,17 +1768,106 @@ impl HTML<'_> {
         }
     }
 
-    fn write_headers(file: &mut BufWriter<File>) -> Result<(), RuntimeError> {
-        // Write file header
-        HTML::write_to_file(file, HEADER)?;
+    /// Wrap a formatted top-level message in the user's message template, if any
+    fn apply_message_template(&self, message: &Message, formatted: String) -> String {
+        match &self.templates.message {
+            Some(template) => {
+                let sender = self.config.who(
+                    message.handle_id,
+                    message.is_from_me(),
+                    &message.destination_caller_id,
+                );
+                let date = format(&message.date(&self.config.offset));
+                let service = message.service().to_string();
+                render(
+                    template,
+                    &[
+                        ("message", &formatted),
+                        ("guid", &message.guid),
+                        ("sender", &sanitize_html(sender)),
+                        ("date", &date),
+                        ("service", &service),
+                        (
+                            "direction",
+                            if message.is_from_me() {
+                                "sent"
+                            } else {
+                                "received"
+                            },
+                        ),
+                    ],
+                )
+            }
+            None => formatted,
+        }
+    }
 
-        // Write CSS
-        HTML::write_to_file(file, "<style>\n")?;
-        HTML::write_to_file(file, STYLE)?;
-        HTML::write_to_file(file, "\n</style>")?;
-        HTML::write_to_file(file, "<link rel=\"stylesheet\" href=\"style.css\">")?;
-        HTML::write_to_file(file, "\n</head>\n<body>\n")?;
-        Ok(())
+    /// Wrap a formatted app balloon in the user's balloon template, if any
+    fn apply_balloon_template(&self, message: &Message, formatted: String) -> String {
+        match &self.templates.balloon {
+            Some(template) => render(
+                template,
+                &[
+                    ("balloon", &formatted),
+                    (
+                        "bundle_id",
+                        &sanitize_html(message.balloon_bundle_id.as_deref().unwrap_or_default()),
+                    ),
+                ],
+            ),
+            None => formatted,
+        }
+    }
+
+    /// Wrap a formatted tapback in the user's tapback template, if any
+    fn apply_tapback_template(&self, tapback: &Message, formatted: String) -> String {
+        match &self.templates.tapback {
+            Some(template) => {
+                let sender = self.config.who(
+                    tapback.handle_id,
+                    tapback.is_from_me(),
+                    &tapback.destination_caller_id,
+                );
+                render(
+                    template,
+                    &[("tapback", &formatted), ("sender", &sanitize_html(sender))],
+                )
+            }
+            None => formatted,
+        }
+    }
+
+    /// Build a `data:` URI for an image, video, or audio attachment when exporting standalone files
+    ///
+    /// Runs after the attachment manager so converted files are embedded instead of the originals.
+    /// Returns [`None`] if the file is missing or larger than the configured limit, so the caller can link to it instead.
+    fn data_uri(&self, attachment: &Attachment) -> Option<String> {
+        let limit = self.config.options.standalone?;
+        let mime_type = match attachment.mime_type() {
+            MediaType::Image(_) | MediaType::Video(_) | MediaType::Audio(_) => {
+                attachment.mime_type().as_mime_type()
+            }
+            _ => return None,
+        };
+
+        let path = match &attachment.copied_path {
+            Some(path) => path.clone(),
+            None => PathBuf::from(attachment.resolved_attachment_path(
+                &self.config.options.platform,
+                &self.config.options.db_path,
+                self.config.options.attachment_root.as_deref(),
+            )?),
+        };
+
+        let metadata = path.metadata().ok()?;
+        if !metadata.is_file() || metadata.len() > limit {
+            return None;
+        }
+
+        Some(format!(
+            "data:{mime_type};base64,{}",
+            STANDARD.encode(read(&path).ok()?)
+        ))
     }
 
     fn edited_to_html(&self, timestamp: &str, text: &str, last: bool) -> String {
@@ -1799,10 +2010,535 @@ impl HTML<'_> {
     }
 }
 
+// MARK: Pages
+/// A single page of a paginated conversation
+#[derive(Debug, Default)]
+struct Page {
+    /// Date of the first message on the page
+    first: Option<DateTime<Local>>,
+    /// Date of the last message on the page
+    last: Option<DateTime<Local>>,
+}
+
+/// Layout of every paginated conversation, planned before any messages are written
+#[derive(Debug)]
+struct Pages {
+    /// How conversations are divided into pages
+    pagination: Pagination,
+    /// Map of conversation filename to the pages it is split into
+    conversations: HashMap<String, Vec<Page>>,
+    /// Map of message GUID to the page it is written on, for messages that are part of a thread
+    locations: HashMap<String, String>,
+    /// Map of conversation filename to the position of the next message written to it
+    cursors: HashMap<String, PageCursor>,
+    /// Map of conversation filename to the page currently open for writing
+    open: HashMap<String, usize>,
+}
+
+impl Pages {
+    fn new(pagination: Pagination) -> Self {
+        Pages {
+            pagination,
+            conversations: HashMap::new(),
+            locations: HashMap::new(),
+            cursors: HashMap::new(),
+            open: HashMap::new(),
+        }
+    }
+
+    /// Place the next message of a conversation, returning the page it belongs on
+    fn advance(&mut self, filename: &str, date: Option<&DateTime<Local>>) -> usize {
+        self.cursors
+            .entry(filename.to_string())
+            .or_default()
+            .advance(&self.pagination, date)
+    }
+
+    /// Get the writer for the page of a conversation that a message belongs on, opening a new page if needed
+    fn get_or_create_page<'b>(
+        &mut self,
+        files: &'b mut HashMap<String, BufWriter<File>>,
+        config: &Config,
+        layout: &Layout,
+        message: &Message,
+        filename: String,
+    ) -> Result<&'b mut BufWriter<File>, RuntimeError> {
+        let date = message.date(&config.offset).ok();
+        let number = self.advance(&filename, date.as_ref());
+
+        match files.entry(filename) {
+            Occupied(entry) => {
+                if self.open.get(entry.key()) != Some(&number) {
+                    // Finish the previous page before moving on to the next one
+                    let filename = entry.key().clone();
+                    let buf = entry.into_mut();
+                    HTML::write_to_file(buf, &self.format_nav(&filename))?;
+                    HTML::write_to_file(buf, &layout.footer)?;
+
+                    *buf = self.open_page(config, layout, &filename, number)?;
+                    return Ok(buf);
+                }
+                Ok(entry.into_mut())
+            }
+            Vacant(entry) => {
+                let buf = self.open_page(config, layout, entry.key(), number)?;
+                Ok(entry.insert(buf))
+            }
+        }
+    }
+
+    /// Create the file for a page of a conversation and write its headers
+    fn open_page(
+        &mut self,
+        config: &Config,
+        layout: &Layout,
+        filename: &str,
+        number: usize,
+    ) -> Result<BufWriter<File>, RuntimeError> {
+        self.open.insert(filename.to_string(), number);
+
+        let mut path = config.options.export_path.clone();
+        path.push(page_filename(filename, number));
+
+        let mut buf = BufWriter::new(File::create(&path)?);
+        HTML::write_to_file(&mut buf, &layout.header)?;
+        HTML::write_to_file(&mut buf, &self.format_nav(filename))?;
+        Ok(buf)
+    }
+
+    /// Format the links to the neighbors of the currently open page of a conversation
+    fn format_nav(&self, filename: &str) -> String {
+        let page = self.open.get(filename).copied().unwrap_or(1);
+        let total = self.conversations.get(filename).map_or(page, Vec::len);
+
+        let mut nav = String::from("<nav class=\"pages\">");
+        if page > 1 {
+            let _ = write!(
+                nav,
+                "<a href=\"{}\">← Previous</a> ",
+                page_href(filename, page - 1)
+            );
+        }
+        let _ = write!(
+            nav,
+            "<a href=\"{}\">Contents</a> <span class=\"page_number\">Page {page} of {total}</span>",
+            encode_href(&contents_filename(filename))
+        );
+        if page < total {
+            let _ = write!(
+                nav,
+                " <a href=\"{}\">Next →</a>",
+                page_href(filename, page + 1)
+            );
+        }
+        nav.push_str("</nav>\n");
+        nav
+    }
+
+    /// Format the table of contents for a conversation, linking to every page
+    fn format_contents(&self, filename: &str) -> String {
+        let mut contents = format!(
+            "<h1>{}</h1>\n<ol class=\"contents\">\n",
+            sanitize_html(filename.trim_end_matches(ExportType::Html.extension()))
+        );
+        for (idx, page) in self
+            .conversations
+            .get(filename)
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            let _ = writeln!(
+                contents,
+                "<li><a href=\"{}\">{}</a></li>",
+                page_href(filename, idx + 1),
+                self.format_period(page, idx + 1)
+            );
+        }
+        contents.push_str("</ol>\n");
+        contents
+    }
+
+    /// Describe the messages a page contains
+    fn format_period(&self, page: &Page, number: usize) -> String {
+        match (&self.pagination, page.first, page.last) {
+            (Pagination::Month, Some(first), _) => first.format("%B %Y").to_string(),
+            (Pagination::Messages(_), Some(first), Some(last)) => {
+                let (first, last) = (first.format("%b %d, %Y"), last.format("%b %d, %Y"));
+                format!("Page {number}: {first} – {last}")
+            }
+            _ => format!("Page {number}"),
+        }
+    }
+}
+
+/// Get the name of the file that lists every page of a conversation
+fn contents_filename(filename: &str) -> String {
+    let mut path = PathBuf::from(filename);
+    path.set_extension("html");
+    path.display().to_string()
+}
+
+/// Get the name of the file a page of a conversation is written to
+fn page_filename(filename: &str, page: usize) -> String {
+    format!(
+        "{}-{page}{}",
+        filename.trim_end_matches(ExportType::Html.extension()),
+        ExportType::Html.extension()
+    )
+}
+
+/// Get a link to a page of a conversation
+fn page_href(filename: &str, page: usize) -> String {
+    encode_href(&page_filename(filename, page))
+}
+
+/// Escape the characters in a filename that would otherwise change the meaning of a relative link
+fn encode_href(filename: &str) -> String {
+    let mut href = String::with_capacity(filename.len());
+    for c in filename.chars() {
+        match c {
+            '%' => href.push_str("%25"),
+            '#' => href.push_str("%23"),
+            ' ' => href.push_str("%20"),
+            '&' => href.push_str("&amp;"),
+            '\'' => href.push_str("%27"),
+            c => href.push(c),
+        }
+    }
+    href
+}
+
+impl HTML<'_> {
+    /// Walk every message once to determine which page it belongs on
+    ///
+    /// Mirrors the filtering in [`HTML::iter_messages`] so the planned layout matches what is written.
+    fn plan_pages(&self, pagination: Pagination) -> Result<Pages, RuntimeError> {
+        let mut pages = Pages::new(pagination);
+
+        // Keep track of current message ROWID
+        let mut current_message_row = -1;
+
+        let mut statement =
+            Message::stream_rows(self.config.db(), &self.config.options.query_context)?;
+
+        let messages = statement
+            .query_map([], |row| Ok(Message::from_row(row)))
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
+
+        for message in messages {
+            let mut msg = Message::extract(message)?;
+
+            if msg.rowid == current_message_row {
+                continue;
+            }
+            current_message_row = msg.rowid;
+
+            // Skip messages that do not match the filters that require decoded text
+            let _ = msg.generate_text(self.config.db());
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                continue;
+            }
+
+            if msg.is_tapback() && !msg.is_announcement() {
+                continue;
+            }
+
+            // Orphaned messages are not paginated
+            let Some((chatroom, _)) = self.config.conversation(&msg) else {
+                continue;
+            };
+            let filename = self.config.filename(chatroom);
+            let date = msg.date(&self.config.offset).ok();
+            let number = pages.advance(&filename, date.as_ref());
+
+            let conversation = pages.conversations.entry(filename.clone()).or_default();
+            if conversation.len() < number {
+                conversation.push(Page {
+                    first: date,
+                    last: date,
+                });
+            } else if let Some(page) = conversation.last_mut() {
+                page.first = page.first.or(date);
+                page.last = date.or(page.last);
+            }
+
+            // Threads link between replies and the messages they respond to
+            if msg.is_reply() || msg.has_replies() {
+                pages
+                    .locations
+                    .insert(msg.guid, page_href(&filename, number));
+            }
+        }
+
+        // Writing starts again from the first page
+        pages.cursors.clear();
+        Ok(pages)
+    }
+
+    /// Get a link to the page a message in a thread is written on, or an empty string if it is on the same page
+    fn page_of(&self, guid: &str) -> &str {
+        self.pages
+            .as_ref()
+            .and_then(|pages| pages.locations.get(guid))
+            .map_or("", String::as_str)
+    }
+}
+
+// MARK: Index
+/// Summary of a conversation file, used to build the index page
+#[derive(Debug, Default)]
+struct Summary {
+    /// Name of the conversation
+    title: String,
+    /// Names of everyone in the conversation other than the database owner
+    participants: String,
+    /// Service the conversation uses
+    service: String,
+    /// Number of messages in the conversation
+    count: usize,
+    /// Date of the first message
+    first: Option<DateTime<Local>>,
+    /// Date of the last message
+    last: Option<DateTime<Local>>,
+    /// Start of the text of the last message
+    preview: String,
+}
+
+impl HTML<'_> {
+    /// Add a message to the summary of the conversation it belongs to
+    fn summarize(&mut self, message: &Message) {
+        let Some((chatroom, _)) = self.config.conversation(message) else {
+            self.orphaned_count += 1;
+            return;
+        };
+
+        let summary = self
+            .summaries
+            .entry(self.config.filename(chatroom))
+            .or_insert_with(|| {
+                let participants = self
+                    .config
+                    .chatroom_participants
+                    .get(&chatroom.rowid)
+                    .into_iter()
+                    .flatten()
+                    .map(|participant| self.config.who(Some(*participant), false, &None))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Summary {
+                    title: self.config.chat_title(chatroom).map_or_else(
+                        || {
+                            if participants.is_empty() {
+                                chatroom.chat_identifier.clone()
+                            } else {
+                                participants.clone()
+                            }
+                        },
+                        str::to_string,
+                    ),
+                    participants,
+                    service: chatroom.service().to_string(),
+                    ..Default::default()
+                }
+            });
+
+        let date = message.date(&self.config.offset).ok();
+        summary.count += 1;
+        summary.first = summary.first.or(date);
+        summary.last = date.or(summary.last);
+
+        let text = message
+            .text
+            .as_deref()
+            .unwrap_or_default()
+            .replace('\u{FFFC}', "");
+        let text = text.trim();
+        if !text.is_empty() {
+            summary.preview = text.chars().take(PREVIEW_LENGTH).collect();
+            if text.chars().count() > PREVIEW_LENGTH {
+                summary.preview.push('…');
+            }
+        } else if message.num_attachments > 0 {
+            summary.preview = String::from("Attachment");
+        }
+    }
+
+    /// Write the page that links to every exported conversation, most recent first
+    fn write_index(&self) -> Result<(), RuntimeError> {
+        let mut path = self.config.options.export_path.clone();
+        path.push(INDEX);
+        path.set_extension("html");
+
+        let mut buf = BufWriter::new(File::create(&path)?);
+        HTML::write_to_file(&mut buf, &self.layout.header)?;
+        HTML::write_to_file(&mut buf, &self.format_index())?;
+        HTML::write_to_file(&mut buf, &self.layout.footer)
+    }
+
+    /// Format the table of conversations shown on the index page
+    fn format_index(&self) -> String {
+        let mut conversations: Vec<(&String, &Summary)> = self.summaries.iter().collect();
+        conversations.sort_by(|(a_file, a), (b_file, b)| {
+            b.last.cmp(&a.last).then_with(|| a_file.cmp(b_file))
+        });
+
+        let mut index = String::new();
+        if self.config.options.search {
+            let _ = writeln!(
+                index,
+                "<p class=\"search\"><a href=\"{SEARCH}.html\">Search messages</a></p>"
+            );
+        }
+        index.push_str(
+            "<h1>Conversations</h1>\n<table class=\"index\">\n<thead><tr><th>Conversation</th><th>Participants</th><th>Service</th><th>Messages</th><th>Dates</th><th>Last message</th></tr></thead>\n<tbody>\n",
+        );
+        for (filename, summary) in conversations {
+            let dates = match (summary.first, summary.last) {
+                (Some(first), Some(last)) => format!(
+                    "{} – {}",
+                    first.format("%b %d, %Y"),
+                    last.format("%b %d, %Y")
+                ),
+                _ => String::new(),
+            };
+            let _ = writeln!(
+                index,
+                "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{dates}</td><td>{}</td></tr>",
+                encode_href(filename),
+                sanitize_html(&summary.title),
+                sanitize_html(&summary.participants),
+                summary.service,
+                summary.count,
+                sanitize_html(&summary.preview),
+            );
+        }
+        index.push_str("</tbody>\n</table>\n");
+
+        if self.orphaned_count > 0 {
+            let _ = writeln!(
+                index,
+                "<p class=\"orphaned\"><a href=\"{ORPHANED}.html\">Messages without a conversation</a> ({})</p>",
+                self.orphaned_count
+            );
+        }
+        index
+    }
+}
+
+// MARK: Search
+impl HTML<'_> {
+    /// Add the text of a message to the search index, linking to where it was written
+    fn add_to_search_index(&mut self, message: &Message) -> Result<(), RuntimeError> {
+        if self.search_index.is_none() {
+            return Ok(());
+        }
+
+        let text = message
+            .text
+            .as_deref()
+            .unwrap_or_default()
+            .replace('\u{FFFC}', "");
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let (conversation, file) = match self.config.conversation(message) {
+            Some((chatroom, _)) => {
+                let filename = self.config.filename(chatroom);
+                let title = self
+                    .summaries
+                    .get(&filename)
+                    .map_or_else(|| filename.clone(), |summary| summary.title.clone());
+                let file = match &self.pages {
+                    Some(pages) => {
+                        page_href(&filename, pages.open.get(&filename).copied().unwrap_or(1))
+                    }
+                    None => encode_href(&filename),
+                };
+                (title, file)
+            }
+            None => (String::from("Orphaned"), format!("{ORPHANED}.html")),
+        };
+
+        let row = (
+            text,
+            self.config.who(
+                message.handle_id,
+                message.is_from_me(),
+                &message.destination_caller_id,
+            ),
+            format(&message.date(&self.config.offset)),
+            conversation,
+            format!("{file}#r-{}", message.guid),
+        );
+
+        if let Some(buf) = &mut self.search_index {
+            HTML::write_to_file(buf, &serde_json::to_string(&row).unwrap_or_default())?;
+            HTML::write_to_file(buf, ",\n")?;
+        }
+        Ok(())
+    }
+
+    /// Write the page that searches the messages in the search index
+    fn write_search(&self) -> Result<(), RuntimeError> {
+        let mut path = self.config.options.export_path.clone();
+        path.push(SEARCH);
+        path.set_extension("html");
+
+        let mut buf = BufWriter::new(File::create(&path)?);
+        HTML::write_to_file(&mut buf, &self.layout.header)?;
+        HTML::write_to_file(
+            &mut buf,
+            &format!(
+                "<h1>Search</h1>\n<p><a href=\"{INDEX}.html\">All conversations</a></p>\n<input id=\"query\" type=\"search\" placeholder=\"Search messages\" autofocus>\n<p id=\"summary\"></p>\n<div id=\"results\"></div>\n<script src=\"{SEARCH_INDEX}.js\"></script>\n<script>\n{SEARCH_SCRIPT}</script>\n"
+            ),
+        )?;
+        HTML::write_to_file(&mut buf, &self.layout.footer)
+    }
+}
+
+// MARK: Layout
+/// Markup written before and after the messages in every HTML file
+#[derive(Debug)]
+struct Layout {
+    /// Everything before the first message
+    header: String,
+    /// Everything after the last message
+    footer: String,
+}
+
+impl Layout {
+    fn new(config: &Config, templates: &Templates) -> Self {
+        // Built-in styles come first so user styles can override them
+        let mut head = format!("{META}<style>\n{STYLE}\n</style>");
+        if let Some(style) = &templates.style {
+            let _ = write!(head, "\n<style>\n{style}\n</style>");
+        }
+        // Standalone files cannot depend on a stylesheet next to them
+        if config.options.standalone.is_none() {
+            head.push_str("<link rel=\"stylesheet\" href=\"style.css\">");
+        }
+
+        match templates.page_layout(&head) {
+            Some((header, footer)) => Layout { header, footer },
+            None => Layout {
+                header: format!("{HEADER}{head}\n</head>\n<body>\n"),
+                footer: FOOTER.to_string(),
+            },
+        }
+    }
+}
+
 // MARK: Tests
 #[cfg(test)]
 mod tests {
-    use std::{env::current_dir, path::PathBuf};
+    use std::{
+        env::{current_dir, temp_dir},
+        fs::write,
+        path::PathBuf,
+    };
 
     use crate::{
         Config, Exporter, HTML, Options,
@@ -2525,72 +3261,163 @@ mod tests {
     }
 
     #[test]
-    fn can_format_html_attachment_macos_invalid_disabled() {
+    fn can_format_html_attachment_standalone() {
         // Create exporter
-        let options = Options::fake_options(ExportType::Html);
+        let mut options = Options::fake_options(ExportType::Html);
+        options.standalone = Some(1024);
         let config = Config::fake_app(options);
         let exporter = HTML::new(&config).unwrap();
 
         let message = Config::fake_message();
 
+        let path = temp_dir().join("imessage-exporter-html-standalone.png");
+        write(&path, b"Hello world").unwrap();
+
         let mut attachment = Config::fake_attachment();
-        attachment.filename = None;
-        attachment.transfer_name = None;
+        attachment.filename = Some(path.display().to_string());
 
-        let actual =
-            exporter.format_attachment(&mut attachment, &message, &AttachmentMeta::default());
+        let actual = exporter
+            .format_attachment(&mut attachment, &message, &AttachmentMeta::default())
+            .unwrap();
 
-        assert_eq!(actual, Err("Attachment missing name metadata!"));
+        assert_eq!(
+            actual,
+            "<img src=\"data:image/png;base64,SGVsbG8gd29ybGQ=\" loading=\"lazy\">"
+        );
     }
 
     #[test]
-    fn can_format_html_attachment_macos_invalid_clone() {
+    fn can_format_html_attachment_standalone_too_large() {
         // Create exporter
         let mut options = Options::fake_options(ExportType::Html);
-        options.attachment_manager.mode = AttachmentManagerMode::Clone;
-
+        options.standalone = Some(4);
         let config = Config::fake_app(options);
         let exporter = HTML::new(&config).unwrap();
 
         let message = Config::fake_message();
 
+        let path = temp_dir().join("imessage-exporter-html-standalone-large.png");
+        write(&path, b"Hello world").unwrap();
+
         let mut attachment = Config::fake_attachment();
-        attachment.filename = None;
-        attachment.transfer_name = None;
+        attachment.filename = Some(path.display().to_string());
 
-        let actual =
-            exporter.format_attachment(&mut attachment, &message, &AttachmentMeta::default());
+        let actual = exporter
+            .format_attachment(&mut attachment, &message, &AttachmentMeta::default())
+            .unwrap();
 
-        assert_eq!(actual, Err("Attachment missing name metadata!"));
+        assert_eq!(
+            actual,
+            format!("<img src=\"{}\" loading=\"lazy\">", path.display())
+        );
     }
 
     #[test]
-    fn can_format_html_attachment_ios() {
+    fn can_format_html_attachment_standalone_video() {
         // Create exporter
-        let options = Options::fake_options(ExportType::Html);
-        let mut config = Config::fake_app(options);
-        config.options.no_lazy = true;
-        config.options.platform = Platform::iOS;
+        let mut options = Options::fake_options(ExportType::Html);
+        options.standalone = Some(1024);
+        let config = Config::fake_app(options);
         let exporter = HTML::new(&config).unwrap();
+
         let message = Config::fake_message();
 
+        let path = temp_dir().join("imessage-exporter-html-standalone.mp4");
+        write(&path, b"Hello world").unwrap();
+
         let mut attachment = Config::fake_attachment();
+        attachment.filename = Some(path.display().to_string());
+        attachment.mime_type = Some("video/mp4".to_string());
 
         let actual = exporter
             .format_attachment(&mut attachment, &message, &AttachmentMeta::default())
             .unwrap();
 
-        assert!(actual.ends_with("33/33c81da8ae3194fc5a0ea993ef6ffe0b048baedb\">"));
+        assert_eq!(
+            actual,
+            "<video controls> <source src=\"data:video/mp4;base64,SGVsbG8gd29ybGQ=\"> </video>"
+        );
     }
 
     #[test]
-    fn can_format_html_attachment_ios_invalid_disabled() {
-        // Create exporter
-        let options = Options::fake_options(ExportType::Html);
+    fn can_write_headers_standalone() {
+        let mut options = Options::fake_options(ExportType::Html);
+        options.standalone = Some(1024);
         let config = Config::fake_app(options);
         let exporter = HTML::new(&config).unwrap();
 
-        let message = Config::fake_message();
+        let actual = &exporter.layout.header;
+        assert!(actual.contains("<style>"));
+        assert!(!actual.contains("href=\"style.css\""));
+    }
+
+    #[test]
+    fn can_format_html_attachment_macos_invalid_disabled() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = None;
+        attachment.transfer_name = None;
+
+        let actual =
+            exporter.format_attachment(&mut attachment, &message, &AttachmentMeta::default());
+
+        assert_eq!(actual, Err("Attachment missing name metadata!"));
+    }
+
+    #[test]
+    fn can_format_html_attachment_macos_invalid_clone() {
+        // Create exporter
+        let mut options = Options::fake_options(ExportType::Html);
+        options.attachment_manager.mode = AttachmentManagerMode::Clone;
+
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = None;
+        attachment.transfer_name = None;
+
+        let actual =
+            exporter.format_attachment(&mut attachment, &message, &AttachmentMeta::default());
+
+        assert_eq!(actual, Err("Attachment missing name metadata!"));
+    }
+
+    #[test]
+    fn can_format_html_attachment_ios() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let mut config = Config::fake_app(options);
+        config.options.no_lazy = true;
+        config.options.platform = Platform::iOS;
+        let exporter = HTML::new(&config).unwrap();
+        let message = Config::fake_message();
+
+        let mut attachment = Config::fake_attachment();
+
+        let actual = exporter
+            .format_attachment(&mut attachment, &message, &AttachmentMeta::default())
+            .unwrap();
+
+        assert!(actual.ends_with("33/33c81da8ae3194fc5a0ea993ef6ffe0b048baedb\">"));
+    }
+
+    #[test]
+    fn can_format_html_attachment_ios_invalid_disabled() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let message = Config::fake_message();
 
         let mut attachment = Config::fake_attachment();
         attachment.filename = None;
@@ -3924,3 +4751,651 @@ mod edited_tests {
         assert_eq!(actual, expected);
     }
 }
+
+#[cfg(test)]
+mod pagination_tests {
+    use std::{
+        env::temp_dir,
+        fs::{copy, create_dir_all, read_to_string, remove_dir_all},
+    };
+
+    use chrono::{Local, TimeZone};
+    use rusqlite::Connection;
+
+    use crate::{
+        Config, Exporter, HTML, Options,
+        app::{export_type::ExportType, pagination::Pagination},
+        exporters::{
+            exporter::MessageFormatter,
+            html::{Page, Pages, encode_href, page_filename},
+        },
+    };
+    use imessage_database::tables::chat::Chat;
+
+    fn fake_pages(pagination: Pagination, total: usize) -> Pages {
+        let mut pages = Pages::new(pagination);
+        pages.conversations.insert(
+            "Friends - 1.html".to_string(),
+            (0..total).map(|_| Page::default()).collect(),
+        );
+        pages
+    }
+
+    #[test]
+    fn can_get_page_filename() {
+        assert_eq!(page_filename("Friends - 1.html", 2), "Friends - 1-2.html");
+    }
+
+    #[test]
+    fn can_encode_href() {
+        assert_eq!(
+            encode_href("A & B #1 50%.html"),
+            "A%20&amp;%20B%20%231%2050%25.html"
+        );
+    }
+
+    #[test]
+    fn can_format_nav_first() {
+        let mut pages = fake_pages(Pagination::Messages(10), 3);
+        pages.open.insert("Friends - 1.html".to_string(), 1);
+
+        let actual = pages.format_nav("Friends - 1.html");
+        let expected = "<nav class=\"pages\"><a href=\"Friends%20-%201.html\">Contents</a> <span class=\"page_number\">Page 1 of 3</span> <a href=\"Friends%20-%201-2.html\">Next →</a></nav>\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_nav_last() {
+        let mut pages = fake_pages(Pagination::Messages(10), 3);
+        pages.open.insert("Friends - 1.html".to_string(), 3);
+
+        let actual = pages.format_nav("Friends - 1.html");
+        let expected = "<nav class=\"pages\"><a href=\"Friends%20-%201-2.html\">← Previous</a> <a href=\"Friends%20-%201.html\">Contents</a> <span class=\"page_number\">Page 3 of 3</span></nav>\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_contents_by_month() {
+        let mut pages = fake_pages(Pagination::Month, 0);
+        pages.conversations.insert(
+            "Friends - 1.html".to_string(),
+            vec![
+                Page {
+                    first: Local.with_ymd_and_hms(2022, 5, 17, 12, 0, 0).single(),
+                    last: Local.with_ymd_and_hms(2022, 5, 20, 12, 0, 0).single(),
+                },
+                Page::default(),
+            ],
+        );
+
+        let actual = pages.format_contents("Friends - 1.html");
+        let expected = "<h1>Friends - 1</h1>\n<ol class=\"contents\">\n<li><a href=\"Friends%20-%201-1.html\">May 2022</a></li>\n<li><a href=\"Friends%20-%201-2.html\">Page 2</a></li>\n</ol>\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_contents_by_size() {
+        let mut pages = fake_pages(Pagination::Messages(10), 0);
+        pages.conversations.insert(
+            "Friends - 1.html".to_string(),
+            vec![Page {
+                first: Local.with_ymd_and_hms(2022, 5, 17, 12, 0, 0).single(),
+                last: Local.with_ymd_and_hms(2022, 6, 2, 12, 0, 0).single(),
+            }],
+        );
+
+        let actual = pages.format_contents("Friends - 1.html");
+
+        assert!(actual.contains(
+            "<a href=\"Friends%20-%201-1.html\">Page 1: May 17, 2022 – Jun 02, 2022</a>"
+        ));
+    }
+
+    #[test]
+    fn can_link_reply_to_thread_on_other_page() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let mut exporter = HTML::new(&config).unwrap();
+
+        let mut pages = fake_pages(Pagination::Messages(10), 2);
+        pages.locations.insert(
+            "originator".to_string(),
+            "Friends%20-%201-1.html".to_string(),
+        );
+        exporter.pages = Some(pages);
+
+        let mut message = Config::fake_message();
+        message.guid = "reply".to_string();
+        message.thread_originator_guid = Some("originator".to_string());
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+
+        assert!(
+            actual.contains(
+                "<a title=\"View in thread\" href=\"Friends%20-%201-1.html#reply\">⇱</a>"
+            )
+        );
+    }
+
+    #[test]
+    fn can_link_thread_to_reply_on_other_page() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let mut exporter = HTML::new(&config).unwrap();
+
+        let mut pages = fake_pages(Pagination::Messages(10), 2);
+        pages
+            .locations
+            .insert("reply".to_string(), "Friends%20-%201-2.html".to_string());
+        exporter.pages = Some(pages);
+
+        let mut message = Config::fake_message();
+        message.guid = "reply".to_string();
+        message.thread_originator_guid = Some("originator".to_string());
+
+        let actual = exporter.format_message(&message, 1).unwrap();
+
+        assert!(actual.contains(
+            "<a title=\"View in context\" href=\"Friends%20-%201-2.html#r-reply\">⇲</a>"
+        ));
+    }
+
+    #[test]
+    fn can_link_reply_without_pages() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "reply".to_string();
+        message.thread_originator_guid = Some("originator".to_string());
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+
+        assert!(actual.contains("<a title=\"View in thread\" href=\"#reply\">⇱</a>"));
+    }
+
+    #[test]
+    fn can_write_pages() {
+        let export_path = temp_dir().join("imessage-exporter-html-pages");
+        let _ = remove_dir_all(&export_path);
+        create_dir_all(&export_path).unwrap();
+
+        // Create exporter
+        let mut options = Options::fake_options(ExportType::Html);
+        options.export_path = export_path.clone();
+        options.paginate = Some(Pagination::Messages(1));
+        let mut config = Config::fake_app(options);
+        config.chatrooms.insert(
+            1,
+            Chat {
+                rowid: 1,
+                chat_identifier: "Friends".to_string(),
+                service_name: Some("iMessage".to_string()),
+                display_name: None,
+            },
+        );
+        config.real_chatrooms.insert(1, 1);
+        let filename = config.filename(config.chatrooms.get(&1).unwrap());
+
+        let mut exporter = HTML::new(&config).unwrap();
+        exporter.pages = Some(fake_pages(Pagination::Messages(1), 0));
+        exporter
+            .pages
+            .as_mut()
+            .unwrap()
+            .conversations
+            .insert(filename.clone(), vec![Page::default(), Page::default()]);
+
+        let mut message = Config::fake_message();
+        message.chat_id = Some(1);
+        for text in ["first", "second"] {
+            let file = exporter.get_or_create_file(&message).unwrap();
+            HTML::write_to_file(file, text).unwrap();
+        }
+        // Drop the writer for the last page so it is flushed
+        exporter.files.clear();
+
+        let first = read_to_string(export_path.join(page_filename(&filename, 1))).unwrap();
+        assert!(first.contains("first"));
+        assert!(first.contains("Page 1 of 2"));
+        assert!(first.contains("Next →"));
+        assert!(first.ends_with("</body></html>"));
+
+        let second = read_to_string(export_path.join(page_filename(&filename, 2))).unwrap();
+        assert!(second.contains("second"));
+        assert!(second.contains("← Previous"));
+    }
+
+    #[test]
+    fn can_plan_pages_with_text_filter() {
+        let export_path = temp_dir().join("imessage-exporter-html-plan-filter");
+        let _ = remove_dir_all(&export_path);
+        create_dir_all(&export_path).unwrap();
+
+        // Put both test messages in one conversation, with only one matching the filter
+        let mut options = Options::fake_options(ExportType::Html);
+        let db_path = export_path.join("chat.db");
+        copy(&options.db_path, &db_path).unwrap();
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "INSERT INTO chat (ROWID, guid, chat_identifier) VALUES (1, 'chat', 'Friends');
+                INSERT INTO chat_message_join (chat_id, message_id) SELECT 1, ROWID FROM message;
+                UPDATE message SET attributedBody = NULL, text = 'drop';
+                UPDATE message SET text = 'keep' WHERE ROWID = (SELECT MIN(ROWID) FROM message);",
+            )
+            .unwrap();
+
+        // Create exporter
+        options.db_path = db_path;
+        options.paginate = Some(Pagination::Messages(1));
+        options.query_context.set_text("keep");
+        let mut config = Config::fake_app(options);
+        config.chatrooms.insert(
+            1,
+            Chat {
+                rowid: 1,
+                chat_identifier: "Friends".to_string(),
+                service_name: None,
+                display_name: None,
+            },
+        );
+        config.real_chatrooms.insert(1, 1);
+        let filename = config.filename(config.chatrooms.get(&1).unwrap());
+        let exporter = HTML::new(&config).unwrap();
+
+        let pages = exporter.plan_pages(Pagination::Messages(1)).unwrap();
+
+        assert_eq!(pages.conversations[&filename].len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod index_tests {
+    use std::collections::BTreeSet;
+
+    use chrono::{Local, TimeZone};
+
+    use crate::{
+        Config, Exporter, HTML, Options,
+        app::export_type::ExportType,
+        exporters::html::{PREVIEW_LENGTH, Summary},
+    };
+    use imessage_database::tables::chat::Chat;
+
+    fn fake_chat() -> Chat {
+        Chat {
+            rowid: 1,
+            chat_identifier: "chat123".to_string(),
+            service_name: Some("iMessage".to_string()),
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn can_summarize_message() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(2, "Person 2".to_string());
+        config.participants.insert(3, "Person 3".to_string());
+        config
+            .chatroom_participants
+            .insert(1, BTreeSet::from([2, 3]));
+        config.chatrooms.insert(1, fake_chat());
+        config.real_chatrooms.insert(1, 1);
+        let mut exporter = HTML::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.chat_id = Some(1);
+        // May 17, 2022  5:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("\u{FFFC}Hello world".to_string());
+        exporter.summarize(&message);
+        exporter.summarize(&message);
+
+        let summary = exporter.summaries.values().next().unwrap();
+        assert_eq!(exporter.summaries.len(), 1);
+        assert_eq!(summary.title, "Person 2, Person 3");
+        assert_eq!(summary.participants, "Person 2, Person 3");
+        assert_eq!(summary.service, "iMessage");
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.preview, "Hello world");
+        assert!(summary.first.is_some());
+        assert_eq!(exporter.orphaned_count, 0);
+    }
+
+    #[test]
+    fn can_summarize_long_message() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let mut config = Config::fake_app(options);
+        config.chatrooms.insert(1, fake_chat());
+        config.real_chatrooms.insert(1, 1);
+        let mut exporter = HTML::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.chat_id = Some(1);
+        message.text = Some("a".repeat(PREVIEW_LENGTH + 1));
+        exporter.summarize(&message);
+
+        let summary = exporter.summaries.values().next().unwrap();
+        assert_eq!(summary.title, "chat123");
+        assert_eq!(summary.preview, format!("{}…", "a".repeat(PREVIEW_LENGTH)));
+    }
+
+    #[test]
+    fn can_summarize_orphaned_message() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let mut exporter = HTML::new(&config).unwrap();
+
+        exporter.summarize(&Config::fake_message());
+
+        assert!(exporter.summaries.is_empty());
+        assert_eq!(exporter.orphaned_count, 1);
+    }
+
+    #[test]
+    fn can_format_index_by_recency() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let mut exporter = HTML::new(&config).unwrap();
+
+        exporter.summaries.insert(
+            "Old.html".to_string(),
+            Summary {
+                title: "Old".to_string(),
+                count: 1,
+                first: Local.with_ymd_and_hms(2020, 1, 1, 12, 0, 0).single(),
+                last: Local.with_ymd_and_hms(2020, 2, 1, 12, 0, 0).single(),
+                ..Default::default()
+            },
+        );
+        exporter.summaries.insert(
+            "New Chat.html".to_string(),
+            Summary {
+                title: "New <Chat>".to_string(),
+                participants: "Person 2".to_string(),
+                service: "SMS".to_string(),
+                count: 2,
+                first: Local.with_ymd_and_hms(2022, 5, 17, 12, 0, 0).single(),
+                last: Local.with_ymd_and_hms(2022, 6, 2, 12, 0, 0).single(),
+                preview: "Hello world".to_string(),
+            },
+        );
+        exporter.orphaned_count = 3;
+
+        let actual = exporter.format_index();
+
+        let new = actual
+            .find("<tr><td><a href=\"New%20Chat.html\">New &lt;Chat&gt;</a></td><td>Person 2</td><td>SMS</td><td>2</td><td>May 17, 2022 – Jun 02, 2022</td><td>Hello world</td></tr>")
+            .unwrap();
+        let old = actual.find("<a href=\"Old.html\">Old</a>").unwrap();
+        assert!(new < old);
+        assert!(
+            actual.contains("<a href=\"orphaned.html\">Messages without a conversation</a> (3)")
+        );
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use std::{
+        env::temp_dir,
+        fs::{File, create_dir_all, read_to_string},
+        io::BufWriter,
+    };
+
+    use crate::{
+        Config, Exporter, HTML, Options, app::export_type::ExportType,
+        exporters::exporter::MessageFormatter,
+    };
+    use imessage_database::tables::chat::Chat;
+
+    #[test]
+    fn can_add_anchor_when_searchable() {
+        // Create exporter
+        let mut options = Options::fake_options(ExportType::Html);
+        options.search = true;
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+
+        assert!(actual.starts_with("<div class=\"message\", id=\"r-fake_guid\">\n"));
+    }
+
+    #[test]
+    fn can_skip_anchor_when_not_searchable() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+
+        assert!(actual.starts_with("<div class=\"message\">\n"));
+    }
+
+    #[test]
+    fn can_add_to_search_index() {
+        let export_path = temp_dir().join("imessage-exporter-html-search");
+        create_dir_all(&export_path).unwrap();
+        let path = export_path.join("search_index.js");
+
+        // Create exporter
+        let mut options = Options::fake_options(ExportType::Html);
+        options.search = true;
+        let mut config = Config::fake_app(options);
+        config.chatrooms.insert(
+            1,
+            Chat {
+                rowid: 1,
+                chat_identifier: "Friends".to_string(),
+                service_name: Some("iMessage".to_string()),
+                display_name: Some("Friends".to_string()),
+            },
+        );
+        config.real_chatrooms.insert(1, 1);
+        let mut exporter = HTML::new(&config).unwrap();
+        exporter.search_index = Some(BufWriter::new(File::create(&path).unwrap()));
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+        message.chat_id = Some(1);
+        message.is_from_me = true;
+        // May 17, 2022  5:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Dinner \"tonight\"?\u{FFFC}".to_string());
+        exporter.summarize(&message);
+        exporter.add_to_search_index(&message).unwrap();
+
+        // Messages without text are not searchable
+        message.text = None;
+        exporter.add_to_search_index(&message).unwrap();
+
+        // Orphaned messages link to the orphaned file
+        let mut orphaned = Config::fake_message();
+        orphaned.guid = "orphaned_guid".to_string();
+        orphaned.date = 674526582885055488;
+        orphaned.text = Some("Hello".to_string());
+        exporter.add_to_search_index(&orphaned).unwrap();
+
+        // Flush the index
+        exporter.search_index = None;
+
+        let actual = read_to_string(&path).unwrap();
+        let expected = concat!(
+            "[\"Dinner \\\"tonight\\\"?\",\"Me\",\"May 17, 2022  5:29:42 PM\",\"Friends\",\"Friends%20-%201.html#r-fake_guid\"],\n",
+            "[\"Hello\",\"Unknown\",\"May 17, 2022  5:29:42 PM\",\"Orphaned\",\"orphaned.html#r-orphaned_guid\"],\n",
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_link_index_to_search() {
+        // Create exporter
+        let mut options = Options::fake_options(ExportType::Html);
+        options.search = true;
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let actual = exporter.format_index();
+
+        assert!(
+            actual.starts_with(
+                "<p class=\"search\"><a href=\"search.html\">Search messages</a></p>\n"
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod template_tests {
+    use crate::{
+        Config, Exporter, HTML, Options,
+        app::export_type::ExportType,
+        exporters::{
+            html::{FOOTER, HEADER, Layout},
+            templates::Templates,
+        },
+    };
+    use imessage_database::tables::table::ME;
+
+    #[test]
+    fn can_apply_message_template() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(0, ME.to_string());
+        let mut exporter = HTML::new(&config).unwrap();
+        exporter.templates.message = Some(
+            "<article class=\"{{direction}} {{service}}\" title=\"{{sender}} {{date}}\" id=\"{{guid}}\">{{message}}</article>"
+                .to_string(),
+        );
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+        message.is_from_me = true;
+        message.service = Some("iMessage".to_string());
+        // May 17, 2022  5:29:42 PM
+        message.date = 674526582885055488;
+
+        let actual = exporter.apply_message_template(&message, "<p>Hi</p>".to_string());
+        let expected = "<article class=\"sent iMessage\" title=\"Me May 17, 2022  5:29:42 PM\" id=\"fake_guid\"><p>Hi</p></article>";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_skip_message_template_when_missing() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let exporter = HTML::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let actual = exporter.apply_message_template(&message, "<p>Hi</p>".to_string());
+
+        assert_eq!(actual, "<p>Hi</p>");
+    }
+
+    #[test]
+    fn can_apply_balloon_template() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let mut exporter = HTML::new(&config).unwrap();
+        exporter.templates.balloon =
+            Some("<aside data-app=\"{{bundle_id}}\">{{balloon}}</aside>".to_string());
+
+        let mut message = Config::fake_message();
+        message.balloon_bundle_id = Some("com.apple.messages.URLBalloonProvider".to_string());
+
+        let actual = exporter.apply_balloon_template(&message, "<a>Link</a>".to_string());
+        let expected =
+            "<aside data-app=\"com.apple.messages.URLBalloonProvider\"><a>Link</a></aside>";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_apply_tapback_template() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Html);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "Sample <Contact>".to_string());
+        let mut exporter = HTML::new(&config).unwrap();
+        exporter.templates.tapback = Some("<em title=\"{{sender}}\">{{tapback}}</em>".to_string());
+
+        let mut message = Config::fake_message();
+        message.handle_id = Some(999999);
+
+        let actual = exporter.apply_tapback_template(&message, "<b>Loved</b>".to_string());
+        let expected = "<em title=\"Sample &lt;Contact&gt;\"><b>Loved</b></em>";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_build_default_layout() {
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+
+        let layout = Layout::new(&config, &Templates::default());
+
+        assert!(layout.header.starts_with(HEADER));
+        assert!(layout.header.contains("href=\"style.css\""));
+        assert!(layout.header.ends_with("</head>\n<body>\n"));
+        assert_eq!(layout.footer, FOOTER);
+    }
+
+    #[test]
+    fn can_build_layout_from_page_template() {
+        let options = Options::fake_options(ExportType::Html);
+        let config = Config::fake_app(options);
+        let templates = Templates {
+            page: Some(
+                "<html><head>{{head}}</head><body><header>Archive</header>{{content}}</body></html>"
+                    .to_string(),
+            ),
+            style: Some("body { font-family: serif; }".to_string()),
+            ..Default::default()
+        };
+
+        let layout = Layout::new(&config, &templates);
+
+        assert!(layout.header.starts_with("<html><head><meta"));
+        assert!(
+            layout
+                .header
+                .ends_with("</head><body><header>Archive</header>")
+        );
+        assert_eq!(layout.footer, "</body></html>");
+
+        // User styles come after the built-in styles so they take precedence
+        let builtin = layout.header.find("<style>").unwrap();
+        let user = layout.header.find("font-family: serif").unwrap();
+        assert!(builtin < user);
+    }
+}
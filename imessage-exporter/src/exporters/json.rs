@@ -1,41 +1,78 @@
 use std::{
-    collections::HashMap,
+    collections::{
+        HashMap, HashSet,
+        hash_map::Entry::{Occupied, Vacant},
+    },
     fs::File,
-    io::{BufWriter, Write},
+    io::{BufWriter, Stdout, Write, stdout},
+    path::Path,
 };
 
+use chrono::Local;
+use plist::Value;
+use serde::Serialize;
+
 use crate::{
     app::{
-        error::RuntimeError,
-        progress::ExportProgress, runtime::Config,
+        compatibility::attachment_manager::AttachmentManagerMode, error::RuntimeError,
+        export_type::ExportType, progress::ExportProgress, runtime::Config,
+    },
+    exporters::{
+        exporter::{ATTACHMENT_NO_FILENAME, Exporter, variant_label},
+        json_schema::{self as schema, SCHEMA_VERSION},
     },
-    exporters::exporter::{ATTACHMENT_NO_FILENAME, BalloonFormatter, Exporter, MessageFormatter},
 };
 
 use imessage_database::{
-    error::plist::PlistParseError,
-    message_types::edited::EditedMessage,
+    error::{plist::PlistParseError, table::TableError},
+    message_types::{
+        app::AppMessage,
+        digital_touch::{self, DigitalTouch},
+        edited::{EditStatus, EditedMessage},
+        handwriting::HandwrittenMessage,
+        sticker::StickerSource,
+        url::URLMessage,
+        variants::{BalloonProvider, CustomBalloon, Tapback, URLOverride, Variant},
+    },
     tables::{
-        attachment::Attachment,
-        messages::{Message, models::AttachmentMeta, models::TextAttributes},
+        attachment::{Attachment, MediaType},
+        chat::Chat,
+        messages::{
+            Message,
+            models::{BubbleComponent, TextAttributes},
+        },
         table::{ORPHANED, Table},
     },
     util::{
         dates::{format, get_local_time},
+        plist::parse_ns_keyed_archiver,
     },
 };
 
-use serde_json::json;
-use rusqlite;
+// MARK: JSON
+/// Closes the `messages` array and the top-level document object
+const FOOTER: &str = "\n]}\n";
 
+/// Writes each conversation as a JSON document, or each message as a line of newline-delimited JSON
+///
+/// Messages are built as typed [`json_schema`](schema) records and serialized once, so unlike the
+/// text based exporters, this exporter does not implement the string based formatter traits.
 pub struct JSON<'a> {
     /// Data that is setup from the application's runtime
     pub config: &'a Config,
     /// Handles to files we want to write messages to
     /// Map of resolved chatroom file location to a buffered writer
     pub files: HashMap<String, BufWriter<File>>,
-    /// Writer instance for orphaned messages
-    pub orphaned: BufWriter<File>,
+    /// Writer instance for orphaned messages, created when the first one is found
+    pub orphaned: Option<BufWriter<File>>,
+    /// Combined writer used instead of per-chat files when streaming to stdout
+    stream: Option<BufWriter<Stdout>>,
+    /// If true, write one compact message object per line instead of a single document
+    lines: bool,
+    /// Files that already contain a message, so the next one needs a separator
+    populated: HashSet<String>,
+    /// GUIDs of exported messages whose replies were nested under them
+    threads: HashSet<String>,
     /// Progress Bar model for alerting the user about current export state
     pb: ExportProgress,
 }
@@ -43,26 +80,28 @@ pub struct JSON<'a> {
 // MARK: Exporter
 impl<'a> Exporter<'a> for JSON<'a> {
     fn new(config: &'a Config) -> Result<Self, RuntimeError> {
-        let mut orphaned = config.options.export_path.clone();
-        orphaned.push(ORPHANED);
-        orphaned.set_extension("json");
-
-        let file = File::options().append(true).create(true).open(&orphaned)?;
-
         Ok(JSON {
             config,
             files: HashMap::new(),
-            orphaned: BufWriter::new(file),
+            orphaned: None,
+            stream: config.options.stdout.then(|| BufWriter::new(stdout())),
+            lines: matches!(config.options.export_type, Some(ExportType::NdJson)),
+            populated: HashSet::new(),
+            threads: HashSet::new(),
             pb: ExportProgress::new(),
         })
     }
 
     fn iter_messages(&mut self) -> Result<(), RuntimeError> {
         // Tell the user what we are doing
-        eprintln!(
-            "Exporting to {} as json...",
-            self.config.options.export_path.display()
-        );
+        match &self.stream {
+            Some(_) => eprintln!("Exporting to stdout as {}...", ExportType::NdJson),
+            None => eprintln!(
+                "Exporting to {} as {}...",
+                self.config.options.export_path.display(),
+                self.extension()
+            ),
+        }
 
         // Keep track of current message ROWID
         let mut current_message_row = -1;
@@ -78,12 +117,13 @@ impl<'a> Exporter<'a> for JSON<'a> {
 
         let messages = statement
             .query_map([], |row| Ok(Message::from_row(row)))
-            .map_err(|err| RuntimeError::DatabaseError(imessage_database::error::table::TableError::QueryError(err)))?;
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
 
         for message in messages {
             let mut msg = Message::extract(message)?;
 
             // Early escape if we try and render the same message GUID twice
+            // See https://github.com/ReagentX/imessage-exporter/issues/135 for rationale
             if msg.rowid == current_message_row {
                 current_message += 1;
                 continue;
@@ -93,18 +133,47 @@ impl<'a> Exporter<'a> for JSON<'a> {
             // Generate the text of the message
             let _ = msg.generate_text(self.config.db());
 
-            // Skip tapbacks as they're handled in context
-            if !msg.is_tapback() {
-                let message_json = self.format_message(&msg, 0)?;
-                JSON::write_to_file(self.get_or_create_file(&msg)?, &message_json)?;
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
             }
-            
+
+            // Skip tapbacks as they're handled in context, and replies already nested under their originator
+            let nested = msg
+                .thread_originator_guid
+                .as_ref()
+                .is_some_and(|guid| self.threads.contains(guid));
+            if !msg.is_tapback() && !nested {
+                let message = self.format_message(&msg)?;
+                self.write_message(&msg, &message)?;
+                if self.config.options.nest_replies && msg.has_replies() {
+                    self.threads.insert(msg.guid.clone());
+                }
+            }
+
             current_message += 1;
             if current_message % 99 == 0 {
                 self.pb.set_position(current_message);
             }
         }
         self.pb.finish();
+
+        if let Some(stream) = &mut self.stream {
+            stream.flush().map_err(RuntimeError::DiskError)?;
+        }
+
+        // Line-delimited files are complete after the last record
+        if !self.lines {
+            eprintln!("Writing JSON footers...");
+            for buf in self.files.values_mut() {
+                JSON::write_to_file(buf, FOOTER)?;
+            }
+            if let Some(buf) = &mut self.orphaned {
+                JSON::write_to_file(buf, FOOTER)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -113,23 +182,36 @@ impl<'a> Exporter<'a> for JSON<'a> {
         &mut self,
         message: &Message,
     ) -> Result<&mut BufWriter<File>, RuntimeError> {
+        let extension = self.extension();
         match self.config.conversation(message) {
             Some((chatroom, _)) => {
                 let filename = self.config.filename(chatroom);
                 match self.files.entry(filename) {
-                    std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.into_mut()),
-                    std::collections::hash_map::Entry::Vacant(entry) => {
+                    Occupied(entry) => Ok(entry.into_mut()),
+                    Vacant(entry) => {
                         let mut path = self.config.options.export_path.clone();
                         path.push(self.config.filename(chatroom));
-                        path.set_extension("json");
+                        path.set_extension(&extension);
 
-                        let file = File::options().append(true).create(true).open(&path)?;
-
-                        Ok(entry.insert(BufWriter::new(file)))
+                        let buf =
+                            JSON::create_file(self.config, self.lines, &path, Some(chatroom))?;
+                        Ok(entry.insert(buf))
                     }
                 }
             }
-            None => Ok(&mut self.orphaned),
+            None => {
+                let buf = match self.orphaned.take() {
+                    Some(buf) => buf,
+                    None => {
+                        let mut path = self.config.options.export_path.clone();
+                        path.push(ORPHANED);
+                        path.set_extension(&extension);
+
+                        JSON::create_file(self.config, self.lines, &path, None)?
+                    }
+                };
+                Ok(self.orphaned.insert(buf))
+            }
         }
     }
 
@@ -139,17 +221,284 @@ impl<'a> Exporter<'a> for JSON<'a> {
     }
 }
 
-// MARK: MessageFormatter
-impl<'a> MessageFormatter<'a> for JSON<'a> {
-    fn format_message(&self, message: &Message, _indent_size: usize) -> Result<String, imessage_database::error::table::TableError> {
-        // Get basic message info
-        let timestamp = format(&message.date(&self.config.offset));
-        let sender = self.config.who(
-            message.handle_id,
-            message.is_from_me(),
-            &message.destination_caller_id,
-        );
+impl JSON<'_> {
+    /// The file extension used for the current output mode
+    fn extension(&self) -> String {
+        if self.lines {
+            ExportType::NdJson.to_string()
+        } else {
+            ExportType::Json.to_string()
+        }
+    }
+
+    /// Create an output file, writing the document header if the file is not line-delimited
+    ///
+    /// A JSON document cannot be appended to, so any existing file is replaced.
+    fn create_file(
+        config: &Config,
+        lines: bool,
+        path: &Path,
+        chatroom: Option<&Chat>,
+    ) -> Result<BufWriter<File>, RuntimeError> {
+        let mut buf = BufWriter::new(File::create(path)?);
+        if !lines {
+            JSON::write_to_file(&mut buf, &JSON::format_header(config, chatroom)?)?;
+        }
+        Ok(buf)
+    }
+
+    /// Build the opening of a conversation document, leaving the `messages` array open
+    fn format_header(config: &Config, chatroom: Option<&Chat>) -> Result<String, RuntimeError> {
+        let chat = chatroom.map(|chatroom| schema::ChatInfo {
+            rowid: chatroom.rowid,
+            chat_identifier: &chatroom.chat_identifier,
+            display_name: chatroom.display_name(),
+            service: chatroom.service().to_string(),
+            participants: config
+                .chatroom_participants
+                .get(&chatroom.rowid)
+                .map(|handles| {
+                    handles
+                        .iter()
+                        .map(|handle_id| schema::Participant {
+                            handle_id: *handle_id,
+                            name: config.who(Some(*handle_id), false, &None),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        });
+
+        let exporter = schema::ExporterInfo {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+        };
+
+        // Write every field of a `Document` in order, leaving `messages` open so messages can be streamed into it
+        Ok(format!(
+            "{{\n  \"schema_version\": {},\n  \"exporter\": {},\n  \"exported_at\": {},\n  \"chat\": {},\n  \"messages\": [",
+            to_json(&SCHEMA_VERSION)?,
+            to_json(&exporter)?,
+            to_json(&Local::now().to_rfc3339())?,
+            to_json(&chat)?,
+        ))
+    }
+
+    /// Write a formatted message to the stream or to its conversation's file
+    fn write_message(&mut self, message: &Message, text: &str) -> Result<(), RuntimeError> {
+        if let Some(stream) = &mut self.stream {
+            return writeln!(stream, "{text}").map_err(RuntimeError::DiskError);
+        }
+
+        if self.lines {
+            let file = self.get_or_create_file(message)?;
+            JSON::write_to_file(file, text)?;
+            return JSON::write_to_file(file, "\n");
+        }
+
+        // Documents need a separator between items in the `messages` array
+        let key = match self.config.conversation(message) {
+            Some((chatroom, _)) => self.config.filename(chatroom),
+            None => ORPHANED.to_string(),
+        };
+        let separator = if self.populated.insert(key) {
+            "\n"
+        } else {
+            ",\n"
+        };
+
+        let file = self.get_or_create_file(message)?;
+        JSON::write_to_file(file, separator)?;
+        JSON::write_to_file(file, text)
+    }
+
+    /// Serialize a message with its tapbacks and replies
+    ///
+    /// Records are compact when line-delimited, otherwise pretty-printed.
+    fn format_message(&self, message: &Message) -> Result<String, RuntimeError> {
+        let data = self.load_message(message)?;
+        let record = self.message_record(message, &data)?;
+        if self.lines {
+            serde_json::to_string(&record)
+        } else {
+            serde_json::to_string_pretty(&record)
+        }
+        .map_err(RuntimeError::SerializationError)
+    }
+}
+
+// MARK: Loading
+/// Data read from the database or disk that a message's record borrows from
+struct MessageData {
+    /// The message's attachments, with the details of any that are stickers
+    attachments: Vec<(Attachment, Option<schema::Sticker>)>,
+    /// The payload of an app balloon, if the message is one
+    payload: Option<Result<Payload, PlistParseError>>,
+    /// Threaded replies to any part of the message, in part order, if replies are nested
+    replies: Option<Vec<(Message, MessageData)>>,
+}
+
+/// The decoded payload of an app balloon
+enum Payload {
+    Handwriting(HandwrittenMessage),
+    DigitalTouch(DigitalTouch),
+    /// A parsed `NSKeyedArchiver` payload
+    Archive(Value),
+    /// A URL message that is missing its payload, so the link is the message text
+    Text,
+}
+
+impl JSON<'_> {
+    /// Read everything a message's record needs, copying its attachments if requested
+    fn load_message(&self, message: &Message) -> Result<MessageData, TableError> {
+        // Get attachments
+        let attachments = Attachment::from_message(self.config.db(), message)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut attachment| {
+                self.copy_attachment(&mut attachment, message);
+                let sticker = attachment
+                    .is_sticker
+                    .then(|| self.sticker_details(&attachment));
+                (attachment, sticker)
+            })
+            .collect();
+
+        // Get the payload of app balloons
+        let payload =
+            matches!(message.variant(), Variant::App(_)).then(|| self.load_payload(message));
+
+        // Get replies to every part of the message that match the export filters, in part order
+        let replies = if self.config.options.nest_replies {
+            let context = &self.config.options.query_context;
+            let mut threads: Vec<(usize, Vec<Message>)> = message
+                .get_filtered_replies(self.config.db(), context)?
+                .into_iter()
+                .collect();
+            threads.sort_unstable_by_key(|(idx, _)| *idx);
+            let mut replies = Vec::new();
+            for mut reply in threads.into_iter().flat_map(|(_, thread)| thread) {
+                let _ = reply.generate_text(self.config.db());
+                if !reply.is_tapback() && reply.matches_filters(self.config.db(), context) {
+                    let data = self.load_message(&reply)?;
+                    replies.push((reply, data));
+                }
+            }
+            Some(replies)
+        } else {
+            None
+        };
+
+        Ok(MessageData {
+            attachments,
+            payload,
+            replies,
+        })
+    }
+
+    /// Read and decode the payload of an app balloon
+    fn load_payload(&self, message: &Message) -> Result<Payload, PlistParseError> {
+        // Handwritten messages use a different payload type, so check that first
+        if message.is_handwriting()
+            && let Some(payload) = message.raw_payload_data(self.config.db())
+        {
+            return HandwrittenMessage::from_payload(&payload)
+                .map(Payload::Handwriting)
+                .map_err(PlistParseError::HandwritingError);
+        }
+
+        if message.is_digital_touch()
+            && let Some(payload) = message.raw_payload_data(self.config.db())
+        {
+            return digital_touch::from_payload(&payload)
+                .map(Payload::DigitalTouch)
+                .ok_or(PlistParseError::DigitalTouchError);
+        }
 
+        if let Some(payload) = message.payload_data(self.config.db()) {
+            return parse_ns_keyed_archiver(&payload).map(Payload::Archive);
+        }
+
+        // Sometimes, URL messages are missing their payloads
+        if message.is_url() && message.text.is_some() {
+            return Ok(Payload::Text);
+        }
+        Err(PlistParseError::NoPayload)
+    }
+
+    /// Serialize the contents of an app balloon, for exports that store balloons as JSON
+    pub(super) fn format_balloon(&self, message: &Message) -> Result<String, RuntimeError> {
+        let payload = self.load_payload(message);
+        serde_json::to_string(&balloon_record(message, &payload))
+            .map_err(RuntimeError::SerializationError)
+    }
+
+    /// Copy an attachment to the export directory, if requested
+    fn copy_attachment(&self, attachment: &mut Attachment, message: &Message) {
+        // When encoding videos, alert the user that the time estimate may be inaccurate
+        let will_encode = matches!(attachment.mime_type(), MediaType::Video(_))
+            && matches!(
+                self.config.options.attachment_manager.mode,
+                AttachmentManagerMode::Full
+            );
+
+        if will_encode {
+            self.pb
+                .set_busy_style("Encoding video, estimates paused...".to_string());
+        }
+
+        // Copy the file, if requested
+        self.config
+            .options
+            .attachment_manager
+            .handle_attachment(message, attachment, self.config);
+
+        if will_encode {
+            self.pb.set_default_style();
+        }
+    }
+
+    /// Determine where a sticker came from
+    fn sticker_details(&self, sticker: &Attachment) -> schema::Sticker {
+        let source = sticker.get_sticker_source(self.config.db());
+        let mut details = schema::Sticker {
+            source: source.as_ref().into(),
+            bundle_id: None,
+            app_name: None,
+            effect: None,
+        };
+        match source {
+            Some(StickerSource::UserGenerated) => {
+                // Add sticker effect
+                details.effect = sticker
+                    .get_sticker_effect(
+                        &self.config.options.platform,
+                        &self.config.options.db_path,
+                        self.config.options.attachment_root.as_deref(),
+                    )
+                    .ok()
+                    .flatten()
+                    .map(|effect| effect.to_string());
+            }
+            Some(StickerSource::App(bundle_id)) => {
+                // Add the application name used to generate/send the sticker
+                details.app_name = sticker.get_sticker_source_application_name(self.config.db());
+                details.bundle_id = Some(bundle_id);
+            }
+            _ => {}
+        }
+        details
+    }
+}
+
+// MARK: Records
+impl JSON<'_> {
+    /// Build the record for a message from the data loaded for it
+    fn message_record<'b>(
+        &'b self,
+        message: &'b Message,
+        data: &'b MessageData,
+    ) -> Result<schema::Message<'b>, TableError> {
         // Get read time if available
         let readtime = if message.date_read > 0 {
             let read_time = get_local_time(&message.date_read, &self.config.offset);
@@ -158,181 +507,1465 @@ impl<'a> MessageFormatter<'a> for JSON<'a> {
             None
         };
 
-        // Get message contents
-        let contents = message.text.clone().unwrap_or_default();
+        // Get attachments, where audio messages carry their transcription in the body metadata
+        let mut metadata = message
+            .components
+            .iter()
+            .filter_map(|component| match component {
+                BubbleComponent::Attachment(meta) => Some(meta),
+                _ => None,
+            });
+        let attachments: Vec<schema::Attachment> = data
+            .attachments
+            .iter()
+            .map(|(attachment, sticker)| {
+                let transcription = metadata
+                    .next()
+                    .and_then(|meta| meta.transcription.as_deref());
+                let mut record = self.attachment_record(
+                    attachment,
+                    if sticker.is_some() {
+                        None
+                    } else {
+                        transcription
+                    },
+                );
+                record.sticker.clone_from(sticker);
+                record
+            })
+            .collect();
 
-        // Get attachments
-        let mut attachments = Vec::new();
-        if let Ok(attachments_list) = Attachment::from_message(self.config.db(), message) {
-            for attachment in attachments_list {
-                let attachment_info = json!({
-                    "filename": attachment.filename().unwrap_or(ATTACHMENT_NO_FILENAME),
-                    "mime_type": format!("{:?}", attachment.mime_type()),
-                    "file_size": attachment.file_size()
-                });
-                attachments.push(attachment_info);
+        // Get tapbacks for every part of the message, in part order
+        let mut reactions = Vec::new();
+        if let Some(tapbacks_map) = self.config.tapbacks.get(&message.guid) {
+            let mut parts: Vec<&usize> = tapbacks_map.keys().collect();
+            parts.sort_unstable();
+            for tapback in parts.iter().flat_map(|idx| &tapbacks_map[*idx]) {
+                reactions.push(self.reaction_record(tapback));
             }
         }
 
-        // Create the JSON object
-        let message_json = json!({
-            "timestamp": timestamp,
-            "sender": sender,
-            "contents": contents,
-            "attachments": attachments,
-            "readtime": readtime,
-            "is_from_me": message.is_from_me,
-            "guid": message.guid
+        // Get replies to every part of the message
+        let replies = data
+            .replies
+            .as_ref()
+            .map(|replies| {
+                replies
+                    .iter()
+                    .map(|(reply, reply_data)| self.message_record(reply, reply_data))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+
+        // Get the body of the message from its components
+        let mut parts = Vec::with_capacity(message.components.len());
+        let mut attachment_index = 0;
+        for (idx, message_part) in message.components.iter().enumerate() {
+            let edited = message.is_part_edited(idx);
+            let part = match message_part {
+                BubbleComponent::Text(text_attrs) => schema::Part::Text {
+                    part: idx,
+                    edited,
+                    ranges: message
+                        .text
+                        .as_deref()
+                        .map(|text| text_ranges(text, text_attrs)),
+                },
+                BubbleComponent::Attachment(metadata) => {
+                    let attachment =
+                        (attachment_index < attachments.len()).then_some(attachment_index);
+                    attachment_index += 1;
+                    schema::Part::Attachment {
+                        part: idx,
+                        edited,
+                        attachment,
+                        guid: metadata.guid.as_deref(),
+                        name: metadata.name.as_deref(),
+                        transcription: metadata.transcription.as_deref(),
+                        width: metadata.width,
+                        height: metadata.height,
+                    }
+                }
+                BubbleComponent::App => schema::Part::App { part: idx, edited },
+                BubbleComponent::Retracted => schema::Part::Retracted { part: idx, edited },
+            };
+            parts.push(part);
+        }
+
+        // Get the edit and unsend history of every part of the message
+        let edits = message.edited_parts.as_ref().map(|edited_parts| {
+            (0..edited_parts.items())
+                .filter_map(|idx| self.edit_record(message, edited_parts, idx))
+                .collect()
         });
 
-        // Convert to pretty-printed JSON string
-        Ok(serde_json::to_string_pretty(&message_json)
-            .map_err(|_| imessage_database::error::table::TableError::QueryError(rusqlite::Error::InvalidParameterName("JSON serialization failed".to_string())))?)
+        // Get the structured contents of app balloons
+        let balloon = data
+            .payload
+            .as_ref()
+            .map(|payload| balloon_record(message, payload));
+
+        Ok(schema::Message {
+            timestamp: format(&message.date(&self.config.offset)),
+            sender: self.config.who(
+                message.handle_id,
+                message.is_from_me(),
+                &message.destination_caller_id,
+            ),
+            contents: message.text.as_deref().unwrap_or_default(),
+            parts,
+            attachments,
+            readtime,
+            is_from_me: message.is_from_me,
+            guid: &message.guid,
+            chat_id: message.chat_id.or(message.deleted_from),
+            balloon,
+            reactions,
+            // Get the message part this message replies to, if any
+            reply_to: message
+                .thread_originator_guid
+                .as_deref()
+                .map(|guid| schema::ReplyTo {
+                    guid,
+                    part: message.get_reply_index(),
+                }),
+            replies,
+            edited_at: message
+                .is_edited()
+                .then(|| format(&message.date_edited(&self.config.offset))),
+            edits,
+        })
     }
 
-    fn format_attachment(
+    /// Collect the metadata that describes an attachment
+    ///
+    /// Attachments that could not be copied keep their metadata, but have no `exported_path`.
+    fn attachment_record<'b>(
         &self,
-        attachment: &'a mut Attachment,
-        _message: &Message,
-        _metadata: &AttachmentMeta,
-    ) -> Result<String, &'a str> {
-        Ok(format!("{:?}", attachment.mime_type()))
+        attachment: &'b Attachment,
+        transcription: Option<&'b str>,
+    ) -> schema::Attachment<'b> {
+        schema::Attachment {
+            filename: attachment.filename().unwrap_or(ATTACHMENT_NO_FILENAME),
+            transfer_name: attachment.transfer_name.as_deref(),
+            uti: attachment.uti.as_deref(),
+            mime_type: attachment.mime_type().as_mime_type(),
+            file_size: attachment.file_size(),
+            total_bytes: attachment.total_bytes,
+            is_sticker: attachment.is_sticker,
+            hide_attachment: attachment.hide_attachment != 0,
+            emoji_description: attachment.emoji_description.as_deref(),
+            transcription,
+            resolved_path: attachment.resolved_attachment_path(
+                &self.config.options.platform,
+                &self.config.options.db_path,
+                self.config.options.attachment_root.as_deref(),
+            ),
+            exported_path: attachment
+                .copied_path
+                .as_ref()
+                .map(|_| self.config.message_attachment_path(attachment)),
+            sticker: None,
+        }
     }
 
-    fn format_sticker(&self, attachment: &'a mut Attachment, _message: &Message) -> String {
-        format!("Sticker: {:?}", attachment.mime_type())
+    /// Build the record for a tapback, copying the image used by a sticker tapback
+    ///
+    /// A sticker tapback whose image cannot be found is still written, but without a `sticker` path.
+    fn reaction_record<'b>(&'b self, msg: &'b Message) -> schema::Reaction<'b> {
+        match msg.variant() {
+            Variant::Tapback(idx, action, tapback) => {
+                // Sticker messages have only one attachment, the sticker image
+                let sticker = match tapback {
+                    Tapback::Sticker => Attachment::from_message(self.config.db(), msg)
+                        .unwrap_or_default()
+                        .first_mut()
+                        .map(|sticker| {
+                            self.config.options.attachment_manager.handle_attachment(
+                                msg,
+                                sticker,
+                                self.config,
+                            );
+                            self.config.message_attachment_path(sticker)
+                        }),
+                    _ => None,
+                };
+
+                schema::Reaction {
+                    part: idx,
+                    kind: (&tapback).into(),
+                    emoji: match tapback {
+                        Tapback::Emoji(emoji) => emoji,
+                        _ => None,
+                    },
+                    sticker,
+                    action: (&action).into(),
+                    sender: self.config.who(
+                        msg.handle_id,
+                        msg.is_from_me(),
+                        &msg.destination_caller_id,
+                    ),
+                    is_from_me: msg.is_from_me(),
+                    timestamp: format(&msg.date(&self.config.offset)),
+                    guid: &msg.guid,
+                }
+            }
+            _ => unreachable!(),
+        }
     }
 
-    fn format_app(
+    /// Build the edit and unsend history of a message part, if the part exists
+    fn edit_record<'b>(
         &self,
-        _message: &'a Message,
-        _attachments: &mut Vec<Attachment>,
-        _indent: &str,
-    ) -> Result<String, PlistParseError> {
-        Ok("App message".to_string())
+        msg: &'b Message,
+        edited_message: &'b EditedMessage,
+        message_part_idx: usize,
+    ) -> Option<schema::Edit<'b>> {
+        let edited_message_part = edited_message.part(message_part_idx)?;
+
+        Some(schema::Edit {
+            part: message_part_idx,
+            status: (&edited_message_part.status).into(),
+            timestamp: matches!(edited_message_part.status, EditStatus::Unsent)
+                .then(|| format(&msg.date_edited(&self.config.offset))),
+            history: edited_message_part
+                .edit_history
+                .iter()
+                .map(|event| schema::EditEvent {
+                    timestamp: format(&get_local_time(&event.date, &self.config.offset)),
+                    text: event.text.as_deref(),
+                    guid: event.guid.as_deref(),
+                })
+                .collect(),
+        })
     }
+}
+
+/// Build the ranges of message text and the effects applied to them
+fn text_ranges<'b>(text: &'b str, attributes: &'b [TextAttributes]) -> Vec<schema::TextRange<'b>> {
+    attributes
+        .iter()
+        .map(|range| schema::TextRange {
+            start: range.start,
+            end: range.end,
+            // Ranges are byte offsets, so an invalid one yields no text rather than a panic
+            text: text.get(range.start..range.end).unwrap_or_default(),
+            effects: range.effects.iter().map(Into::into).collect(),
+        })
+        .collect()
+}
+
+/// Serialize a single field of a JSON document
+fn to_json(value: &impl Serialize) -> Result<String, RuntimeError> {
+    serde_json::to_string(value).map_err(RuntimeError::SerializationError)
+}
+
+// MARK: Balloons
+/// Build the structured contents of an app balloon, or describe why it could not be parsed
+fn balloon_record<'b>(
+    message: &'b Message,
+    payload: &'b Result<Payload, PlistParseError>,
+) -> schema::Balloon<'b> {
+    parse_balloon(message, payload).unwrap_or_else(|why| schema::Balloon::Error {
+        error: format!(
+            "Unable to format {} message: {why}",
+            variant_label(&message.variant())
+        ),
+    })
+}
 
-    fn format_tapback(&self, _msg: &Message) -> Result<String, imessage_database::error::table::TableError> {
-        Ok("Tapback".to_string())
+/// Build the structured contents of an app balloon from its decoded payload
+fn parse_balloon<'b>(
+    message: &'b Message,
+    payload: &'b Result<Payload, PlistParseError>,
+) -> Result<schema::Balloon<'b>, String> {
+    let Variant::App(balloon) = message.variant() else {
+        return Err(PlistParseError::WrongMessageType.to_string());
+    };
+
+    Ok(match payload.as_ref().map_err(ToString::to_string)? {
+        Payload::Handwriting(note) => schema::Balloon::Handwriting(note.into()),
+        Payload::DigitalTouch(touch) => schema::Balloon::DigitalTouch {
+            touch: touch.into(),
+        },
+        Payload::Text => schema::Balloon::Url(schema::UrlBalloon {
+            url: message.text.as_deref(),
+            ..Default::default()
+        }),
+        Payload::Archive(parsed) if message.is_url() => {
+            match URLMessage::get_url_message_override(parsed).map_err(|why| why.to_string())? {
+                URLOverride::Normal(balloon) => schema::Balloon::Url((&balloon).into()),
+                URLOverride::AppleMusic(balloon) => schema::Balloon::Music((&balloon).into()),
+                URLOverride::Collaboration(balloon) => {
+                    schema::Balloon::Collaboration((&balloon).into())
+                }
+                URLOverride::AppStore(balloon) => schema::Balloon::AppStore((&balloon).into()),
+                URLOverride::SharedPlacemark(balloon) => {
+                    schema::Balloon::Placemark((&balloon).into())
+                }
+            }
+        }
+        Payload::Archive(parsed) => {
+            let bubble = AppMessage::from_map(parsed).map_err(|why| why.to_string())?;
+            match balloon {
+                CustomBalloon::Application(bundle_id) => {
+                    schema::Balloon::App(app_balloon(&bubble, Some(bundle_id)))
+                }
+                CustomBalloon::ApplePay => schema::Balloon::ApplePay(app_balloon(&bubble, None)),
+                CustomBalloon::Fitness => schema::Balloon::Fitness(app_balloon(&bubble, None)),
+                CustomBalloon::Slideshow => schema::Balloon::Slideshow(app_balloon(&bubble, None)),
+                CustomBalloon::CheckIn => schema::Balloon::CheckIn(app_balloon(&bubble, None)),
+                CustomBalloon::FindMy => schema::Balloon::FindMy(app_balloon(&bubble, None)),
+                // These balloons have their own payload types, so an archive is unexpected
+                CustomBalloon::Handwriting | CustomBalloon::DigitalTouch | CustomBalloon::URL => {
+                    return Err(PlistParseError::WrongMessageType.to_string());
+                }
+            }
+        }
+    })
+}
+
+/// Collect the fields shared by all [`AppMessage`] based balloons
+fn app_balloon<'b>(balloon: &AppMessage<'b>, bundle_id: Option<&'b str>) -> schema::AppBalloon<'b> {
+    schema::AppBalloon {
+        bundle_id,
+        app_name: balloon.app_name,
+        title: balloon.title,
+        subtitle: balloon.subtitle,
+        caption: balloon.caption,
+        subcaption: balloon.subcaption,
+        trailing_caption: balloon.trailing_caption,
+        trailing_subcaption: balloon.trailing_subcaption,
+        ldtext: balloon.ldtext,
+        image: balloon.image,
+        url: balloon.url,
+        query: balloon
+            .parse_query_string()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
     }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        env::temp_dir,
+        fs::{copy, create_dir_all, read_to_string, remove_dir_all},
+    };
+
+    use rusqlite::Connection;
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::{Config, Exporter, Options};
+    use imessage_database::util::platform::Platform;
+    use imessage_database::{
+        message_types::text_effects::{Animation, Style, TextEffect, Unit},
+        tables::messages::models::AttachmentMeta,
+    };
 
-    fn format_expressive(&self, _msg: &'a Message) -> &'a str {
-        "Expressive message"
+    fn fake_chat() -> Chat {
+        Chat {
+            rowid: 1,
+            chat_identifier: "+15558675309".to_string(),
+            service_name: Some("iMessage".to_string()),
+            display_name: Some("Friends".to_string()),
+        }
     }
 
-    fn format_announcement(&self, _msg: &'a Message) -> String {
-        "Announcement".to_string()
+    /// Export a thread where the later test message replies to the earlier one, returning the written records
+    fn export_thread(name: &str, text: Option<&str>) -> Vec<Value> {
+        let export_path = temp_dir().join(name);
+        let _ = remove_dir_all(&export_path);
+        create_dir_all(&export_path).unwrap();
+
+        let mut options = Options::fake_options(ExportType::NdJson);
+        let db_path = export_path.join("chat.db");
+        copy(&options.db_path, &db_path).unwrap();
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "INSERT INTO chat (ROWID, guid, chat_identifier) VALUES (1, 'chat', '+15558675309');
+                INSERT INTO chat_message_join (chat_id, message_id) SELECT 1, ROWID FROM message;
+                UPDATE message SET attributedBody = NULL, text = 'hello' WHERE ROWID = 123445;
+                UPDATE message SET attributedBody = NULL, text = 'reply', date = date + 1,
+                    thread_originator_guid = (SELECT guid FROM message WHERE ROWID = 123445)
+                    WHERE ROWID = 452567;",
+            )
+            .unwrap();
+
+        options.db_path = db_path;
+        options.export_path = export_path.clone();
+        options.nest_replies = true;
+        if let Some(text) = text {
+            options.query_context.set_text(text);
+        }
+        let mut config = Config::fake_app(options);
+        config.chatrooms.insert(1, fake_chat());
+        config.real_chatrooms.insert(1, 1);
+        let filename = config.filename(&fake_chat());
+
+        let mut exporter = JSON::new(&config).unwrap();
+        exporter.iter_messages().unwrap();
+        drop(exporter);
+
+        let mut path = export_path.join(filename);
+        path.set_extension(ExportType::NdJson.to_string());
+        read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
     }
 
-    fn format_shareplay(&self) -> &str {
-        "SharePlay message"
+    #[test]
+    fn can_create() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+        assert_eq!(exporter.files.len(), 0);
     }
 
-    fn format_shared_location(&self, _msg: &'a Message) -> &str {
-        "Shared location"
+    #[test]
+    fn can_format_basic_message() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hello world".to_string());
+        message.is_from_me = true;
+        message.date = 674526582885055488; // May 17, 2022 8:29:42 PM
+
+        let result = exporter.format_message(&message).unwrap();
+        let parsed: Value = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(parsed["sender"], "Me");
+        assert_eq!(parsed["contents"], "Hello world");
+        assert_eq!(parsed["is_from_me"], true);
+        assert_eq!(parsed["guid"], message.guid);
     }
 
-    fn format_edited(
-        &self,
-        _msg: &'a Message,
-        _edited_message: &'a EditedMessage,
-        _message_part_idx: usize,
-        _indent: &str,
-    ) -> Option<String> {
-        Some("Edited message".to_string())
+    #[test]
+    fn can_format_basic_message_ndjson() {
+        let options = Options::fake_options(ExportType::NdJson);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hello\nworld".to_string());
+
+        let result = exporter.format_message(&message).unwrap();
+        assert_eq!(result.lines().count(), 1);
+
+        let parsed: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["contents"], "Hello\nworld");
     }
 
-    fn format_attributes(&'a self, text: &'a str, _attributes: &'a [TextAttributes]) -> String {
-        text.to_string()
+    #[test]
+    fn can_get_extension() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        assert_eq!(JSON::new(&config).unwrap().extension(), "json");
+
+        let options = Options::fake_options(ExportType::NdJson);
+        let config = Config::fake_app(options);
+        assert_eq!(JSON::new(&config).unwrap().extension(), "ndjson");
     }
-}
 
-// MARK: BalloonFormatter - Basic implementations for required traits
-impl<'a> BalloonFormatter<&'a str> for JSON<'a> {
-    fn format_url(&self, _msg: &Message, _balloon: &imessage_database::message_types::url::URLMessage, _indent: &str) -> String {
-        "URL message".to_string()
+    #[test]
+    fn can_format_json_tapback_me() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.associated_message_type = Some(2000);
+        message.associated_message_guid =
+            Some("p:1/24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string());
+        message.is_from_me = true;
+
+        let actual: Value = serde_json::to_value(exporter.reaction_record(&message)).unwrap();
+
+        assert_eq!(actual["part"], 1);
+        assert_eq!(actual["kind"], "loved");
+        assert_eq!(actual["action"], "added");
+        assert_eq!(actual["sender"], "Me");
+        assert_eq!(actual["is_from_me"], true);
+        assert!(actual["emoji"].is_null());
     }
 
-    fn format_music(&self, _balloon: &imessage_database::message_types::music::MusicMessage, _indent: &str) -> String {
-        "Music message".to_string()
+    #[test]
+    fn can_format_json_tapback_custom_emoji_removed() {
+        let options = Options::fake_options(ExportType::Json);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "Sample Contact".to_string());
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.associated_message_type = Some(3006);
+        message.associated_message_guid = Some("fake_guid".to_string());
+        message.handle_id = Some(999999);
+        message.associated_message_emoji = Some("☕️".to_string());
+
+        let actual: Value = serde_json::to_value(exporter.reaction_record(&message)).unwrap();
+
+        assert_eq!(actual["kind"], "emoji");
+        assert_eq!(actual["emoji"], "☕️");
+        assert_eq!(actual["action"], "removed");
+        assert_eq!(actual["sender"], "Sample Contact");
     }
 
-    fn format_collaboration(&self, _balloon: &imessage_database::message_types::collaboration::CollaborationMessage, _indent: &str) -> String {
-        "Collaboration message".to_string()
+    #[test]
+    fn can_format_json_tapback_custom_sticker_missing() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.associated_message_type = Some(2007);
+        message.associated_message_guid = Some("fake_guid".to_string());
+        message.num_attachments = 1;
+
+        let actual: Value = serde_json::to_value(exporter.reaction_record(&message)).unwrap();
+
+        assert_eq!(actual["kind"], "sticker");
+        assert!(actual["sticker"].is_null());
     }
 
-    fn format_app_store(&self, _balloon: &imessage_database::message_types::app_store::AppStoreMessage, _indent: &str) -> String {
-        "App Store message".to_string()
+    #[test]
+    fn can_format_message_with_reactions() {
+        let options = Options::fake_options(ExportType::Json);
+        let mut config = Config::fake_app(options);
+
+        let mut message = Config::fake_message();
+        message.guid = "24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string();
+
+        let mut liked = Config::fake_message();
+        liked.associated_message_type = Some(2001);
+        liked.associated_message_guid =
+            Some("p:2/24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string());
+
+        let mut loved = Config::fake_message();
+        loved.associated_message_type = Some(2000);
+        loved.associated_message_guid =
+            Some("p:0/24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string());
+
+        config.tapbacks.insert(
+            "24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string(),
+            HashMap::from([(2, vec![liked]), (0, vec![loved])]),
+        );
+        let exporter = JSON::new(&config).unwrap();
+
+        let actual: Value =
+            serde_json::from_str(&exporter.format_message(&message).unwrap()).unwrap();
+
+        assert_eq!(actual["reactions"].as_array().unwrap().len(), 2);
+        assert_eq!(actual["reactions"][0]["kind"], "loved");
+        assert_eq!(actual["reactions"][0]["part"], 0);
+        assert_eq!(actual["reactions"][1]["kind"], "liked");
+        assert_eq!(actual["reactions"][1]["part"], 2);
     }
 
-    fn format_placemark(&self, _balloon: &imessage_database::message_types::placemark::PlacemarkMessage, _indent: &str) -> String {
-        "Placemark message".to_string()
+    #[test]
+    fn can_format_message_reply_to() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.thread_originator_guid = Some("parent_guid".to_string());
+        message.thread_originator_part = Some("1:0:5".to_string());
+
+        let actual: Value =
+            serde_json::from_str(&exporter.format_message(&message).unwrap()).unwrap();
+
+        assert_eq!(
+            actual["reply_to"],
+            json!({"guid": "parent_guid", "part": 1})
+        );
+        assert!(actual["replies"].is_null());
     }
 
-    fn format_handwriting(&self, _msg: &Message, _balloon: &imessage_database::message_types::handwriting::HandwrittenMessage, _indent: &str) -> String {
-        "Handwritten message".to_string()
+    #[test]
+    fn can_format_message_nested_replies() {
+        let mut options = Options::fake_options(ExportType::Json);
+        options.nest_replies = true;
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let actual: Value =
+            serde_json::from_str(&exporter.format_message(&Config::fake_message()).unwrap())
+                .unwrap();
+
+        assert_eq!(actual["replies"], json!([]));
     }
 
-    fn format_digital_touch(&self, _: &Message, _balloon: &imessage_database::message_types::digital_touch::DigitalTouch, _indent: &str) -> String {
-        "Digital Touch message".to_string()
+    #[test]
+    fn can_nest_replies_under_exported_message() {
+        let records = export_thread("imessage-exporter-json-nest", None);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["contents"], "hello");
+        assert_eq!(records[0]["replies"][0]["contents"], "reply");
     }
 
-    fn format_apple_pay(&self, _balloon: &imessage_database::message_types::app::AppMessage, _indent: &str) -> String {
-        "Apple Pay message".to_string()
+    #[test]
+    fn can_write_reply_when_originator_not_exported() {
+        let records = export_thread("imessage-exporter-json-nest-orphan", Some("reply"));
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["contents"], "reply");
+        assert_eq!(records[0]["replies"], json!([]));
     }
 
-    fn format_fitness(&self, _balloon: &imessage_database::message_types::app::AppMessage, _indent: &str) -> String {
-        "Fitness message".to_string()
+    #[test]
+    fn can_filter_nested_replies() {
+        let records = export_thread("imessage-exporter-json-nest-filter", Some("hello"));
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["contents"], "hello");
+        assert_eq!(records[0]["replies"], json!([]));
     }
 
-    fn format_slideshow(&self, _balloon: &imessage_database::message_types::app::AppMessage, _indent: &str) -> String {
-        "Slideshow message".to_string()
+    #[test]
+    fn can_format_message_not_reply() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let actual: Value =
+            serde_json::from_str(&exporter.format_message(&message).unwrap()).unwrap();
+
+        assert!(actual["reply_to"].is_null());
     }
 
-    fn format_check_in(&self, _balloon: &imessage_database::message_types::app::AppMessage, _indent: &str) -> String {
-        "Check-in message".to_string()
+    #[test]
+    fn can_format_message_parts() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hi Chris\u{FFFC}Bye".to_string());
+        message.components = vec![
+            BubbleComponent::Text(vec![
+                TextAttributes::new(0, 3, vec![TextEffect::Default]),
+                TextAttributes::new(3, 8, vec![TextEffect::Mention("+5558675309".to_string())]),
+            ]),
+            BubbleComponent::Attachment(AttachmentMeta {
+                guid: Some("at_0_F0668F79-20C2-49C9-A87F-1B007ABB0CED".to_string()),
+                name: Some("image.png".to_string()),
+                ..Default::default()
+            }),
+            BubbleComponent::Text(vec![TextAttributes::new(
+                11,
+                14,
+                vec![TextEffect::Styles(vec![Style::Bold, Style::Italic])],
+            )]),
+            BubbleComponent::Retracted,
+        ];
+
+        let actual: Value =
+            serde_json::from_str(&exporter.format_message(&message).unwrap()).unwrap();
+        let parts = actual["parts"].as_array().unwrap();
+
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0]["kind"], "text");
+        assert_eq!(parts[0]["ranges"][0]["text"], "Hi ");
+        assert_eq!(parts[0]["ranges"][1]["text"], "Chris");
+        assert_eq!(parts[0]["ranges"][1]["effects"][0]["kind"], "mention");
+        assert_eq!(parts[0]["ranges"][1]["effects"][0]["handle"], "+5558675309");
+        assert_eq!(parts[1]["kind"], "attachment");
+        assert_eq!(parts[1]["name"], "image.png");
+        assert!(parts[1]["attachment"].is_null());
+        assert_eq!(parts[2]["part"], 2);
+        assert_eq!(parts[2]["ranges"][0]["text"], "Bye");
+        assert_eq!(
+            parts[2]["ranges"][0]["effects"][0]["styles"],
+            json!(["bold", "italic"])
+        );
+        assert_eq!(parts[3]["kind"], "retracted");
+    }
+
+    #[test]
+    fn can_format_attributes_effects() {
+        let text = "Visit example.com, code 123456, 5 mi away!";
+        let attributes = vec![
+            TextAttributes::new(
+                6,
+                17,
+                vec![TextEffect::Link("https://example.com".to_string())],
+            ),
+            TextAttributes::new(24, 30, vec![TextEffect::OTP]),
+            TextAttributes::new(32, 36, vec![TextEffect::Conversion(Unit::Distance)]),
+            TextAttributes::new(37, 42, vec![TextEffect::Animated(Animation::Unknown(99))]),
+        ];
+
+        let actual: Value = serde_json::to_value(text_ranges(text, &attributes)).unwrap();
+
+        assert_eq!(actual[0]["text"], "example.com");
+        assert_eq!(actual[0]["effects"][0]["kind"], "link");
+        assert_eq!(actual[0]["effects"][0]["url"], "https://example.com");
+        assert_eq!(actual[1]["text"], "123456");
+        assert_eq!(actual[1]["effects"][0]["kind"], "otp");
+        assert_eq!(actual[2]["effects"][0]["unit"], "distance");
+        assert_eq!(actual[3]["effects"][0]["animation"], "unknown");
+        assert_eq!(actual[3]["effects"][0]["id"], 99);
+    }
+
+    #[test]
+    fn can_format_attributes_invalid_range() {
+        let attributes = vec![TextAttributes::new(2, 20, vec![TextEffect::Default])];
+
+        let actual: Value = serde_json::to_value(text_ranges("Hello", &attributes)).unwrap();
+
+        assert_eq!(actual[0]["end"], 20);
+        assert_eq!(actual[0]["text"], "");
+    }
+
+    #[test]
+    fn can_format_json_attachment_macos() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let attachment = Config::fake_attachment();
+        let metadata = AttachmentMeta {
+            transcription: Some("Hello world".to_string()),
+            ..Default::default()
+        };
+
+        let actual: Value = serde_json::to_value(
+            exporter.attachment_record(&attachment, metadata.transcription.as_deref()),
+        )
+        .unwrap();
+
+        assert_eq!(actual["filename"], "d.jpg");
+        assert_eq!(actual["transfer_name"], "d.jpg");
+        assert_eq!(actual["uti"], "public.png");
+        assert_eq!(actual["mime_type"], "image/png");
+        assert_eq!(actual["total_bytes"], 100);
+        assert_eq!(actual["is_sticker"], false);
+        assert_eq!(actual["hide_attachment"], false);
+        assert_eq!(actual["transcription"], "Hello world");
+        assert_eq!(actual["resolved_path"], "a/b/c/d.jpg");
+        assert!(actual["exported_path"].is_null());
+        assert!(actual["sticker"].is_null());
+    }
+
+    #[test]
+    fn can_format_json_attachment_ios() {
+        let options = Options::fake_options(ExportType::Json);
+        let mut config = Config::fake_app(options);
+        config.options.platform = Platform::iOS;
+        let exporter = JSON::new(&config).unwrap();
+
+        let attachment = Config::fake_attachment();
+
+        let actual: Value =
+            serde_json::to_value(exporter.attachment_record(&attachment, None)).unwrap();
+
+        assert!(
+            actual["resolved_path"]
+                .as_str()
+                .unwrap()
+                .ends_with("33/33c81da8ae3194fc5a0ea993ef6ffe0b048baedb")
+        );
+    }
+
+    #[test]
+    fn can_format_json_attachment_copied() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.copied_path = Some(config.options.export_path.join("attachments/0/0.png"));
+
+        let actual: Value =
+            serde_json::to_value(exporter.attachment_record(&attachment, None)).unwrap();
+
+        assert_eq!(actual["resolved_path"], "a/b/c/d.jpg");
+        assert_eq!(actual["exported_path"], "attachments/0/0.png");
+    }
+
+    #[test]
+    fn can_format_json_attachment_missing_clone() {
+        let mut options = Options::fake_options(ExportType::Json);
+        options.attachment_manager.mode = AttachmentManagerMode::Clone;
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = None;
+        attachment.transfer_name = None;
+
+        let actual: Value =
+            serde_json::to_value(exporter.attachment_record(&attachment, None)).unwrap();
+
+        assert_eq!(actual["filename"], ATTACHMENT_NO_FILENAME);
+        assert!(actual["resolved_path"].is_null());
+        assert!(actual["exported_path"].is_null());
+    }
+
+    #[test]
+    fn can_format_json_sticker() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut sticker = Config::fake_attachment();
+        sticker.is_sticker = true;
+        sticker.emoji_description = Some("A cat wearing a hat".to_string());
+
+        let mut record = exporter.attachment_record(&sticker, None);
+        record.sticker = Some(exporter.sticker_details(&sticker));
+        let actual = serde_json::to_value(record).unwrap();
+
+        assert_eq!(actual["is_sticker"], true);
+        assert_eq!(actual["emoji_description"], "A cat wearing a hat");
+        assert!(actual["sticker"]["source"].is_string());
+    }
+
+    #[test]
+    fn can_format_header() {
+        let options = Options::fake_options(ExportType::Json);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(2, "+15558675309".to_string());
+        config
+            .chatroom_participants
+            .insert(1, BTreeSet::from([2, 3]));
+
+        let document = format!(
+            "{}{FOOTER}",
+            JSON::format_header(&config, Some(&fake_chat())).unwrap()
+        );
+        let parsed: Value = serde_json::from_str(&document).unwrap();
+
+        assert_eq!(parsed["schema_version"], SCHEMA_VERSION);
+        assert_eq!(parsed["exporter"]["name"], "imessage-exporter");
+        assert_eq!(parsed["chat"]["rowid"], 1);
+        assert_eq!(parsed["chat"]["chat_identifier"], "+15558675309");
+        assert_eq!(parsed["chat"]["display_name"], "Friends");
+        assert_eq!(parsed["chat"]["service"], "iMessage");
+        assert_eq!(
+            parsed["chat"]["participants"],
+            json!([
+                {"handle_id": 2, "name": "+15558675309"},
+                {"handle_id": 3, "name": "Unknown"},
+            ])
+        );
+        assert_eq!(parsed["messages"], json!([]));
     }
 
-    fn format_generic_app(&self, _balloon: &imessage_database::message_types::app::AppMessage, _bundle_id: &str, _: &mut Vec<Attachment>, _indent: &str) -> String {
-        "Generic app message".to_string()
+    #[test]
+    fn can_format_header_orphaned() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+
+        let document = format!("{}{FOOTER}", JSON::format_header(&config, None).unwrap());
+        let parsed: Value = serde_json::from_str(&document).unwrap();
+
+        assert!(parsed["chat"].is_null());
+        assert_eq!(parsed["messages"], json!([]));
     }
 
-    fn format_find_my(&self, _balloon: &imessage_database::message_types::app::AppMessage, _indent: &str) -> String {
-        "Find My message".to_string()
+    #[test]
+    fn can_format_document() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hello world".to_string());
+        let formatted = exporter.format_message(&message).unwrap();
+
+        let document = format!(
+            "{}\n{formatted},\n{formatted}{FOOTER}",
+            JSON::format_header(&config, Some(&fake_chat())).unwrap()
+        );
+        let parsed: Value = serde_json::from_str(&document).unwrap();
+
+        assert_eq!(parsed["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(parsed["messages"][1]["contents"], "Hello world");
     }
 }
 
-// MARK: Tests
 #[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{Config, Exporter, Options, app::export_type::ExportType};
+mod edited_tests {
+    use crate::{Config, Exporter, JSON, Options, app::export_type::ExportType};
+    use imessage_database::message_types::edited::{
+        EditStatus, EditedEvent, EditedMessage, EditedMessagePart,
+    };
+    use serde_json::Value;
+
+    fn fake_edited_message() -> EditedMessage {
+        EditedMessage {
+            parts: vec![
+                EditedMessagePart {
+                    status: EditStatus::Edited,
+                    edit_history: vec![
+                        EditedEvent {
+                            date: 674526582885055488,
+                            text: Some("Hello".to_string()),
+                            components: vec![],
+                            guid: None,
+                        },
+                        EditedEvent {
+                            date: 674526592885055488,
+                            text: Some("Hello world".to_string()),
+                            components: vec![],
+                            guid: Some("76A466B8-D21E-4A20-AF62-FF2D3A20D31C".to_string()),
+                        },
+                    ],
+                },
+                EditedMessagePart {
+                    status: EditStatus::Unsent,
+                    edit_history: vec![],
+                },
+            ],
+        }
+    }
 
     #[test]
-    fn can_create() {
-        let options = Options::fake_options(ExportType::Html); // Use HTML as base since JSON isn't in ExportType yet
+    fn can_format_json_edited() {
+        let options = Options::fake_options(ExportType::Json);
         let config = Config::fake_app(options);
         let exporter = JSON::new(&config).unwrap();
-        assert_eq!(exporter.files.len(), 0);
+
+        let edited_message = fake_edited_message();
+        let message = Config::fake_message();
+
+        let actual: Value =
+            serde_json::to_value(exporter.edit_record(&message, &edited_message, 0).unwrap())
+                .unwrap();
+
+        assert_eq!(actual["part"], 0);
+        assert_eq!(actual["status"], "edited");
+        assert!(actual["timestamp"].is_null());
+        assert_eq!(actual["history"].as_array().unwrap().len(), 2);
+        assert_eq!(actual["history"][0]["text"], "Hello");
+        assert!(actual["history"][0]["guid"].is_null());
+        assert_eq!(actual["history"][1]["text"], "Hello world");
+        assert_eq!(
+            actual["history"][1]["guid"],
+            "76A466B8-D21E-4A20-AF62-FF2D3A20D31C"
+        );
     }
 
     #[test]
-    fn can_format_basic_message() {
-        let options = Options::fake_options(ExportType::Html);
+    fn can_format_json_unsent() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let edited_message = fake_edited_message();
+        let mut message = Config::fake_message();
+        message.date_edited = 674530231992568192;
+
+        let actual: Value =
+            serde_json::to_value(exporter.edit_record(&message, &edited_message, 1).unwrap())
+                .unwrap();
+
+        assert_eq!(actual["status"], "unsent");
+        assert!(actual["timestamp"].is_string());
+        assert!(actual["history"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn cant_format_json_edited_missing_part() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let edited_message = fake_edited_message();
+        let message = Config::fake_message();
+
+        assert!(exporter.edit_record(&message, &edited_message, 2).is_none());
+    }
+
+    #[test]
+    fn can_format_message_with_edits() {
+        let options = Options::fake_options(ExportType::Json);
         let config = Config::fake_app(options);
         let exporter = JSON::new(&config).unwrap();
 
         let mut message = Config::fake_message();
         message.text = Some("Hello world".to_string());
-        message.is_from_me = true;
-        message.date = 674526582885055488; // May 17, 2022 8:29:42 PM
+        message.date_edited = 674530231992568192;
+        message.edited_parts = Some(fake_edited_message());
 
-        let result = exporter.format_message(&message, 0).unwrap();
-        let parsed: Value = serde_json::from_str(&result).unwrap();
+        let actual: Value =
+            serde_json::from_str(&exporter.format_message(&message).unwrap()).unwrap();
 
-        assert_eq!(parsed["sender"], "Me");
-        assert_eq!(parsed["contents"], "Hello world");
-        assert_eq!(parsed["is_from_me"], true);
-        assert_eq!(parsed["guid"], message.guid);
+        assert!(actual["edited_at"].is_string());
+        assert_eq!(actual["edits"].as_array().unwrap().len(), 2);
+        assert_eq!(actual["edits"][1]["status"], "unsent");
+    }
+
+    #[test]
+    fn can_format_message_not_edited() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let actual: Value =
+            serde_json::from_str(&exporter.format_message(&message).unwrap()).unwrap();
+
+        assert!(actual["edited_at"].is_null());
+        assert!(actual["edits"].is_null());
+    }
+}
+
+#[cfg(test)]
+mod balloon_format_tests {
+    use crate::exporters::{json::app_balloon, json_schema::Balloon};
+    use imessage_database::message_types::{
+        app::AppMessage,
+        digital_touch::DigitalTouch,
+        music::MusicMessage,
+        placemark::{Placemark, PlacemarkMessage},
+        url::URLMessage,
+    };
+
+    use serde_json::{Value, json};
+
+    #[test]
+    fn can_format_json_url() {
+        let balloon = URLMessage {
+            title: Some("title"),
+            summary: Some("summary"),
+            url: Some("url"),
+            original_url: Some("original_url"),
+            item_type: Some("item_type"),
+            images: vec!["images"],
+            icons: vec!["icons"],
+            site_name: Some("site_name"),
+            placeholder: false,
+        };
+
+        let expected = serde_json::to_value(Balloon::Url((&balloon).into())).unwrap();
+        let actual = json!({
+            "kind": "url",
+            "title": "title",
+            "summary": "summary",
+            "url": "url",
+            "original_url": "original_url",
+            "item_type": "item_type",
+            "images": ["images"],
+            "icons": ["icons"],
+            "site_name": "site_name",
+            "placeholder": false,
+        });
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn can_format_json_music_lyrics() {
+        let balloon = MusicMessage {
+            url: Some("url"),
+            preview: None,
+            artist: Some("artist"),
+            album: Some("album"),
+            track_name: Some("track_name"),
+            lyrics: Some(vec!["a", "b"]),
+        };
+
+        let expected = serde_json::to_value(Balloon::Music((&balloon).into())).unwrap();
+        let actual = json!({
+            "kind": "music",
+            "url": "url",
+            "preview": null,
+            "artist": "artist",
+            "album": "album",
+            "track_name": "track_name",
+            "lyrics": ["a", "b"],
+        });
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn can_format_json_placemark() {
+        let balloon = PlacemarkMessage {
+            url: Some("url"),
+            original_url: Some("original_url"),
+            place_name: Some("Name"),
+            placemark: Placemark {
+                name: Some("name"),
+                address: Some("address"),
+                state: Some("state"),
+                city: Some("city"),
+                iso_country_code: Some("iso_country_code"),
+                postal_code: Some("postal_code"),
+                country: Some("country"),
+                street: Some("street"),
+                sub_administrative_area: Some("sub_administrative_area"),
+                sub_locality: Some("sub_locality"),
+            },
+        };
+
+        let expected = serde_json::to_value(Balloon::Placemark((&balloon).into())).unwrap();
+
+        assert_eq!(expected["kind"], "placemark");
+        assert_eq!(expected["place_name"], "Name");
+        assert_eq!(expected["placemark"]["postal_code"], "postal_code");
+        assert_eq!(expected["placemark"]["sub_locality"], "sub_locality");
+    }
+
+    #[test]
+    fn can_format_json_digital_touch() {
+        let expected = serde_json::to_value(Balloon::DigitalTouch {
+            touch: (&DigitalTouch::Heartbeat).into(),
+        })
+        .unwrap();
+        let actual = json!({
+            "kind": "digital_touch",
+            "touch": {"kind": "heartbeat"},
+        });
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn can_format_json_apple_pay() {
+        let balloon = AppMessage {
+            image: None,
+            url: Some("?amount=1.00&currency=USD"),
+            title: None,
+            subtitle: None,
+            caption: Some("Apple Cash"),
+            subcaption: Some("$1 Payment"),
+            trailing_caption: None,
+            trailing_subcaption: None,
+            app_name: Some("Apple Pay"),
+            ldtext: Some("Sent $1 with Apple Pay."),
+        };
+
+        let expected =
+            serde_json::to_value(Balloon::ApplePay(app_balloon(&balloon, None))).unwrap();
+
+        assert_eq!(expected["kind"], "apple_pay");
+        assert_eq!(expected["bundle_id"], Value::Null);
+        assert_eq!(expected["caption"], "Apple Cash");
+        assert_eq!(expected["subcaption"], "$1 Payment");
+        assert_eq!(expected["ldtext"], "Sent $1 with Apple Pay.");
+        assert_eq!(
+            expected["query"],
+            json!({"amount": "1.00", "currency": "USD"})
+        );
+    }
+
+    #[test]
+    fn can_format_json_generic_app() {
+        let balloon = AppMessage {
+            image: Some("image"),
+            url: Some("url"),
+            title: Some("title"),
+            subtitle: Some("subtitle"),
+            caption: Some("caption"),
+            subcaption: Some("subcaption"),
+            trailing_caption: Some("trailing_caption"),
+            trailing_subcaption: Some("trailing_subcaption"),
+            app_name: Some("app_name"),
+            ldtext: Some("ldtext"),
+        };
+
+        let expected =
+            serde_json::to_value(Balloon::App(app_balloon(&balloon, Some("bundle_id")))).unwrap();
+        let actual = json!({
+            "kind": "app",
+            "bundle_id": "bundle_id",
+            "app_name": "app_name",
+            "title": "title",
+            "subtitle": "subtitle",
+            "caption": "caption",
+            "subcaption": "subcaption",
+            "trailing_caption": "trailing_caption",
+            "trailing_subcaption": "trailing_subcaption",
+            "ldtext": "ldtext",
+            "image": "image",
+            "url": "url",
+            "query": {},
+        });
+
+        assert_eq!(expected, actual);
+    }
+}
+
+#[cfg(test)]
+mod schema_tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        Config, Exporter, JSON, Options,
+        app::export_type::ExportType,
+        exporters::{
+            json::{FOOTER, app_balloon},
+            json_schema::{Balloon, schema},
+        },
+    };
+    use imessage_database::{
+        message_types::{
+            app::AppMessage,
+            digital_touch::DigitalTouch,
+            edited::{EditStatus, EditedEvent, EditedMessage, EditedMessagePart},
+            text_effects::{Style, TextEffect},
+            url::URLMessage,
+        },
+        tables::messages::models::{AttachmentMeta, BubbleComponent, TextAttributes},
+    };
+    use serde_json::Value;
+
+    /// Assert that an instance is valid under the schema for the given export type
+    fn assert_valid(export_type: &ExportType, instance: &Value) {
+        let validator = jsonschema::validator_for(&schema(export_type).to_value()).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(instance)
+            .map(|error| format!("{}: {error}", error.instance_path))
+            .collect();
+        assert!(errors.is_empty(), "{errors:#?}");
+    }
+
+    fn format(exporter: &JSON, message: &imessage_database::tables::messages::Message) -> Value {
+        serde_json::from_str(&exporter.format_message(message).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn basic_message_matches_schema() {
+        let options = Options::fake_options(ExportType::NdJson);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hello world".to_string());
+
+        assert_valid(&ExportType::NdJson, &format(&exporter, &message));
+    }
+
+    #[test]
+    fn rich_message_matches_schema() {
+        let options = Options::fake_options(ExportType::NdJson);
+        let mut config = Config::fake_app(options);
+
+        let mut message = Config::fake_message();
+        message.guid = "24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string();
+        message.text = Some("Hi Chris\u{FFFC}".to_string());
+        message.thread_originator_guid = Some("parent_guid".to_string());
+        message.thread_originator_part = Some("1:0:5".to_string());
+        message.date_edited = 674530231992568192;
+        message.components = vec![
+            BubbleComponent::Text(vec![
+                TextAttributes::new(0, 3, vec![TextEffect::Default]),
+                TextAttributes::new(
+                    3,
+                    8,
+                    vec![
+                        TextEffect::Mention("+5558675309".to_string()),
+                        TextEffect::Styles(vec![Style::Bold]),
+                    ],
+                ),
+            ]),
+            BubbleComponent::Attachment(AttachmentMeta::default()),
+            BubbleComponent::Retracted,
+        ];
+        message.edited_parts = Some(EditedMessage {
+            parts: vec![
+                EditedMessagePart {
+                    status: EditStatus::Edited,
+                    edit_history: vec![EditedEvent {
+                        date: 674526582885055488,
+                        text: Some("Hi".to_string()),
+                        components: vec![],
+                        guid: None,
+                    }],
+                },
+                EditedMessagePart {
+                    status: EditStatus::Original,
+                    edit_history: vec![],
+                },
+                EditedMessagePart {
+                    status: EditStatus::Unsent,
+                    edit_history: vec![],
+                },
+            ],
+        });
+
+        let mut loved = Config::fake_message();
+        loved.associated_message_type = Some(2000);
+        loved.associated_message_guid =
+            Some("p:0/24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string());
+        let mut emoji = Config::fake_message();
+        emoji.associated_message_type = Some(3006);
+        emoji.associated_message_guid =
+            Some("p:0/24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string());
+        emoji.associated_message_emoji = Some("☕️".to_string());
+        config.tapbacks.insert(
+            "24C7F1C5-5B4D-4B2E-9C4D-2B0E5F3A9D10".to_string(),
+            HashMap::from([(0, vec![loved, emoji])]),
+        );
+        let exporter = JSON::new(&config).unwrap();
+
+        let actual = format(&exporter, &message);
+
+        assert_eq!(actual["reactions"].as_array().unwrap().len(), 2);
+        assert_eq!(actual["edits"].as_array().unwrap().len(), 3);
+        assert_valid(&ExportType::NdJson, &actual);
+    }
+
+    #[test]
+    fn attachments_match_schema() {
+        let options = Options::fake_options(ExportType::NdJson);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = format(&exporter, &Config::fake_message());
+        let attachment = Config::fake_attachment();
+        let mut sticker = Config::fake_attachment();
+        sticker.is_sticker = true;
+        let mut sticker_record = exporter.attachment_record(&sticker, None);
+        sticker_record.sticker = Some(exporter.sticker_details(&sticker));
+
+        message["attachments"] = serde_json::to_value([
+            exporter.attachment_record(&attachment, None),
+            sticker_record,
+        ])
+        .unwrap();
+
+        assert_valid(&ExportType::NdJson, &message);
+    }
+
+    #[test]
+    fn balloons_match_schema() {
+        let options = Options::fake_options(ExportType::NdJson);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let url = URLMessage {
+            title: Some("title"),
+            summary: None,
+            url: Some("https://example.com"),
+            original_url: None,
+            item_type: None,
+            images: vec!["image"],
+            icons: vec![],
+            site_name: None,
+            placeholder: false,
+        };
+        let app = AppMessage {
+            image: None,
+            url: Some("data:?amount=1&type=send"),
+            title: None,
+            subtitle: None,
+            caption: Some("Apple Cash"),
+            subcaption: None,
+            trailing_caption: None,
+            trailing_subcaption: None,
+            app_name: Some("Apple Pay"),
+            ldtext: None,
+        };
+
+        let balloons = [
+            Balloon::Url((&url).into()),
+            Balloon::ApplePay(app_balloon(&app, None)),
+            Balloon::App(app_balloon(&app, Some("bundle_id"))),
+            Balloon::DigitalTouch {
+                touch: (&DigitalTouch::Heartbeat).into(),
+            },
+        ];
+
+        let mut message = format(&exporter, &Config::fake_message());
+        for balloon in balloons {
+            message["balloon"] = serde_json::to_value(balloon).unwrap();
+            assert_valid(&ExportType::NdJson, &message);
+        }
+    }
+
+    #[test]
+    fn invalid_message_does_not_match_schema() {
+        let options = Options::fake_options(ExportType::NdJson);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = format(&exporter, &Config::fake_message());
+        message["is_from_me"] = Value::String("yes".to_string());
+
+        let validator = jsonschema::validator_for(&schema(&ExportType::NdJson).to_value()).unwrap();
+        assert!(!validator.is_valid(&message));
+    }
+
+    #[test]
+    fn document_matches_schema() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+        let exporter = JSON::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hello world".to_string());
+        let formatted = exporter.format_message(&message).unwrap();
+
+        for header in [
+            JSON::format_header(&config, None).unwrap(),
+            JSON::format_header(
+                &config,
+                Some(&imessage_database::tables::chat::Chat {
+                    rowid: 1,
+                    chat_identifier: "chat123".to_string(),
+                    service_name: Some("iMessage".to_string()),
+                    display_name: Some("Friends".to_string()),
+                }),
+            )
+            .unwrap(),
+        ] {
+            let document: Value =
+                serde_json::from_str(&format!("{header}\n{formatted}{FOOTER}")).unwrap();
+            assert_valid(&ExportType::Json, &document);
+        }
+    }
+
+    #[test]
+    fn empty_document_matches_schema() {
+        let options = Options::fake_options(ExportType::Json);
+        let config = Config::fake_app(options);
+
+        for header in [
+            JSON::format_header(&config, None).unwrap(),
+            JSON::format_header(
+                &config,
+                Some(&imessage_database::tables::chat::Chat {
+                    rowid: 1,
+                    chat_identifier: "chat123".to_string(),
+                    service_name: Some("iMessage".to_string()),
+                    display_name: Some("Friends".to_string()),
+                }),
+            )
+            .unwrap(),
+        ] {
+            let document: Value = serde_json::from_str(&format!("{header}{FOOTER}")).unwrap();
+            assert_valid(&ExportType::Json, &document);
+            assert_eq!(
+                document.as_object().unwrap().keys().collect::<Vec<_>>(),
+                [
+                    "chat",
+                    "exported_at",
+                    "exporter",
+                    "messages",
+                    "schema_version"
+                ]
+            );
+        }
     }
 }
@@ -0,0 +1,844 @@
+/*!
+ Records written by the [`JSON`](crate::JSON) exporter, which double as its published schema.
+
+ Every object in a `json` or `ndjson` export is serialized from one of these types, so the
+ [JSON Schema](https://json-schema.org) printed by `--print-schema` is generated from the same
+ definitions that produce the output.
+*/
+
+use std::collections::BTreeMap;
+
+use schemars::{JsonSchema, Schema, schema_for};
+use serde::Serialize;
+
+use crate::app::export_type::ExportType;
+
+use imessage_database::message_types::{
+    app_store::AppStoreMessage,
+    collaboration::CollaborationMessage,
+    digital_touch::DigitalTouch,
+    edited::EditStatus,
+    handwriting::HandwrittenMessage,
+    music::MusicMessage,
+    placemark::PlacemarkMessage,
+    sticker::StickerSource,
+    text_effects::{Animation, Style, TextEffect, Unit},
+    url::URLMessage,
+    variants::{Tapback, TapbackAction},
+};
+
+/// Version of the document structure written by the JSON exporter
+///
+/// This follows semantic versioning in the same change as the records it describes:
+/// - Bump the major version when a field is renamed, removed, or changes type
+/// - Bump the minor version when a field or enum value is added
+/// - Bump the patch version when only descriptions change
+pub const SCHEMA_VERSION: &str = "1.0.0";
+
+/// Generate the schema that each file written for the given export type conforms to
+///
+/// `json` files are a single [`Document`], while each line of an `ndjson` file is a [`Message`].
+/// Both schemas carry the [`SCHEMA_VERSION`] they describe in their `x-schema-version` keyword.
+#[must_use]
+pub fn schema(export_type: &ExportType) -> Schema {
+    match export_type {
+        ExportType::NdJson => schema_for!(Message<'static>),
+        _ => schema_for!(Document<'static>),
+    }
+}
+
+// MARK: Document
+/// A single conversation exported as one JSON document
+///
+/// The JSON exporter streams messages into this document, so it writes the fields before
+/// `messages` itself; new fields need to be added there too.
+#[derive(Serialize, JsonSchema)]
+#[schemars(
+    title = "imessage-exporter JSON document",
+    extend("x-schema-version" = SCHEMA_VERSION)
+)]
+pub struct Document<'a> {
+    /// Version of the schema this document conforms to
+    #[schemars(extend("const" = SCHEMA_VERSION))]
+    pub schema_version: &'a str,
+    /// The program that wrote the document
+    pub exporter: ExporterInfo<'a>,
+    /// When the export was run, in RFC 3339 format
+    pub exported_at: String,
+    /// The conversation the messages belong to, or `null` for messages without one
+    pub chat: Option<ChatInfo<'a>>,
+    /// The messages in the conversation, in chronological order
+    pub messages: Vec<Message<'a>>,
+}
+
+/// The name and version of the exporter
+#[derive(Serialize, JsonSchema)]
+pub struct ExporterInfo<'a> {
+    /// Name of the program
+    pub name: &'a str,
+    /// Version of the program
+    pub version: &'a str,
+}
+
+/// Metadata for an exported conversation
+#[derive(Serialize, JsonSchema)]
+pub struct ChatInfo<'a> {
+    /// The conversation's row in the `chat` table
+    pub rowid: i32,
+    /// The unique identifier of the conversation
+    pub chat_identifier: &'a str,
+    /// The name of the conversation, if one was assigned
+    pub display_name: Option<&'a str>,
+    /// The service the conversation uses, such as `iMessage` or `SMS`
+    pub service: String,
+    /// Everyone else in the conversation
+    pub participants: Vec<Participant<'a>>,
+}
+
+/// A member of a conversation
+#[derive(Serialize, JsonSchema)]
+pub struct Participant<'a> {
+    /// The participant's row in the `handle` table
+    pub handle_id: i32,
+    /// The participant's display name
+    pub name: &'a str,
+}
+
+// MARK: Message
+/// A single message; each line of an `ndjson` export is one of these
+#[derive(Serialize, JsonSchema)]
+#[schemars(
+    title = "imessage-exporter JSON message",
+    extend("x-schema-version" = SCHEMA_VERSION)
+)]
+pub struct Message<'a> {
+    /// When the message was sent
+    pub timestamp: String,
+    /// Who sent the message
+    pub sender: &'a str,
+    /// The plain text of the message
+    pub contents: &'a str,
+    /// The components that make up the body of the message, in display order
+    pub parts: Vec<Part<'a>>,
+    /// Files attached to the message
+    pub attachments: Vec<Attachment<'a>>,
+    /// When the message was read, if it was
+    pub readtime: Option<String>,
+    /// `true` if the database owner sent the message
+    pub is_from_me: bool,
+    /// The unique identifier of the message
+    pub guid: &'a str,
+    /// The row of the conversation the message belongs to
+    pub chat_id: Option<i32>,
+    /// The contents of an app integration or rich link, if the message is one
+    pub balloon: Option<Balloon<'a>>,
+    /// Tapbacks applied to any part of the message, as one list in part order
+    ///
+    /// Reactions are not nested under the part they apply to, because `parts` only describes
+    /// the message body. Use each reaction's `part` to find its target.
+    pub reactions: Vec<Reaction<'a>>,
+    /// The message part this message is a threaded reply to
+    pub reply_to: Option<ReplyTo<'a>>,
+    /// Threaded replies to any part of the message, in part order
+    ///
+    /// Replies are only nested when exporting with `--nest-replies`, and only the ones that match
+    /// the export filters, which are then not written as top-level records. Otherwise this is `null`
+    /// and every reply is its own record that links back to this message with `reply_to`.
+    pub replies: Option<Vec<Message<'a>>>,
+    /// When the message was last edited or unsent
+    pub edited_at: Option<String>,
+    /// The edit and unsend history of each part, if the message was edited
+    pub edits: Option<Vec<Edit<'a>>>,
+}
+
+/// A reference to the message part a reply belongs to
+#[derive(Serialize, JsonSchema)]
+pub struct ReplyTo<'a> {
+    /// The GUID of the message that started the thread
+    pub guid: &'a str,
+    /// The index of the part of that message that was replied to
+    pub part: usize,
+}
+
+// MARK: Parts
+/// A component of a message body
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Part<'a> {
+    /// A run of formatted text
+    Text {
+        /// The index of this part in the message
+        part: usize,
+        /// `true` if this part was edited
+        edited: bool,
+        /// Ranges of the message text and the effects applied to them
+        ranges: Option<Vec<TextRange<'a>>>,
+    },
+    /// An inline attachment
+    Attachment {
+        /// The index of this part in the message
+        part: usize,
+        /// `true` if this part was edited
+        edited: bool,
+        /// The index of the file in the message's `attachments`, if it exists
+        attachment: Option<usize>,
+        /// The GUID of the attachment
+        guid: Option<&'a str>,
+        /// The attachment's original filename
+        name: Option<&'a str>,
+        /// The transcription of an audio message
+        transcription: Option<&'a str>,
+        /// The width of the attachment in points
+        width: Option<f64>,
+        /// The height of the attachment in points
+        height: Option<f64>,
+    },
+    /// An app integration, described by the message's `balloon`
+    App {
+        /// The index of this part in the message
+        part: usize,
+        /// `true` if this part was edited
+        edited: bool,
+    },
+    /// A part that was unsent
+    Retracted {
+        /// The index of this part in the message
+        part: usize,
+        /// `true` if this part was edited
+        edited: bool,
+    },
+}
+
+/// A range of message text and the effects applied to it
+#[derive(Serialize, JsonSchema)]
+pub struct TextRange<'a> {
+    /// The byte offset where the range starts
+    pub start: usize,
+    /// The byte offset where the range ends
+    pub end: usize,
+    /// The text in the range
+    pub text: &'a str,
+    /// The effects applied to the range
+    pub effects: Vec<Effect<'a>>,
+}
+
+/// An effect applied to a range of message text
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Effect<'a> {
+    /// Plain text
+    Default,
+    /// A mention of another participant
+    Mention {
+        /// The handle that was mentioned
+        handle: &'a str,
+    },
+    /// A link detected in the text
+    Link {
+        /// The destination of the link
+        url: &'a str,
+    },
+    /// A one-time passcode
+    Otp,
+    /// Text formatting
+    Styles {
+        /// The styles applied to the text
+        styles: Vec<TextStyle>,
+    },
+    /// An animated text effect
+    Animated {
+        /// The animation applied to the text
+        animation: TextAnimation,
+        /// The raw identifier of an unknown animation
+        id: Option<i64>,
+    },
+    /// A unit that can be converted
+    Conversion {
+        /// The kind of unit
+        unit: ConversionUnit,
+    },
+}
+
+impl<'a> From<&'a TextEffect> for Effect<'a> {
+    fn from(effect: &'a TextEffect) -> Self {
+        match effect {
+            TextEffect::Default => Self::Default,
+            TextEffect::Mention(handle) => Self::Mention { handle },
+            TextEffect::Link(url) => Self::Link { url },
+            TextEffect::OTP => Self::Otp,
+            TextEffect::Styles(styles) => Self::Styles {
+                styles: styles.iter().map(Into::into).collect(),
+            },
+            TextEffect::Animated(animation) => Self::Animated {
+                animation: animation.into(),
+                id: match animation {
+                    Animation::Unknown(id) => Some(*id),
+                    _ => None,
+                },
+            },
+            TextEffect::Conversion(unit) => Self::Conversion { unit: unit.into() },
+        }
+    }
+}
+
+/// A text style
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TextStyle {
+    Bold,
+    Italic,
+    Strikethrough,
+    Underline,
+}
+
+impl From<&Style> for TextStyle {
+    fn from(style: &Style) -> Self {
+        match style {
+            Style::Bold => Self::Bold,
+            Style::Italic => Self::Italic,
+            Style::Strikethrough => Self::Strikethrough,
+            Style::Underline => Self::Underline,
+        }
+    }
+}
+
+/// A text animation
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAnimation {
+    Big,
+    Small,
+    Shake,
+    Nod,
+    Explode,
+    Ripple,
+    Bloom,
+    Jitter,
+    Unknown,
+}
+
+impl From<&Animation> for TextAnimation {
+    fn from(animation: &Animation) -> Self {
+        match animation {
+            Animation::Big => Self::Big,
+            Animation::Small => Self::Small,
+            Animation::Shake => Self::Shake,
+            Animation::Nod => Self::Nod,
+            Animation::Explode => Self::Explode,
+            Animation::Ripple => Self::Ripple,
+            Animation::Bloom => Self::Bloom,
+            Animation::Jitter => Self::Jitter,
+            Animation::Unknown(_) => Self::Unknown,
+        }
+    }
+}
+
+/// A unit of measure
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionUnit {
+    Currency,
+    Distance,
+    Temperature,
+    Timezone,
+    Volume,
+    Weight,
+}
+
+impl From<&Unit> for ConversionUnit {
+    fn from(unit: &Unit) -> Self {
+        match unit {
+            Unit::Currency => Self::Currency,
+            Unit::Distance => Self::Distance,
+            Unit::Temperature => Self::Temperature,
+            Unit::Timezone => Self::Timezone,
+            Unit::Volume => Self::Volume,
+            Unit::Weight => Self::Weight,
+        }
+    }
+}
+
+// MARK: Attachments
+/// A file attached to a message
+#[derive(Serialize, JsonSchema)]
+pub struct Attachment<'a> {
+    /// The name of the file
+    pub filename: &'a str,
+    /// The name of the file when it was sent
+    pub transfer_name: Option<&'a str>,
+    /// The Uniform Type Identifier of the file
+    pub uti: Option<&'a str>,
+    /// The MIME type of the file, after any conversion
+    pub mime_type: String,
+    /// The human readable size of the file
+    pub file_size: String,
+    /// The size of the file in bytes
+    pub total_bytes: i64,
+    /// `true` if the file is a sticker
+    pub is_sticker: bool,
+    /// `true` if the file is not displayed in the conversation
+    pub hide_attachment: bool,
+    /// The prompt used to generate a Genmoji
+    pub emoji_description: Option<&'a str>,
+    /// The transcription of an audio message
+    pub transcription: Option<&'a str>,
+    /// The location of the original file
+    pub resolved_path: Option<String>,
+    /// The location of the exported copy, relative to the export directory
+    pub exported_path: Option<String>,
+    /// Sticker details, if the file is a sticker
+    pub sticker: Option<Sticker>,
+}
+
+/// Details about a sticker
+#[derive(Serialize, JsonSchema, Clone)]
+pub struct Sticker {
+    /// Where the sticker came from
+    pub source: StickerKind,
+    /// The bundle ID of the app that provided the sticker
+    pub bundle_id: Option<String>,
+    /// The name of the app that provided the sticker
+    pub app_name: Option<String>,
+    /// The effect applied to a user generated sticker
+    pub effect: Option<String>,
+}
+
+/// The source of a sticker
+#[derive(Serialize, JsonSchema, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum StickerKind {
+    Genmoji,
+    Memoji,
+    UserGenerated,
+    App,
+    Unknown,
+}
+
+impl From<Option<&StickerSource>> for StickerKind {
+    fn from(source: Option<&StickerSource>) -> Self {
+        match source {
+            Some(StickerSource::Genmoji) => Self::Genmoji,
+            Some(StickerSource::Memoji) => Self::Memoji,
+            Some(StickerSource::UserGenerated) => Self::UserGenerated,
+            Some(StickerSource::App(_)) => Self::App,
+            None => Self::Unknown,
+        }
+    }
+}
+
+// MARK: Reactions
+/// A tapback applied to a message part
+#[derive(Serialize, JsonSchema)]
+pub struct Reaction<'a> {
+    /// The index of the message part the tapback applies to
+    pub part: usize,
+    /// The kind of tapback
+    pub kind: ReactionKind,
+    /// The emoji used for an `emoji` tapback
+    pub emoji: Option<&'a str>,
+    /// The path to the image used for a `sticker` tapback
+    pub sticker: Option<String>,
+    /// Whether the tapback was added or removed
+    pub action: ReactionAction,
+    /// Who sent the tapback
+    pub sender: &'a str,
+    /// `true` if the database owner sent the tapback
+    pub is_from_me: bool,
+    /// When the tapback was sent
+    pub timestamp: String,
+    /// The unique identifier of the tapback message
+    pub guid: &'a str,
+}
+
+/// The kind of a tapback
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionKind {
+    Loved,
+    Liked,
+    Disliked,
+    Laughed,
+    Emphasized,
+    Questioned,
+    Emoji,
+    Sticker,
+}
+
+impl From<&Tapback<'_>> for ReactionKind {
+    fn from(tapback: &Tapback<'_>) -> Self {
+        match tapback {
+            Tapback::Loved => Self::Loved,
+            Tapback::Liked => Self::Liked,
+            Tapback::Disliked => Self::Disliked,
+            Tapback::Laughed => Self::Laughed,
+            Tapback::Emphasized => Self::Emphasized,
+            Tapback::Questioned => Self::Questioned,
+            Tapback::Emoji(_) => Self::Emoji,
+            Tapback::Sticker => Self::Sticker,
+        }
+    }
+}
+
+/// Whether a tapback was added or removed
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionAction {
+    Added,
+    Removed,
+}
+
+impl From<&TapbackAction> for ReactionAction {
+    fn from(action: &TapbackAction) -> Self {
+        match action {
+            TapbackAction::Added => Self::Added,
+            TapbackAction::Removed => Self::Removed,
+        }
+    }
+}
+
+// MARK: Edits
+/// The edit and unsend history of a message part
+#[derive(Serialize, JsonSchema)]
+pub struct Edit<'a> {
+    /// The index of the message part
+    pub part: usize,
+    /// What happened to the part
+    pub status: EditKind,
+    /// When the part was unsent
+    pub timestamp: Option<String>,
+    /// Every version of the part, oldest first
+    pub history: Vec<EditEvent<'a>>,
+}
+
+/// What happened to a message part
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EditKind {
+    Edited,
+    Unsent,
+    Original,
+}
+
+impl From<&EditStatus> for EditKind {
+    fn from(status: &EditStatus) -> Self {
+        match status {
+            EditStatus::Edited => Self::Edited,
+            EditStatus::Unsent => Self::Unsent,
+            EditStatus::Original => Self::Original,
+        }
+    }
+}
+
+/// A single version of an edited message part
+#[derive(Serialize, JsonSchema)]
+pub struct EditEvent<'a> {
+    /// When this version was sent
+    pub timestamp: String,
+    /// The text of this version
+    pub text: Option<&'a str>,
+    /// The GUID of the message that holds this version
+    pub guid: Option<&'a str>,
+}
+
+// MARK: Balloons
+/// The contents of an app integration or rich link
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Balloon<'a> {
+    /// A rich link preview
+    Url(UrlBalloon<'a>),
+    /// An Apple Music link
+    Music(MusicBalloon<'a>),
+    /// A shared document or project
+    Collaboration(CollaborationBalloon<'a>),
+    /// An App Store link
+    AppStore(AppStoreBalloon<'a>),
+    /// A shared location
+    Placemark(PlacemarkBalloon<'a>),
+    /// A handwritten note
+    Handwriting(HandwritingBalloon<'a>),
+    /// A Digital Touch message
+    DigitalTouch {
+        /// The kind of touch that was sent
+        touch: Touch,
+    },
+    /// An Apple Pay transaction
+    ApplePay(AppBalloon<'a>),
+    /// A Fitness activity
+    Fitness(AppBalloon<'a>),
+    /// A Photos slideshow
+    Slideshow(AppBalloon<'a>),
+    /// A Find My location
+    FindMy(AppBalloon<'a>),
+    /// A Check In
+    CheckIn(AppBalloon<'a>),
+    /// Any other app, generally third party
+    App(AppBalloon<'a>),
+    /// A balloon whose payload could not be parsed
+    Error {
+        /// Why the balloon could not be parsed
+        error: String,
+    },
+}
+
+/// A rich link preview
+#[derive(Serialize, JsonSchema, Default)]
+pub struct UrlBalloon<'a> {
+    pub title: Option<&'a str>,
+    pub summary: Option<&'a str>,
+    pub url: Option<&'a str>,
+    pub original_url: Option<&'a str>,
+    pub item_type: Option<&'a str>,
+    pub images: Vec<&'a str>,
+    pub icons: Vec<&'a str>,
+    pub site_name: Option<&'a str>,
+    pub placeholder: bool,
+}
+
+impl<'a> From<&URLMessage<'a>> for UrlBalloon<'a> {
+    fn from(balloon: &URLMessage<'a>) -> Self {
+        Self {
+            title: balloon.title,
+            summary: balloon.summary,
+            url: balloon.url,
+            original_url: balloon.original_url,
+            item_type: balloon.item_type,
+            images: balloon.images.clone(),
+            icons: balloon.icons.clone(),
+            site_name: balloon.site_name,
+            placeholder: balloon.placeholder,
+        }
+    }
+}
+
+/// An Apple Music link
+#[derive(Serialize, JsonSchema)]
+pub struct MusicBalloon<'a> {
+    pub url: Option<&'a str>,
+    pub preview: Option<&'a str>,
+    pub artist: Option<&'a str>,
+    pub album: Option<&'a str>,
+    pub track_name: Option<&'a str>,
+    pub lyrics: Option<Vec<&'a str>>,
+}
+
+impl<'a> From<&MusicMessage<'a>> for MusicBalloon<'a> {
+    fn from(balloon: &MusicMessage<'a>) -> Self {
+        Self {
+            url: balloon.url,
+            preview: balloon.preview,
+            artist: balloon.artist,
+            album: balloon.album,
+            track_name: balloon.track_name,
+            lyrics: balloon.lyrics.clone(),
+        }
+    }
+}
+
+/// A shared document or project
+#[derive(Serialize, JsonSchema)]
+pub struct CollaborationBalloon<'a> {
+    pub original_url: Option<&'a str>,
+    pub url: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub creation_date: Option<f64>,
+    pub bundle_id: Option<&'a str>,
+    pub app_name: Option<&'a str>,
+}
+
+impl<'a> From<&CollaborationMessage<'a>> for CollaborationBalloon<'a> {
+    fn from(balloon: &CollaborationMessage<'a>) -> Self {
+        Self {
+            original_url: balloon.original_url,
+            url: balloon.url,
+            title: balloon.title,
+            creation_date: balloon.creation_date,
+            bundle_id: balloon.bundle_id,
+            app_name: balloon.app_name,
+        }
+    }
+}
+
+/// An App Store link
+#[derive(Serialize, JsonSchema)]
+pub struct AppStoreBalloon<'a> {
+    pub url: Option<&'a str>,
+    pub original_url: Option<&'a str>,
+    pub app_name: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub platform: Option<&'a str>,
+    pub genre: Option<&'a str>,
+}
+
+impl<'a> From<&AppStoreMessage<'a>> for AppStoreBalloon<'a> {
+    fn from(balloon: &AppStoreMessage<'a>) -> Self {
+        Self {
+            url: balloon.url,
+            original_url: balloon.original_url,
+            app_name: balloon.app_name,
+            description: balloon.description,
+            platform: balloon.platform,
+            genre: balloon.genre,
+        }
+    }
+}
+
+/// A shared location
+#[derive(Serialize, JsonSchema)]
+pub struct PlacemarkBalloon<'a> {
+    pub url: Option<&'a str>,
+    pub original_url: Option<&'a str>,
+    pub place_name: Option<&'a str>,
+    pub placemark: PlacemarkDetails<'a>,
+}
+
+/// The address of a shared location
+#[derive(Serialize, JsonSchema)]
+pub struct PlacemarkDetails<'a> {
+    pub name: Option<&'a str>,
+    pub address: Option<&'a str>,
+    pub state: Option<&'a str>,
+    pub city: Option<&'a str>,
+    pub iso_country_code: Option<&'a str>,
+    pub postal_code: Option<&'a str>,
+    pub country: Option<&'a str>,
+    pub street: Option<&'a str>,
+    pub sub_administrative_area: Option<&'a str>,
+    pub sub_locality: Option<&'a str>,
+}
+
+impl<'a> From<&PlacemarkMessage<'a>> for PlacemarkBalloon<'a> {
+    fn from(balloon: &PlacemarkMessage<'a>) -> Self {
+        let placemark = &balloon.placemark;
+        Self {
+            url: balloon.url,
+            original_url: balloon.original_url,
+            place_name: balloon.place_name,
+            placemark: PlacemarkDetails {
+                name: placemark.name,
+                address: placemark.address,
+                state: placemark.state,
+                city: placemark.city,
+                iso_country_code: placemark.iso_country_code,
+                postal_code: placemark.postal_code,
+                country: placemark.country,
+                street: placemark.street,
+                sub_administrative_area: placemark.sub_administrative_area,
+                sub_locality: placemark.sub_locality,
+            },
+        }
+    }
+}
+
+/// A handwritten note
+#[derive(Serialize, JsonSchema)]
+pub struct HandwritingBalloon<'a> {
+    /// The unique identifier of the note
+    pub id: &'a str,
+    /// When the note was created, as seconds since `2001-01-01 00:00:00`
+    pub created_at: i64,
+    /// The width of the note in pixels
+    pub width: u16,
+    /// The height of the note in pixels
+    pub height: u16,
+    /// The note rendered as an SVG image
+    pub svg: String,
+}
+
+impl<'a> From<&'a HandwrittenMessage> for HandwritingBalloon<'a> {
+    fn from(balloon: &'a HandwrittenMessage) -> Self {
+        Self {
+            id: &balloon.id,
+            created_at: balloon.created_at,
+            width: balloon.width,
+            height: balloon.height,
+            svg: balloon.render_svg(),
+        }
+    }
+}
+
+/// The animation sent in a Digital Touch message
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Touch {
+    Tap,
+    Heartbeat,
+    Sketch,
+    Kiss,
+    Fireball,
+    Unknown,
+}
+
+impl From<&DigitalTouch> for Touch {
+    fn from(touch: &DigitalTouch) -> Self {
+        match touch {
+            DigitalTouch::Tap => Self::Tap,
+            DigitalTouch::Heartbeat => Self::Heartbeat,
+            DigitalTouch::Sketch => Self::Sketch,
+            DigitalTouch::Kiss => Self::Kiss,
+            DigitalTouch::Fireball => Self::Fireball,
+            DigitalTouch::Unknown => Self::Unknown,
+        }
+    }
+}
+
+/// An app integration
+#[derive(Serialize, JsonSchema)]
+pub struct AppBalloon<'a> {
+    /// The bundle ID of a third party app
+    pub bundle_id: Option<&'a str>,
+    pub app_name: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub subtitle: Option<&'a str>,
+    pub caption: Option<&'a str>,
+    pub subcaption: Option<&'a str>,
+    pub trailing_caption: Option<&'a str>,
+    pub trailing_subcaption: Option<&'a str>,
+    pub ldtext: Option<&'a str>,
+    pub image: Option<&'a str>,
+    pub url: Option<&'a str>,
+    /// The query parameters of the balloon's URL
+    pub query: BTreeMap<String, String>,
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::{
+        app::export_type::ExportType,
+        exporters::json_schema::{SCHEMA_VERSION, schema},
+    };
+
+    #[test]
+    fn can_generate_document_schema() {
+        let schema = schema(&ExportType::Json).to_value();
+
+        assert_eq!(schema["title"], "imessage-exporter JSON document");
+        assert_eq!(schema["x-schema-version"], SCHEMA_VERSION);
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+        assert!(schema["$defs"]["Message"].is_object());
+    }
+
+    #[test]
+    fn can_generate_message_schema() {
+        let schema = schema(&ExportType::NdJson).to_value();
+
+        assert_eq!(schema["title"], "imessage-exporter JSON message");
+        assert_eq!(schema["x-schema-version"], SCHEMA_VERSION);
+        assert!(schema["properties"]["parts"].is_object());
+        assert!(schema["$defs"]["Balloon"].is_object());
+    }
+
+    #[test]
+    fn can_compile_schemas() {
+        for export_type in [ExportType::Json, ExportType::NdJson] {
+            let schema: Value = schema(&export_type).to_value();
+            assert!(jsonschema::validator_for(&schema).is_ok());
+        }
+    }
+}
@@ -0,0 +1,1954 @@
+use std::{
+    borrow::Cow,
+    collections::{
+        HashMap,
+        hash_map::Entry::{Occupied, Vacant},
+    },
+    fmt::Write as FmtWrite,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    app::{
+        compatibility::attachment_manager::AttachmentManagerMode, error::RuntimeError,
+        progress::ExportProgress, runtime::Config,
+    },
+    exporters::exporter::{
+        ATTACHMENT_NO_FILENAME, BalloonFormatter, Exporter, MessageFormatter, TextEffectFormatter,
+    },
+};
+
+use imessage_database::{
+    error::{plist::PlistParseError, table::TableError},
+    message_types::{
+        app::AppMessage,
+        app_store::AppStoreMessage,
+        collaboration::CollaborationMessage,
+        digital_touch::{self, DigitalTouch},
+        edited::{EditStatus, EditedMessage},
+        expressives::{BubbleEffect, Expressive, ScreenEffect},
+        handwriting::HandwrittenMessage,
+        music::MusicMessage,
+        placemark::PlacemarkMessage,
+        sticker::StickerSource,
+        text_effects::{Animation, Style, TextEffect, Unit},
+        url::URLMessage,
+        variants::{
+            Announcement, BalloonProvider, CustomBalloon, Tapback, TapbackAction, URLOverride,
+            Variant,
+        },
+    },
+    tables::{
+        attachment::{Attachment, MediaType},
+        chat::Chat,
+        messages::{
+            Message,
+            models::{AttachmentMeta, BubbleComponent, GroupAction, TextAttributes},
+        },
+        table::{FITNESS_RECEIVER, ME, ORPHANED, Table, YOU},
+    },
+    util::{
+        dates::{TIMESTAMP_FACTOR, format, get_local_time, readable_diff},
+        plist::parse_ns_keyed_archiver,
+    },
+};
+
+pub struct Markdown<'a> {
+    /// Data that is setup from the application's runtime
+    pub config: &'a Config,
+    /// Handles to files we want to write messages to
+    /// Map of resolved chatroom file location to a buffered writer
+    pub files: HashMap<String, BufWriter<File>>,
+    /// Writer instance for orphaned messages
+    pub orphaned: BufWriter<File>,
+    /// Progress Bar model for alerting the user about current export state
+    pb: ExportProgress,
+}
+
+// MARK: Exporter
+impl<'a> Exporter<'a> for Markdown<'a> {
+    fn new(config: &'a Config) -> Result<Self, RuntimeError> {
+        let mut orphaned = config.options.export_path.clone();
+        orphaned.push(ORPHANED);
+        orphaned.set_extension("md");
+
+        Ok(Markdown {
+            config,
+            files: HashMap::new(),
+            orphaned: Markdown::create_file(config, &orphaned, None)?,
+            pb: ExportProgress::new(),
+        })
+    }
+
+    fn iter_messages(&mut self) -> Result<(), RuntimeError> {
+        // Tell the user what we are doing
+        eprintln!(
+            "Exporting to {} as markdown...",
+            self.config.options.export_path.display()
+        );
+
+        // Keep track of current message ROWID
+        let mut current_message_row = -1;
+
+        // Set up progress bar
+        let mut current_message = 0;
+        let total_messages =
+            Message::get_count(self.config.db(), &self.config.options.query_context)?;
+        self.pb.start(total_messages);
+
+        let mut statement =
+            Message::stream_rows(self.config.db(), &self.config.options.query_context)?;
+
+        let messages = statement
+            .query_map([], |row| Ok(Message::from_row(row)))
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
+
+        for message in messages {
+            let mut msg = Message::extract(message)?;
+
+            // Early escape if we try and render the same message GUID twice
+            // See https://github.com/ReagentX/imessage-exporter/issues/135 for rationale
+            if msg.rowid == current_message_row {
+                current_message += 1;
+                continue;
+            }
+            current_message_row = msg.rowid;
+
+            // Generate the text of the message
+            let _ = msg.generate_text(self.config.db());
+
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
+            }
+
+            // Render the announcement in-line
+            if msg.is_announcement() {
+                let announcement = self.format_announcement(&msg);
+                Markdown::write_to_file(self.get_or_create_file(&msg)?, &announcement)?;
+            }
+            // Message replies and tapbacks are rendered in context, so no need to render them separately
+            else if !msg.is_tapback() {
+                let message = self.format_message(&msg, 0)?;
+                Markdown::write_to_file(self.get_or_create_file(&msg)?, &message)?;
+            }
+            current_message += 1;
+            if current_message % 99 == 0 {
+                self.pb.set_position(current_message);
+            }
+        }
+        self.pb.finish();
+        Ok(())
+    }
+
+    /// Create a file for the given chat, caching it so we don't need to build it later
+    fn get_or_create_file(
+        &mut self,
+        message: &Message,
+    ) -> Result<&mut BufWriter<File>, RuntimeError> {
+        match self.config.conversation(message) {
+            Some((chatroom, _)) => {
+                let filename = self.config.filename(chatroom);
+                match self.files.entry(filename) {
+                    Occupied(entry) => Ok(entry.into_mut()),
+                    Vacant(entry) => {
+                        let mut path = self.config.options.export_path.clone();
+                        path.push(self.config.filename(chatroom));
+                        path.set_extension("md");
+
+                        let buf = Markdown::create_file(self.config, &path, Some(chatroom))?;
+                        Ok(entry.insert(buf))
+                    }
+                }
+            }
+            None => Ok(&mut self.orphaned),
+        }
+    }
+
+    fn write_to_file(file: &mut BufWriter<File>, text: &str) -> Result<(), RuntimeError> {
+        file.write_all(text.as_bytes())
+            .map_err(RuntimeError::DiskError)
+    }
+}
+
+// MARK: Writer
+impl<'a> MessageFormatter<'a> for Markdown<'a> {
+    fn format_message(&self, message: &Message, indent_size: usize) -> Result<String, TableError> {
+        // Data we want to write to a file
+        let mut formatted_message = String::new();
+
+        // Add message sender and date
+        let who = self.config.who(
+            message.handle_id,
+            message.is_from_me(),
+            &message.destination_caller_id,
+        );
+        self.add_block(
+            &mut formatted_message,
+            &format!("**{}** · {}", escape_markdown(who), self.get_time(message)),
+        );
+
+        // If message was deleted, annotate it
+        if message.is_deleted() {
+            self.add_block(
+                &mut formatted_message,
+                "*This message was deleted from the conversation!*",
+            );
+        }
+
+        // Useful message metadata
+        let message_parts = &message.components;
+        let mut attachments = Attachment::from_message(self.config.db(), message)?;
+        let mut replies = message.get_replies(self.config.db())?;
+
+        // Index of where we are in the attachment Vector
+        let mut attachment_index: usize = 0;
+
+        // Render subject
+        if let Some(subject) = &message.subject {
+            self.add_block(
+                &mut formatted_message,
+                &format!("**{}**", escape_markdown(subject)),
+            );
+        }
+
+        // Handle SharePlay
+        if message.is_shareplay() {
+            self.add_block(
+                &mut formatted_message,
+                &format!("*{}*", self.format_shareplay()),
+            );
+        }
+
+        // Handle Shared Location
+        if message.started_sharing_location() || message.stopped_sharing_location() {
+            self.add_block(
+                &mut formatted_message,
+                &format!("*{}*", self.format_shared_location(message)),
+            );
+        }
+
+        // Generate the message body from it's components
+        for (idx, message_part) in message_parts.iter().enumerate() {
+            match message_part {
+                // Fitness messages have a prefix that we need to replace with the opposite if who sent the message
+                BubbleComponent::Text(text_attrs) => {
+                    if let Some(text) = &message.text {
+                        // Render edited message content, if applicable
+                        if message.is_part_edited(idx) {
+                            if let Some(edited_parts) = &message.edited_parts
+                                && let Some(edited) =
+                                    self.format_edited(message, edited_parts, idx, "")
+                            {
+                                self.add_block(&mut formatted_message, &edited);
+                            }
+                        } else {
+                            let mut formatted_text = self.format_attributes(text, text_attrs);
+
+                            // If we failed to parse any text above, use the original text
+                            if formatted_text.is_empty() {
+                                formatted_text.push_str(&escape_markdown(text));
+                            }
+
+                            self.add_block(
+                                &mut formatted_message,
+                                &hard_breaks(&formatted_text.replace(FITNESS_RECEIVER, YOU)),
+                            );
+                        }
+                    }
+                }
+                BubbleComponent::Attachment(metadata) => {
+                    match attachments.get_mut(attachment_index) {
+                        Some(attachment) => {
+                            if attachment.is_sticker {
+                                let result = self.format_sticker(attachment, message);
+                                self.add_block(&mut formatted_message, &result);
+                            } else {
+                                match self.format_attachment(attachment, message, metadata) {
+                                    Ok(result) => {
+                                        self.add_block(&mut formatted_message, &result);
+                                    }
+                                    Err(result) => {
+                                        self.add_block(
+                                            &mut formatted_message,
+                                            &escape_markdown(result),
+                                        );
+                                    }
+                                }
+                                attachment_index += 1;
+                            }
+                        }
+                        // Attachment does not exist in attachments table
+                        None => {
+                            self.add_block(&mut formatted_message, "*Attachment missing!*");
+                        }
+                    }
+                }
+                BubbleComponent::App => match self.format_app(message, &mut attachments, "") {
+                    Ok(ok_bubble) => self.add_block(&mut formatted_message, &ok_bubble),
+                    Err(why) => self.add_block(
+                        &mut formatted_message,
+                        &format!("*Unable to format app message: {why}*"),
+                    ),
+                },
+                BubbleComponent::Retracted => {
+                    if let Some(edited_parts) = &message.edited_parts
+                        && let Some(edited) = self.format_edited(message, edited_parts, idx, "")
+                    {
+                        self.add_block(&mut formatted_message, &edited);
+                    }
+                }
+            }
+
+            // Handle Tapbacks
+            if let Some(tapbacks_map) = self.config.tapbacks.get(&message.guid)
+                && let Some(tapbacks) = tapbacks_map.get(&idx)
+            {
+                let mut formatted_tapbacks = String::new();
+                tapbacks
+                    .iter()
+                    .try_for_each(|tapback| -> Result<(), TableError> {
+                        let formatted = self.format_tapback(tapback)?;
+                        if !formatted.is_empty() {
+                            let _ = writeln!(formatted_tapbacks, "- {formatted}");
+                        }
+                        Ok(())
+                    })?;
+
+                if !formatted_tapbacks.is_empty() {
+                    self.add_block(
+                        &mut formatted_message,
+                        &format!("*Tapbacks:*\n\n{}", formatted_tapbacks.trim_end()),
+                    );
+                }
+            }
+
+            // Handle Replies
+            if let Some(replies) = replies.get_mut(&idx) {
+                replies
+                    .iter_mut()
+                    .try_for_each(|reply| -> Result<(), TableError> {
+                        let _ = reply.generate_text(self.config.db());
+                        if !reply.is_tapback() {
+                            self.add_block(
+                                &mut formatted_message,
+                                &blockquote(&self.format_message(reply, indent_size + 1)?),
+                            );
+                        }
+                        Ok(())
+                    })?;
+            }
+        }
+
+        // Handle expressives
+        let expressive = self.format_expressive(message);
+        if !expressive.is_empty() {
+            self.add_block(
+                &mut formatted_message,
+                &format!("*{}*", escape_markdown(expressive)),
+            );
+        }
+
+        // Add a note if the message is a reply
+        if message.is_reply() && indent_size == 0 {
+            self.add_block(
+                &mut formatted_message,
+                "*This message responded to an earlier message.*",
+            );
+        }
+
+        Ok(formatted_message)
+    }
+
+    fn format_attachment(
+        &self,
+        attachment: &'a mut Attachment,
+        message: &Message,
+        metadata: &AttachmentMeta,
+    ) -> Result<String, &'a str> {
+        // When encoding videos, alert the user that the time estimate may be inaccurate
+        let will_encode = matches!(attachment.mime_type(), MediaType::Video(_))
+            && matches!(
+                self.config.options.attachment_manager.mode,
+                AttachmentManagerMode::Full
+            );
+
+        if will_encode {
+            self.pb
+                .set_busy_style("Encoding video, estimates paused...".to_string());
+        }
+
+        // Copy the file, if requested
+        self.config
+            .options
+            .attachment_manager
+            .handle_attachment(message, attachment, self.config)
+            .ok_or(attachment.filename().ok_or(ATTACHMENT_NO_FILENAME)?)?;
+
+        if will_encode {
+            self.pb.set_default_style();
+        }
+
+        // Build a relative filepath from the fully qualified one on the `Attachment`
+        let path = self.config.message_attachment_path(attachment);
+        let name = escape_markdown(attachment.filename().unwrap_or(ATTACHMENT_NO_FILENAME));
+
+        // Images are embedded, everything else is linked
+        let mut out_s = match attachment.mime_type() {
+            MediaType::Image(_) => format!("![{name}]({})", link_destination(&path)),
+            _ => format!("[{name}]({})", link_destination(&path)),
+        };
+
+        // Append the transcription if one is provided
+        if let Some(transcription) = &metadata.transcription {
+            let _ = write!(
+                out_s,
+                "\\\n*Transcription:* {}",
+                escape_markdown(transcription)
+            );
+        }
+
+        Ok(out_s)
+    }
+
+    fn format_sticker(&self, sticker: &'a mut Attachment, message: &Message) -> String {
+        let who = escape_markdown(self.config.who(
+            message.handle_id,
+            message.is_from_me(),
+            &message.destination_caller_id,
+        ));
+        match self.format_attachment(sticker, message, &AttachmentMeta::default()) {
+            Ok(embedded_sticker) => {
+                let mut description = format!("Sticker from {who}");
+
+                // Determine the source of the sticker
+                if let Some(sticker_source) = sticker.get_sticker_source(self.config.db()) {
+                    match sticker_source {
+                        StickerSource::Genmoji => {
+                            // Add sticker prompt
+                            if let Some(prompt) = &sticker.emoji_description {
+                                let _ = write!(
+                                    description,
+                                    " (Genmoji prompt: {})",
+                                    escape_markdown(prompt)
+                                );
+                            }
+                        }
+                        StickerSource::Memoji => description.push_str(" (App: Memoji)"),
+                        StickerSource::UserGenerated => {
+                            // Add sticker effect
+                            if let Ok(Some(sticker_effect)) = sticker.get_sticker_effect(
+                                &self.config.options.platform,
+                                &self.config.options.db_path,
+                                self.config.options.attachment_root.as_deref(),
+                            ) {
+                                description = format!("{sticker_effect} {description}");
+                            }
+                        }
+                        StickerSource::App(bundle_id) => {
+                            // Add the application name used to generate/send the sticker
+                            let app_name = sticker
+                                .get_sticker_source_application_name(self.config.db())
+                                .unwrap_or(bundle_id);
+                            let _ = write!(description, " (App: {})", escape_markdown(&app_name));
+                        }
+                    }
+                }
+
+                format!("{embedded_sticker}\\\n*{description}*")
+            }
+            Err(path) => format!("*Sticker from {who}: {}*", escape_markdown(path)),
+        }
+    }
+
+    fn format_app(
+        &self,
+        message: &'a Message,
+        attachments: &mut Vec<Attachment>,
+        indent: &str,
+    ) -> Result<String, PlistParseError> {
+        if let Variant::App(balloon) = message.variant() {
+            let mut app_bubble = String::new();
+
+            // Handwritten messages use a different payload type, so check that first
+            if message.is_handwriting()
+                && let Some(payload) = message.raw_payload_data(self.config.db())
+            {
+                return match HandwrittenMessage::from_payload(&payload) {
+                    Ok(bubble) => Ok(self.format_handwriting(message, &bubble, indent)),
+                    Err(why) => Err(PlistParseError::HandwritingError(why)),
+                };
+            }
+
+            if message.is_digital_touch()
+                && let Some(payload) = message.raw_payload_data(self.config.db())
+            {
+                return match digital_touch::from_payload(&payload) {
+                    Some(bubble) => Ok(self.format_digital_touch(message, &bubble, indent)),
+                    None => Err(PlistParseError::DigitalTouchError),
+                };
+            }
+
+            if let Some(payload) = message.payload_data(self.config.db()) {
+                // Handle URL messages separately since they are a special case
+                let parsed = parse_ns_keyed_archiver(&payload)?;
+                let res = if message.is_url() {
+                    let bubble = URLMessage::get_url_message_override(&parsed)?;
+                    match bubble {
+                        URLOverride::Normal(balloon) => self.format_url(message, &balloon, indent),
+                        URLOverride::AppleMusic(balloon) => self.format_music(&balloon, indent),
+                        URLOverride::Collaboration(balloon) => {
+                            self.format_collaboration(&balloon, indent)
+                        }
+                        URLOverride::AppStore(balloon) => self.format_app_store(&balloon, indent),
+                        URLOverride::SharedPlacemark(balloon) => {
+                            self.format_placemark(&balloon, indent)
+                        }
+                    }
+                } else {
+                    // Handle the app case
+                    match AppMessage::from_map(&parsed) {
+                        Ok(bubble) => match balloon {
+                            CustomBalloon::Application(bundle_id) => {
+                                self.format_generic_app(&bubble, bundle_id, attachments, indent)
+                            }
+                            CustomBalloon::ApplePay => self.format_apple_pay(&bubble, indent),
+                            CustomBalloon::Fitness => self.format_fitness(&bubble, indent),
+                            CustomBalloon::Slideshow => self.format_slideshow(&bubble, indent),
+                            CustomBalloon::CheckIn => self.format_check_in(&bubble, indent),
+                            CustomBalloon::FindMy => self.format_find_my(&bubble, indent),
+                            CustomBalloon::Handwriting
+                            | CustomBalloon::DigitalTouch
+                            | CustomBalloon::URL => {
+                                unreachable!()
+                            }
+                        },
+                        Err(why) => return Err(why),
+                    }
+                };
+                app_bubble.push_str(&res);
+            } else {
+                // Sometimes, URL messages are missing their payloads
+                if message.is_url()
+                    && let Some(text) = &message.text
+                {
+                    return Ok(escape_markdown(text));
+                }
+                return Err(PlistParseError::NoPayload);
+            }
+            Ok(app_bubble)
+        } else {
+            Err(PlistParseError::WrongMessageType)
+        }
+    }
+
+    fn format_tapback(&self, msg: &Message) -> Result<String, TableError> {
+        match msg.variant() {
+            Variant::Tapback(_, action, tapback) => {
+                if let TapbackAction::Removed = action {
+                    return Ok(String::new());
+                }
+
+                let who = escape_markdown(self.config.who(
+                    msg.handle_id,
+                    msg.is_from_me(),
+                    &msg.destination_caller_id,
+                ));
+                match tapback {
+                    Tapback::Sticker => {
+                        let mut paths = Attachment::from_message(self.config.db(), msg)?;
+                        // Sticker messages have only one attachment, the sticker image
+                        Ok(if let Some(sticker) = paths.get_mut(0) {
+                            self.format_sticker(sticker, msg)
+                        } else {
+                            format!("Sticker from {who} not found!")
+                        })
+                    }
+                    _ => Ok(format!(
+                        "{} by {who}",
+                        escape_markdown(&tapback.to_string())
+                    )),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn format_expressive(&self, msg: &'a Message) -> &'a str {
+        match msg.get_expressive() {
+            Expressive::Screen(effect) => match effect {
+                ScreenEffect::Confetti => "Sent with Confetti",
+                ScreenEffect::Echo => "Sent with Echo",
+                ScreenEffect::Fireworks => "Sent with Fireworks",
+                ScreenEffect::Balloons => "Sent with Balloons",
+                ScreenEffect::Heart => "Sent with Heart",
+                ScreenEffect::Lasers => "Sent with Lasers",
+                ScreenEffect::ShootingStar => "Sent with Shooting Star",
+                ScreenEffect::Sparkles => "Sent with Sparkles",
+                ScreenEffect::Spotlight => "Sent with Spotlight",
+            },
+            Expressive::Bubble(effect) => match effect {
+                BubbleEffect::Slam => "Sent with Slam",
+                BubbleEffect::Loud => "Sent with Loud",
+                BubbleEffect::Gentle => "Sent with Gentle",
+                BubbleEffect::InvisibleInk => "Sent with Invisible Ink",
+            },
+            Expressive::Unknown(effect) => effect,
+            Expressive::None => "",
+        }
+    }
+
+    fn format_announcement(&self, msg: &'a Message) -> String {
+        let mut who = self
+            .config
+            .who(msg.handle_id, msg.is_from_me(), &msg.destination_caller_id);
+        // Rename yourself so we render the proper grammar here
+        if who == ME {
+            who = self.config.options.custom_name.as_deref().unwrap_or(YOU);
+        }
+        let who = escape_markdown(who);
+
+        let timestamp = format(&msg.date(&self.config.offset));
+
+        match msg.get_announcement() {
+            Some(announcement) => {
+                let action_text = match announcement {
+                    Announcement::GroupAction(action) => match action {
+                        GroupAction::ParticipantAdded(person) => format!(
+                            "added {} to the conversation.",
+                            escape_markdown(self.config.who(
+                                Some(person),
+                                false,
+                                &msg.destination_caller_id
+                            ))
+                        ),
+                        GroupAction::ParticipantRemoved(person) => format!(
+                            "removed {} from the conversation.",
+                            escape_markdown(self.config.who(
+                                Some(person),
+                                false,
+                                &msg.destination_caller_id
+                            ))
+                        ),
+                        GroupAction::NameChange(name) => {
+                            format!("renamed the conversation to {}", escape_markdown(name))
+                        }
+                        GroupAction::ParticipantLeft => "left the conversation.".to_string(),
+                        GroupAction::GroupIconChanged => "changed the group photo.".to_string(),
+                        GroupAction::GroupIconRemoved => "removed the group photo.".to_string(),
+                    },
+                    Announcement::AudioMessageKept => "kept an audio message.".to_string(),
+                    Announcement::FullyUnsent => "unsent a message!".to_string(),
+                    Announcement::Unknown(num) => format!("performed unknown action {num}"),
+                };
+                format!("*{timestamp} {who} {action_text}*\n\n")
+            }
+            None => String::from("*Unable to format announcement!*\n\n"),
+        }
+    }
+
+    fn format_shareplay(&self) -> &'static str {
+        "SharePlay Message Ended"
+    }
+
+    fn format_shared_location(&self, msg: &'a Message) -> &'static str {
+        // Handle Shared Location
+        if msg.started_sharing_location() {
+            return "Started sharing location!";
+        } else if msg.stopped_sharing_location() {
+            return "Stopped sharing location!";
+        }
+        "Shared location!"
+    }
+
+    fn format_edited(
+        &self,
+        msg: &'a Message,
+        edited_message: &'a EditedMessage,
+        message_part_idx: usize,
+        _: &str,
+    ) -> Option<String> {
+        if let Some(edited_message_part) = edited_message.part(message_part_idx) {
+            let mut lines: Vec<String> = vec![];
+            let mut previous_timestamp: Option<&i64> = None;
+
+            match edited_message_part.status {
+                EditStatus::Edited => {
+                    let last = edited_message_part.edit_history.len().saturating_sub(1);
+                    for (idx, event) in edited_message_part.edit_history.iter().enumerate() {
+                        let label = match previous_timestamp {
+                            // Original message get an absolute timestamp
+                            None => Some(format(&get_local_time(&event.date, &self.config.offset))),
+                            // Subsequent edits get a relative timestamp
+                            Some(prev_timestamp) => {
+                                let end = get_local_time(&event.date, &self.config.offset);
+                                let start = get_local_time(prev_timestamp, &self.config.offset);
+                                readable_diff(start, end).map(|diff| format!("Edited {diff} later"))
+                            }
+                        };
+
+                        // Update the previous timestamp for the next loop
+                        previous_timestamp = Some(&event.date);
+
+                        // Superseded versions of the message text are struck through
+                        let text = event.text.as_deref().map(escape_markdown);
+                        let text = match text {
+                            Some(text) if idx < last => wrap(&text, "~~", "~~"),
+                            Some(text) => text,
+                            None => String::new(),
+                        };
+
+                        lines.push(match label {
+                            Some(label) => format!("*{label}:* {text}"),
+                            None => text,
+                        });
+                    }
+                }
+                EditStatus::Unsent => {
+                    let who = if msg.is_from_me() {
+                        self.config.options.custom_name.as_deref().unwrap_or(YOU)
+                    } else {
+                        "They"
+                    };
+                    let who = escape_markdown(who);
+
+                    if let Some(diff) = readable_diff(
+                        msg.date(&self.config.offset),
+                        msg.date_edited(&self.config.offset),
+                    ) {
+                        lines.push(format!(
+                            "*{who} unsent this message part {diff} after sending!*"
+                        ));
+                    } else {
+                        lines.push(format!("*{who} unsent this message part!*"));
+                    }
+                }
+                EditStatus::Original => {
+                    return None;
+                }
+            }
+
+            return Some(hard_breaks(&lines.join("\n")));
+        }
+        None
+    }
+
+    fn format_attributes(&'a self, text: &'a str, attributes: &'a [TextAttributes]) -> String {
+        let mut formatted_text = String::with_capacity(text.len());
+        let mut prev_start = 0;
+        let mut prev_end = 0;
+
+        for attribute in attributes {
+            if prev_start == attribute.start && prev_end == attribute.end {
+                continue;
+            } else if let Some(message_content) = text.get(attribute.start..attribute.end) {
+                prev_start = attribute.start;
+                prev_end = attribute.end;
+
+                // Escape the range before wrapping it in the syntax for its effects
+                let line_start = text[..attribute.start].ends_with('\n') || attribute.start == 0;
+                let escaped = escape_markdown_from(message_content, line_start);
+                formatted_text.push_str(&self.apply_effects(&escaped, &attribute.effects));
+            }
+        }
+        formatted_text
+    }
+}
+
+// MARK: Text Effects
+impl<'a> TextEffectFormatter<'a> for Markdown<'a> {
+    fn format_effect(&'a self, text: &'a str, effect: &'a TextEffect) -> Cow<'a, str> {
+        match effect {
+            TextEffect::Default => Cow::Borrowed(text),
+            TextEffect::Mention(mentioned) => Cow::Owned(self.format_mention(text, mentioned)),
+            TextEffect::Link(url) => Cow::Owned(self.format_link(text, url)),
+            TextEffect::OTP => Cow::Owned(self.format_otp(text)),
+            TextEffect::Styles(styles) => Cow::Owned(self.format_styles(text, styles)),
+            TextEffect::Animated(animation) => Cow::Owned(self.format_animated(text, animation)),
+            TextEffect::Conversion(unit) => Cow::Owned(self.format_conversion(text, unit)),
+        }
+    }
+
+    fn format_mention(&self, text: &str, _: &str) -> String {
+        wrap(text, "**", "**")
+    }
+
+    fn format_link(&self, text: &str, url: &str) -> String {
+        format!("[{text}]({})", link_destination(url))
+    }
+
+    fn format_otp(&self, text: &str) -> String {
+        wrap(text, "**", "**")
+    }
+
+    fn format_conversion(&self, text: &str, _: &Unit) -> String {
+        // Markdown has no syntax for unit conversions
+        text.to_string()
+    }
+
+    fn format_styles(&self, text: &str, styles: &[Style]) -> String {
+        let (prefix, suffix): (String, String) = styles.iter().rev().fold(
+            (String::new(), String::new()),
+            |(mut prefix, mut suffix), style| {
+                let marker = match style {
+                    Style::Bold => "**",
+                    Style::Italic => "*",
+                    Style::Strikethrough => "~~",
+                    // Markdown has no syntax for underlined text
+                    Style::Underline => "",
+                };
+                prefix.push_str(marker);
+                suffix.insert_str(0, marker);
+                (prefix, suffix)
+            },
+        );
+
+        wrap(text, &prefix, &suffix)
+    }
+
+    fn format_animated(&self, text: &str, _: &Animation) -> String {
+        // Markdown has no syntax for animated text
+        text.to_string()
+    }
+}
+
+// MARK: Balloon
+impl<'a> BalloonFormatter<&'a str> for Markdown<'a> {
+    fn format_url(&self, msg: &Message, balloon: &URLMessage, _: &str) -> String {
+        let mut lines = vec![];
+
+        if let Some(url) = balloon.get_url() {
+            let text = escape_markdown(balloon.title.unwrap_or(url));
+            lines.push(self.format_link(&text, url));
+        } else if let Some(text) = &msg.text {
+            lines.push(escape_markdown(text));
+        }
+
+        if let Some(summary) = balloon.summary {
+            lines.push(format!("*{}*", escape_markdown(summary)));
+        }
+
+        hard_breaks(&lines.join("\n"))
+    }
+
+    fn format_music(&self, balloon: &MusicMessage, _: &str) -> String {
+        let mut rows = vec![];
+
+        if let Some(track_name) = balloon.track_name {
+            rows.push(("Track", escape_markdown(track_name)));
+        }
+
+        if let Some(album) = balloon.album {
+            rows.push(("Album", escape_markdown(album)));
+        }
+
+        if let Some(artist) = balloon.artist {
+            rows.push(("Artist", escape_markdown(artist)));
+        }
+
+        if let Some(url) = balloon.url {
+            rows.push(("URL", self.format_table_link(url)));
+        }
+
+        if let Some(lyrics) = &balloon.lyrics {
+            rows.push((
+                "Lyrics",
+                lyrics
+                    .iter()
+                    .map(|line| escape_markdown(line))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ));
+        }
+
+        format_table("Apple Music", &rows)
+    }
+
+    fn format_collaboration(&self, balloon: &CollaborationMessage, _: &str) -> String {
+        let mut rows = vec![];
+
+        if let Some(title) = balloon.title {
+            rows.push(("Title", escape_markdown(title)));
+        }
+
+        if let Some(url) = balloon.get_url() {
+            rows.push(("URL", self.format_table_link(url)));
+        }
+
+        let app = balloon
+            .app_name
+            .or(balloon.bundle_id)
+            .unwrap_or("Collaboration");
+        format_table(&escape_markdown(app), &rows)
+    }
+
+    fn format_app_store(&self, balloon: &AppStoreMessage, _: &'a str) -> String {
+        let mut rows = vec![];
+
+        if let Some(name) = balloon.app_name {
+            rows.push(("Name", escape_markdown(name)));
+        }
+
+        if let Some(description) = balloon.description {
+            rows.push(("Description", escape_markdown(description)));
+        }
+
+        if let Some(platform) = balloon.platform {
+            rows.push(("Platform", escape_markdown(platform)));
+        }
+
+        if let Some(genre) = balloon.genre {
+            rows.push(("Genre", escape_markdown(genre)));
+        }
+
+        if let Some(url) = balloon.url {
+            rows.push(("URL", self.format_table_link(url)));
+        }
+
+        format_table("App Store", &rows)
+    }
+
+    fn format_placemark(&self, balloon: &PlacemarkMessage, _: &'a str) -> String {
+        let mut rows = vec![];
+
+        if let Some(name) = balloon.place_name {
+            rows.push(("Place", escape_markdown(name)));
+        }
+
+        if let Some(url) = balloon.get_url() {
+            rows.push(("URL", self.format_table_link(url)));
+        }
+
+        let placemark = &balloon.placemark;
+        for (label, value) in [
+            ("Name", placemark.name),
+            ("Address", placemark.address),
+            ("State", placemark.state),
+            ("City", placemark.city),
+            ("Country code", placemark.iso_country_code),
+            ("Postal code", placemark.postal_code),
+            ("Country", placemark.country),
+            ("Street", placemark.street),
+            ("Area", placemark.sub_administrative_area),
+            ("Locality", placemark.sub_locality),
+        ] {
+            if let Some(value) = value {
+                rows.push((label, escape_markdown(value)));
+            }
+        }
+
+        format_table("Location", &rows)
+    }
+
+    fn format_handwriting(&self, msg: &Message, balloon: &HandwrittenMessage, _: &str) -> String {
+        let ascii = || format!("```\n{}\n```", balloon.render_ascii(40));
+        match self.config.options.attachment_manager.mode {
+            AttachmentManagerMode::Disabled => ascii(),
+            _ => self
+                .config
+                .options
+                .attachment_manager
+                .handle_handwriting(msg, balloon, self.config)
+                .map(|filepath| {
+                    self.config
+                        .relative_path(PathBuf::from(&filepath))
+                        .unwrap_or(filepath.display().to_string())
+                })
+                .map(|filepath| format!("![Handwritten message]({})", link_destination(&filepath)))
+                .unwrap_or_else(ascii),
+        }
+    }
+
+    fn format_digital_touch(&self, _: &Message, balloon: &DigitalTouch, _: &str) -> String {
+        let label = match balloon {
+            DigitalTouch::Tap => "Tap",
+            DigitalTouch::Heartbeat => "Heartbeat",
+            DigitalTouch::Sketch => "Sketch",
+            DigitalTouch::Kiss => "Kiss",
+            DigitalTouch::Fireball => "Fireball",
+            DigitalTouch::Unknown => "Unknown effect",
+        };
+        format!("*Digital Touch Message: {label}*")
+    }
+
+    fn format_apple_pay(&self, balloon: &AppMessage, _: &str) -> String {
+        let mut rows = vec![];
+
+        if let Some(caption) = balloon.caption {
+            rows.push(("Transaction", escape_markdown(caption)));
+        }
+
+        rows.push((
+            "Amount",
+            escape_markdown(balloon.ldtext.unwrap_or("unknown amount")),
+        ));
+
+        format_table("Apple Pay", &rows)
+    }
+
+    fn format_fitness(&self, balloon: &AppMessage, _: &str) -> String {
+        let rows = vec![(
+            "Workout",
+            escape_markdown(balloon.ldtext.unwrap_or("unknown workout")),
+        )];
+
+        format_table(
+            &escape_markdown(balloon.app_name.unwrap_or("Fitness")),
+            &rows,
+        )
+    }
+
+    fn format_slideshow(&self, balloon: &AppMessage, _: &str) -> String {
+        let mut rows = vec![];
+
+        if let Some(ldtext) = balloon.ldtext {
+            rows.push(("Album", escape_markdown(ldtext)));
+        }
+
+        if let Some(url) = balloon.url {
+            rows.push(("URL", self.format_table_link(url)));
+        }
+
+        format_table("Photo album", &rows)
+    }
+
+    fn format_find_my(&self, balloon: &AppMessage, _: &'a str) -> String {
+        let mut rows = vec![];
+
+        if let Some(ldtext) = balloon.ldtext {
+            rows.push(("Status", escape_markdown(ldtext)));
+        }
+
+        format_table(
+            &escape_markdown(balloon.app_name.unwrap_or("Find My")),
+            &rows,
+        )
+    }
+
+    fn format_check_in(&self, balloon: &AppMessage, _: &'a str) -> String {
+        let mut rows = vec![];
+
+        let metadata: HashMap<&str, &str> = balloon.parse_query_string();
+
+        // Parse a date from the message's query string
+        let parse_date = |date_str: &str| {
+            let date_stamp = date_str.parse::<f64>().unwrap_or(0.) as i64 * TIMESTAMP_FACTOR;
+            format(&get_local_time(&date_stamp, &0))
+        };
+
+        // Before manual check-in
+        if let Some(date_str) = metadata.get("estimatedEndTime") {
+            rows.push(("Expected at", parse_date(date_str)));
+        }
+        // Expired check-in
+        else if let Some(date_str) = metadata.get("triggerTime") {
+            rows.push(("Was expected at", parse_date(date_str)));
+        }
+        // Accepted check-in
+        else if let Some(date_str) = metadata.get("sendDate") {
+            rows.push(("Checked in at", parse_date(date_str)));
+        }
+
+        format_table(
+            &escape_markdown(balloon.caption.unwrap_or("Check In")),
+            &rows,
+        )
+    }
+
+    fn format_generic_app(
+        &self,
+        balloon: &AppMessage,
+        bundle_id: &str,
+        _: &mut Vec<Attachment>,
+        _: &str,
+    ) -> String {
+        let mut rows = vec![];
+
+        for (label, value) in [
+            ("Title", balloon.title),
+            ("Subtitle", balloon.subtitle),
+            ("Caption", balloon.caption),
+            ("Subcaption", balloon.subcaption),
+            ("Trailing caption", balloon.trailing_caption),
+            ("Trailing subcaption", balloon.trailing_subcaption),
+        ] {
+            if let Some(value) = value {
+                rows.push((label, escape_markdown(value)));
+            }
+        }
+
+        format_table(
+            &escape_markdown(balloon.app_name.unwrap_or(bundle_id)),
+            &rows,
+        )
+    }
+}
+
+// MARK: Impl
+impl Markdown<'_> {
+    fn get_time(&self, message: &Message) -> String {
+        let mut date = format(&message.date(&self.config.offset));
+        let read_after = message.time_until_read(&self.config.offset);
+        if let Some(time) = read_after
+            && !time.is_empty()
+        {
+            let who = if message.is_from_me() {
+                "them"
+            } else {
+                self.config.options.custom_name.as_deref().unwrap_or("you")
+            };
+            let _ = write!(date, " (Read by {} after {time})", escape_markdown(who));
+        }
+        date
+    }
+
+    /// Add a block of content to a message, separated from the next block by a blank line
+    fn add_block(&self, string: &mut String, block: &str) {
+        if !block.is_empty() {
+            string.push_str(block);
+            string.push_str("\n\n");
+        }
+    }
+
+    /// Apply each effect of a range of already escaped text, in order
+    fn apply_effects<'b>(&'b self, text: &'b str, effects: &'b [TextEffect]) -> Cow<'b, str> {
+        let mut result = Cow::Borrowed(text);
+        for effect in effects {
+            // `Default` does not modify the text, so we can keep borrowing it
+            if !matches!(effect, TextEffect::Default) {
+                let owned_text = result.into_owned();
+                result = Cow::Owned(self.format_effect(&owned_text, effect).into_owned());
+            }
+        }
+        result
+    }
+
+    /// Render a URL inside of a table cell, where it is the link text as well as the target
+    fn format_table_link(&self, url: &str) -> String {
+        self.format_link(&escape_markdown(url), url)
+    }
+
+    /// Create a file and write its title, unless the file already exists
+    ///
+    /// This can happen if multiple chats use the same group name
+    fn create_file(
+        config: &Config,
+        path: &Path,
+        chatroom: Option<&Chat>,
+    ) -> Result<BufWriter<File>, RuntimeError> {
+        let file_exists = path.exists();
+        let file = File::options().append(true).create(true).open(path)?;
+        let mut buf = BufWriter::new(file);
+
+        if !file_exists {
+            Markdown::write_to_file(&mut buf, &Markdown::format_header(config, chatroom))?;
+        }
+
+        Ok(buf)
+    }
+
+    /// Build the title of the file a conversation is written to
+    fn format_header(config: &Config, chatroom: Option<&Chat>) -> String {
+        let title = match chatroom {
+            Some(chatroom) => match config.chat_title(chatroom) {
+                Some(name) => name.to_string(),
+                None => match config.chatroom_participants.get(&chatroom.rowid) {
+                    Some(participants) => participants
+                        .iter()
+                        .map(|participant| config.who(Some(*participant), false, &None))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    None => chatroom.chat_identifier.clone(),
+                },
+            },
+            None => "Orphaned messages".to_string(),
+        };
+
+        format!("# {}\n\n", escape_markdown(&title))
+    }
+}
+
+// MARK: Markdown
+/// Escape characters that Markdown would otherwise interpret as syntax
+fn escape_markdown(text: &str) -> String {
+    escape_markdown_from(text, true)
+}
+
+/// Escape characters that Markdown would otherwise interpret as syntax, where `line_start`
+/// indicates whether the text begins at the start of a line
+///
+/// Block syntax, like lists and headings, is only escaped at the start of a line so that
+/// ordinary punctuation inside of a sentence is left alone.
+fn escape_markdown_from(text: &str, line_start: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut at_line_start = line_start;
+    // Whether only digits have been seen since the start of the line, i.e. an ordered list
+    let mut in_list_number = false;
+
+    for char in text.chars() {
+        match char {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' => {
+                escaped.push('\\');
+            }
+            '-' | '+' | '=' if at_line_start => escaped.push('\\'),
+            '.' | ')' if in_list_number => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(char);
+
+        in_list_number = char.is_ascii_digit() && (at_line_start || in_list_number);
+        at_line_start = char == '\n' || (at_line_start && char == ' ');
+    }
+    escaped
+}
+
+/// Render each newline as a hard line break instead of letting Markdown join the lines
+fn hard_breaks(text: &str) -> String {
+    text.trim_end_matches('\n').replace('\n', "\\\n")
+}
+
+/// Wrap text in emphasis markers, keeping surrounding whitespace outside of them since
+/// Markdown ignores markers that are adjacent to whitespace
+fn wrap(text: &str, open: &str, close: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() || open.is_empty() {
+        return text.to_string();
+    }
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{leading}{open}{trimmed}{close}{trailing}")
+}
+
+/// Prefix every line with a blockquote marker
+fn blockquote(text: &str) -> String {
+    text.trim_end()
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Encode the characters that would end a link destination early
+fn link_destination(path: &str) -> String {
+    path.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// Render a two-column table, with the first column naming each value
+fn format_table(title: &str, rows: &[(&str, String)]) -> String {
+    let mut table = format!("| {title} | |\n| --- | --- |");
+    for (label, value) in rows {
+        let _ = write!(table, "\n| {label} | {} |", value.replace('\n', "<br>"));
+    }
+    table
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        Config, Exporter, Markdown, Options,
+        app::export_type::ExportType,
+        exporters::{
+            exporter::MessageFormatter,
+            markdown::{blockquote, escape_markdown},
+        },
+    };
+    use imessage_database::tables::{chat::Chat, messages::models::AttachmentMeta, table::ME};
+
+    #[test]
+    fn can_create() {
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+        assert_eq!(exporter.files.len(), 0);
+    }
+
+    #[test]
+    fn can_format_markdown_from_me_normal() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+        message.is_from_me = true;
+        message.chat_id = Some(0);
+        message.generate_text_legacy(config.db()).unwrap();
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+        let expected = "**Me** · May 17, 2022  5:29:42 PM\n\nHello world\n\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_from_them_normal_read() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "Sample Contact".to_string());
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.handle_id = Some(999999);
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+        // May 17, 2022  8:29:42 PM
+        message.date_delivered = 674526582885055488;
+        // May 17, 2022  9:30:31 PM
+        message.date_read = 674530231992568192;
+        message.generate_text_legacy(config.db()).unwrap();
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+        let expected = "**Sample Contact** · May 17, 2022  5:29:42 PM (Read by you after 1 hour, 49 seconds)\n\nHello world\n\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_from_me_deleted() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+        message.is_from_me = true;
+        message.deleted_from = Some(0);
+        message.generate_text_legacy(config.db()).unwrap();
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+        let expected = "**Me** · May 17, 2022  5:29:42 PM\n\n*This message was deleted from the conversation!*\n\nHello world\n\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_escaped_multiline() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("# Not a *heading*\n- Not a list".to_string());
+        message.is_from_me = true;
+        message.chat_id = Some(0);
+        message.generate_text_legacy(config.db()).unwrap();
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+        let expected =
+            "**Me** · May 17, 2022  5:29:42 PM\n\n\\# Not a \\*heading\\*\\\n\\- Not a list\n\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_announcement() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(0, ME.to_string());
+
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.group_title = Some("Hello_world".to_string());
+        message.is_from_me = true;
+        message.item_type = 2;
+
+        let actual = exporter.format_announcement(&message);
+        let expected =
+            "*May 17, 2022  5:29:42 PM You renamed the conversation to Hello\\_world*\n\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_tapback_me() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(0, ME.to_string());
+
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.associated_message_type = Some(2000);
+        message.associated_message_guid = Some("fake_guid".to_string());
+
+        let actual = exporter.format_tapback(&message).unwrap();
+        let expected = "Loved by Me";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_tapback_removed() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.associated_message_type = Some(3000);
+        message.associated_message_guid = Some("fake_guid".to_string());
+
+        let actual = exporter.format_tapback(&message).unwrap();
+
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn can_format_markdown_attachment_image() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let mut attachment = Config::fake_attachment();
+
+        let actual = exporter
+            .format_attachment(&mut attachment, &message, &AttachmentMeta::default())
+            .unwrap();
+
+        assert_eq!(actual, "![d.jpg](a/b/c/d.jpg)");
+    }
+
+    #[test]
+    fn can_format_markdown_attachment_link() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = Some("a/b/c/my (1).pdf".to_string());
+        attachment.transfer_name = Some("my (1).pdf".to_string());
+        attachment.mime_type = Some("application/pdf".to_string());
+
+        let actual = exporter
+            .format_attachment(&mut attachment, &message, &AttachmentMeta::default())
+            .unwrap();
+
+        assert_eq!(actual, "[my (1).pdf](a/b/c/my%20%281%29.pdf)");
+    }
+
+    #[test]
+    fn can_format_markdown_attachment_transcription() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = Some("a/b/c/Audio Message.caf".to_string());
+        attachment.transfer_name = Some("Audio Message.caf".to_string());
+        attachment.mime_type = Some("audio/x-caf".to_string());
+
+        let metadata = AttachmentMeta {
+            transcription: Some("Hello world".to_string()),
+            ..Default::default()
+        };
+
+        let actual = exporter
+            .format_attachment(&mut attachment, &message, &metadata)
+            .unwrap();
+
+        assert_eq!(
+            actual,
+            "[Audio Message.caf](a/b/c/Audio%20Message.caf)\\\n*Transcription:* Hello world"
+        );
+    }
+
+    #[test]
+    fn can_format_markdown_attachment_invalid() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let message = Config::fake_message();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = None;
+        attachment.transfer_name = None;
+
+        let actual =
+            exporter.format_attachment(&mut attachment, &message, &AttachmentMeta::default());
+
+        assert_eq!(actual, Err("Attachment missing name metadata!"));
+    }
+
+    #[test]
+    fn can_format_header() {
+        let options = Options::fake_options(ExportType::Markdown);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(2, "Sample Contact".to_string());
+        config
+            .chatroom_participants
+            .insert(1, BTreeSet::from([2, 3]));
+
+        let mut chat = Chat {
+            rowid: 1,
+            chat_identifier: "+15558675309".to_string(),
+            service_name: Some("iMessage".to_string()),
+            display_name: Some("Friends [2024]".to_string()),
+        };
+
+        assert_eq!(
+            Markdown::format_header(&config, Some(&chat)),
+            "# Friends \\[2024\\]\n\n"
+        );
+
+        chat.display_name = None;
+        assert_eq!(
+            Markdown::format_header(&config, Some(&chat)),
+            "# Sample Contact, Unknown\n\n"
+        );
+
+        assert_eq!(
+            Markdown::format_header(&config, None),
+            "# Orphaned messages\n\n"
+        );
+    }
+
+    #[test]
+    fn can_escape_block_syntax_at_line_start() {
+        assert_eq!(
+            escape_markdown("1. one\n+ two\n> three"),
+            "1\\. one\n\\+ two\n\\> three"
+        );
+        assert_eq!(escape_markdown("a - b, 2. c"), "a - b, 2. c");
+    }
+
+    #[test]
+    fn can_blockquote() {
+        assert_eq!(blockquote("Hello\n\nworld\n\n"), "> Hello\n>\n> world");
+    }
+}
+
+#[cfg(test)]
+mod balloon_format_tests {
+    use crate::{
+        Config, Exporter, Markdown, Options, app::export_type::ExportType,
+        exporters::exporter::BalloonFormatter,
+    };
+    use imessage_database::message_types::{
+        app::AppMessage,
+        digital_touch::DigitalTouch,
+        music::MusicMessage,
+        placemark::{Placemark, PlacemarkMessage},
+        url::URLMessage,
+    };
+
+    #[test]
+    fn can_format_markdown_url() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let balloon = URLMessage {
+            title: Some("title"),
+            summary: Some("summary"),
+            url: Some("https://example.com"),
+            original_url: Some("original_url"),
+            item_type: Some("item_type"),
+            images: vec!["images"],
+            icons: vec!["icons"],
+            site_name: Some("site_name"),
+            placeholder: false,
+        };
+
+        let actual = exporter.format_url(&Config::fake_message(), &balloon, "");
+        let expected = "[title](https://example.com)\\\n*summary*";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_url_no_title() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let balloon = URLMessage {
+            title: None,
+            summary: None,
+            url: Some("https://example.com"),
+            original_url: None,
+            item_type: None,
+            images: vec![],
+            icons: vec![],
+            site_name: None,
+            placeholder: false,
+        };
+
+        let actual = exporter.format_url(&Config::fake_message(), &balloon, "");
+        let expected = "[https://example.com](https://example.com)";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_music() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let balloon = MusicMessage {
+            url: Some("url"),
+            preview: Some("preview"),
+            artist: Some("artist"),
+            album: Some("album"),
+            track_name: Some("track_name"),
+            lyrics: Some(vec!["a", "b"]),
+        };
+
+        let actual = exporter.format_music(&balloon, "");
+        let expected = "| Apple Music | |\n| --- | --- |\n| Track | track\\_name |\n| Album | album |\n| Artist | artist |\n| URL | [url](url) |\n| Lyrics | a<br>b |";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_placemark() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let balloon = PlacemarkMessage {
+            url: Some("url"),
+            original_url: None,
+            place_name: Some("Apple Park"),
+            placemark: Placemark {
+                city: Some("Cupertino"),
+                state: Some("CA"),
+                ..Default::default()
+            },
+        };
+
+        let actual = exporter.format_placemark(&balloon, "");
+        let expected = "| Location | |\n| --- | --- |\n| Place | Apple Park |\n| URL | [url](url) |\n| State | CA |\n| City | Cupertino |";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_apple_pay() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let balloon = AppMessage {
+            image: None,
+            url: None,
+            title: None,
+            subtitle: None,
+            caption: Some("Apple Cash"),
+            subcaption: None,
+            trailing_caption: None,
+            trailing_subcaption: None,
+            app_name: Some("Apple Pay"),
+            ldtext: Some("Sent $1 with Apple Pay."),
+        };
+
+        let actual = exporter.format_apple_pay(&balloon, "");
+        let expected = "| Apple Pay | |\n| --- | --- |\n| Transaction | Apple Cash |\n| Amount | Sent $1 with Apple Pay. |";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_generic_app() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let balloon = AppMessage {
+            image: None,
+            url: None,
+            title: Some("Either | or"),
+            subtitle: Some("subtitle"),
+            caption: None,
+            subcaption: None,
+            trailing_caption: None,
+            trailing_subcaption: None,
+            app_name: None,
+            ldtext: None,
+        };
+
+        let actual = exporter.format_generic_app(&balloon, "bundle_id", &mut vec![], "");
+        let expected =
+            "| bundle\\_id | |\n| --- | --- |\n| Title | Either \\| or |\n| Subtitle | subtitle |";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_digital_touch() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let actual = exporter.format_digital_touch(&Config::fake_message(), &DigitalTouch::Tap, "");
+        assert_eq!(actual, "*Digital Touch Message: Tap*");
+
+        let actual =
+            exporter.format_digital_touch(&Config::fake_message(), &DigitalTouch::Unknown, "");
+        assert_eq!(actual, "*Digital Touch Message: Unknown effect*");
+    }
+}
+
+#[cfg(test)]
+mod text_effect_tests {
+    use imessage_database::{
+        message_types::text_effects::{Animation, Style, TextEffect, Unit},
+        tables::messages::models::{BubbleComponent, TextAttributes},
+    };
+
+    use crate::{
+        Config, Exporter, Markdown, Options, app::export_type::ExportType,
+        exporters::exporter::MessageFormatter,
+    };
+
+    #[test]
+    fn can_format_markdown_text_styles() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let text = "Bold italic strike under both";
+        let attributes = vec![
+            TextAttributes::new(0, 5, vec![TextEffect::Styles(vec![Style::Bold])]),
+            TextAttributes::new(5, 12, vec![TextEffect::Styles(vec![Style::Italic])]),
+            TextAttributes::new(12, 19, vec![TextEffect::Styles(vec![Style::Strikethrough])]),
+            TextAttributes::new(19, 25, vec![TextEffect::Styles(vec![Style::Underline])]),
+            TextAttributes::new(
+                25,
+                29,
+                vec![TextEffect::Styles(vec![Style::Bold, Style::Italic])],
+            ),
+        ];
+
+        let actual = exporter.format_attributes(text, &attributes);
+        let expected = "**Bold** *italic* ~~strike~~ under ***both***";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_text_effects() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let text = "Hey Christopher, code 123456 is 5 mi on wiki";
+        let attributes = vec![
+            TextAttributes::new(0, 4, vec![TextEffect::Default]),
+            TextAttributes::new(4, 15, vec![TextEffect::Mention("+5558675309".to_string())]),
+            TextAttributes::new(15, 22, vec![TextEffect::Default]),
+            TextAttributes::new(22, 28, vec![TextEffect::OTP]),
+            TextAttributes::new(28, 32, vec![TextEffect::Animated(Animation::Big)]),
+            TextAttributes::new(32, 36, vec![TextEffect::Conversion(Unit::Distance)]),
+            TextAttributes::new(36, 40, vec![TextEffect::Default]),
+            TextAttributes::new(
+                40,
+                44,
+                vec![TextEffect::Link(
+                    "https://en.wikipedia.org/wiki/Rust_(programming_language)".to_string(),
+                )],
+            ),
+        ];
+
+        let actual = exporter.format_attributes(text, &attributes);
+        let expected = "Hey **Christopher**, code **123456** is 5 mi on [wiki](https://en.wikipedia.org/wiki/Rust_%28programming_language%29)";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_text_styles_escaped() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let text = "*star*";
+        let attributes = vec![TextAttributes::new(
+            0,
+            6,
+            vec![TextEffect::Styles(vec![Style::Bold])],
+        )];
+
+        let actual = exporter.format_attributes(text, &attributes);
+        let expected = "**\\*star\\***";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_text_styles_end_to_end() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Bold\nnormal".to_string());
+        message.is_from_me = true;
+        message.chat_id = Some(0);
+
+        message.components = vec![BubbleComponent::Text(vec![
+            TextAttributes::new(0, 5, vec![TextEffect::Styles(vec![Style::Bold])]),
+            TextAttributes::new(5, 11, vec![TextEffect::Default]),
+        ])];
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+        let expected = "**Me** · May 17, 2022  5:29:42 PM\n\n**Bold**\\\nnormal\n\n";
+
+        assert_eq!(actual, expected);
+    }
+}
+
+#[cfg(test)]
+mod edited_tests {
+    use imessage_database::{
+        message_types::{
+            edited::{EditStatus, EditedEvent, EditedMessage, EditedMessagePart},
+            text_effects::TextEffect,
+        },
+        tables::messages::models::{BubbleComponent, TextAttributes},
+    };
+
+    use crate::{
+        Config, Exporter, Markdown, Options, app::export_type::ExportType,
+        exporters::exporter::MessageFormatter,
+    };
+
+    #[test]
+    fn can_format_markdown_edited() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+        message.is_from_me = true;
+        message.chat_id = Some(0);
+        message.components = vec![BubbleComponent::Text(vec![TextAttributes::new(
+            0,
+            11,
+            vec![TextEffect::Default],
+        )])];
+        message.edited_parts = Some(EditedMessage {
+            parts: vec![EditedMessagePart {
+                status: EditStatus::Edited,
+                edit_history: vec![
+                    EditedEvent {
+                        date: 674526582885055488,
+                        text: Some("Hello".to_string()),
+                        components: vec![],
+                        guid: None,
+                    },
+                    EditedEvent {
+                        date: 674526592885055488,
+                        text: Some("Hello world".to_string()),
+                        components: vec![],
+                        guid: None,
+                    },
+                ],
+            }],
+        });
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+        let expected = "**Me** · May 17, 2022  5:29:42 PM\n\n*May 17, 2022  5:29:42 PM:* ~~Hello~~\\\n*Edited 10 seconds later:* Hello world\n\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_markdown_unsent() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Markdown);
+        let config = Config::fake_app(options);
+        let exporter = Markdown::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.date_edited = 674530231992568192;
+        message.text = Some("Hello world".to_string());
+        message.is_from_me = true;
+        message.chat_id = Some(0);
+        message.components = vec![
+            BubbleComponent::Text(vec![TextAttributes::new(0, 11, vec![TextEffect::Default])]),
+            BubbleComponent::Retracted,
+        ];
+        message.edited_parts = Some(EditedMessage {
+            parts: vec![
+                EditedMessagePart {
+                    status: EditStatus::Original,
+                    edit_history: vec![],
+                },
+                EditedMessagePart {
+                    status: EditStatus::Unsent,
+                    edit_history: vec![],
+                },
+            ],
+        });
+
+        let actual = exporter.format_message(&message, 0).unwrap();
+        let expected = "**Me** · May 17, 2022  5:29:42 PM\n\nHello world\n\n*You unsent this message part 1 hour, 49 seconds after sending!*\n\n";
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,870 @@
+use std::{
+    borrow::Cow,
+    collections::{
+        HashMap,
+        hash_map::Entry::{Occupied, Vacant},
+    },
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+use crate::{
+    app::{
+        error::RuntimeError, export_type::ExportType, progress::ExportProgress, runtime::Config,
+    },
+    exporters::exporter::Exporter,
+};
+
+use imessage_database::{
+    error::table::TableError,
+    message_types::{
+        edited::EditStatus,
+        variants::{Announcement, TapbackAction, Variant},
+    },
+    tables::{
+        attachment::Attachment,
+        messages::{Message, models::GroupAction},
+        table::{ME, ORPHANED, Table, YOU},
+    },
+};
+
+// MARK: Mbox
+/// Domain for addresses and message IDs, reserved by RFC 2606 so it can never be delivered to
+const DOMAIN: &str = "imessage.invalid";
+
+/// Line length used when wrapping `base64` content, as recommended by RFC 2045
+const BASE64_LINE_LENGTH: usize = 76;
+
+/// Longest line allowed by RFC 5322, excluding the line ending
+const MAX_LINE_LENGTH: usize = 998;
+
+/// Number of bytes encoded in each RFC 2047 encoded word, which keeps every word under 75 characters
+const ENCODED_WORD_BYTES: usize = 45;
+
+/// Date format used by the separator line that starts each message in an mbox file
+const FROM_LINE_DATE_FORMAT: &str = "%a %b %e %H:%M:%S %Y";
+
+/// Separator date used when a message has no valid timestamp
+const EPOCH_FROM_LINE_DATE: &str = "Thu Jan  1 00:00:00 1970";
+
+pub struct Mbox<'a> {
+    /// Data that is setup from the application's runtime
+    pub config: &'a Config,
+    /// Handles to files we want to write messages to
+    /// Map of resolved chatroom file location to a buffered writer
+    pub files: HashMap<String, BufWriter<File>>,
+    /// Writer instance for orphaned messages, created when the first one is found
+    pub orphaned: Option<BufWriter<File>>,
+    /// Progress Bar model for alerting the user about current export state
+    pb: ExportProgress,
+}
+
+// MARK: Exporter
+impl<'a> Exporter<'a> for Mbox<'a> {
+    fn new(config: &'a Config) -> Result<Self, RuntimeError> {
+        Ok(Mbox {
+            config,
+            files: HashMap::new(),
+            orphaned: None,
+            pb: ExportProgress::new(),
+        })
+    }
+
+    fn iter_messages(&mut self) -> Result<(), RuntimeError> {
+        // Tell the user what we are doing
+        eprintln!(
+            "Exporting to {} as {}...",
+            self.config.options.export_path.display(),
+            ExportType::Mbox
+        );
+
+        // Keep track of current message ROWID
+        let mut current_message_row = -1;
+
+        // Set up progress bar
+        let mut current_message = 0;
+        let total_messages =
+            Message::get_count(self.config.db(), &self.config.options.query_context)?;
+        self.pb.start(total_messages);
+
+        let mut statement =
+            Message::stream_rows(self.config.db(), &self.config.options.query_context)?;
+
+        let messages = statement
+            .query_map([], |row| Ok(Message::from_row(row)))
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
+
+        for message in messages {
+            let mut msg = Message::extract(message)?;
+
+            // Early escape if we try and render the same message GUID twice
+            // See https://github.com/ReagentX/imessage-exporter/issues/135 for rationale
+            if msg.rowid == current_message_row {
+                current_message += 1;
+                continue;
+            }
+            current_message_row = msg.rowid;
+
+            // Generate the text of the message
+            let _ = msg.generate_text(self.config.db());
+
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
+            }
+
+            // Skip tapbacks as they're handled in context
+            if !msg.is_tapback() {
+                let email = self.format_message(&msg)?;
+                Mbox::write_to_file(self.get_or_create_file(&msg)?, &email)?;
+            }
+
+            current_message += 1;
+            if current_message % 99 == 0 {
+                self.pb.set_position(current_message);
+            }
+        }
+        self.pb.finish();
+
+        Ok(())
+    }
+
+    /// Create a file for the given chat, caching it so we don't need to build it later
+    fn get_or_create_file(
+        &mut self,
+        message: &Message,
+    ) -> Result<&mut BufWriter<File>, RuntimeError> {
+        match self.config.conversation(message) {
+            Some((chatroom, _)) => {
+                let filename = self.config.filename(chatroom);
+                match self.files.entry(filename) {
+                    Occupied(entry) => Ok(entry.into_mut()),
+                    Vacant(entry) => {
+                        let mut path = self.config.options.export_path.clone();
+                        path.push(self.config.filename(chatroom));
+                        path.set_extension(ExportType::Mbox.to_string());
+
+                        Ok(entry.insert(Mbox::create_file(&path)?))
+                    }
+                }
+            }
+            None => {
+                let buf = match self.orphaned.take() {
+                    Some(buf) => buf,
+                    None => {
+                        let mut path = self.config.options.export_path.clone();
+                        path.push(ORPHANED);
+                        path.set_extension(ExportType::Mbox.to_string());
+
+                        Mbox::create_file(&path)?
+                    }
+                };
+                Ok(self.orphaned.insert(buf))
+            }
+        }
+    }
+
+    fn write_to_file(file: &mut BufWriter<File>, text: &str) -> Result<(), RuntimeError> {
+        file.write_all(text.as_bytes())
+            .map_err(RuntimeError::DiskError)
+    }
+}
+
+impl Mbox<'_> {
+    /// Create an empty mailbox, replacing any existing file
+    fn create_file(path: &Path) -> Result<BufWriter<File>, RuntimeError> {
+        Ok(BufWriter::new(File::create(path)?))
+    }
+
+    /// Format a message as an email, including the separator line that starts it in the mailbox
+    fn format_message(&self, message: &Message) -> Result<String, TableError> {
+        let date = message.date(&self.config.offset).ok();
+        let sender = self.mailbox(
+            message.handle_id,
+            message.is_from_me(),
+            &message.destination_caller_id,
+        );
+
+        let mut email = format!(
+            "From {} {}\n",
+            self.sender_address(message),
+            date.map_or(EPOCH_FROM_LINE_DATE.to_string(), |date| date
+                .format(FROM_LINE_DATE_FORMAT)
+                .to_string())
+        );
+
+        // Headers
+        email.push_str(&format!("From: {sender}\n"));
+        let recipients = self.recipients(message);
+        if !recipients.is_empty() {
+            email.push_str(&format!("To: {}\n", recipients.join(",\n ")));
+        }
+        if let Some(date) = date {
+            email.push_str(&format!("Date: {}\n", date.to_rfc2822()));
+        }
+        email.push_str(&format!("Message-ID: {}\n", message_id(&message.guid)));
+        if let Some(thread) = &message.thread_originator_guid {
+            email.push_str(&format!("In-Reply-To: {}\n", message_id(thread)));
+            email.push_str(&format!("References: {}\n", message_id(thread)));
+        }
+        if let Some(subject) = message.subject.as_deref().filter(|s| !s.is_empty()) {
+            email.push_str(&format!("Subject: {}\n", encode_header(subject)));
+        }
+        email.push_str(&format!("X-iMessage-Service: {}\n", message.service()));
+        if let Some((chatroom, _)) = self.config.conversation(message) {
+            email.push_str(&format!(
+                "X-iMessage-Chat: {}\n",
+                encode_header(&chatroom.chat_identifier)
+            ));
+        }
+        if message.is_edited() {
+            email.push_str(&format!(
+                "X-iMessage-Edited: {}\n",
+                message
+                    .date_edited(&self.config.offset)
+                    .map(|date| date.to_rfc2822())
+                    .unwrap_or_default()
+            ));
+        }
+        email.push_str("MIME-Version: 1.0\n");
+
+        // Body
+        let mut body = self.format_body(message)?;
+        let mut parts = vec![];
+        if message.num_attachments > 0 {
+            match Attachment::from_message(self.config.db(), message) {
+                Ok(attachments) => {
+                    for attachment in attachments {
+                        match self.format_attachment(&attachment) {
+                            Some(part) => parts.push(part),
+                            None => body.push_str(&format!(
+                                "\nAttachment missing: {}\n",
+                                attachment.filename().unwrap_or("unknown")
+                            )),
+                        }
+                    }
+                }
+                // Keep the message if its attachment rows cannot be read
+                Err(why) => {
+                    eprintln!("Unable to read attachments for {}: {why}", message.guid);
+                    body.push_str("\nAttachment missing: unable to read attachment data\n");
+                }
+            }
+        }
+
+        let text = format_text_part(&body);
+        if parts.is_empty() {
+            email.push_str(&text);
+        } else {
+            let boundary = format!("=_{}", message.guid);
+            email.push_str(&format!(
+                "Content-Type: multipart/mixed; boundary=\"{boundary}\"\n\n"
+            ));
+            for part in std::iter::once(text).chain(parts) {
+                email.push_str(&format!("--{boundary}\n{part}\n"));
+            }
+            email.push_str(&format!("--{boundary}--\n"));
+        }
+
+        // Each message ends with a blank line before the next separator
+        email.push('\n');
+        Ok(email)
+    }
+
+    /// Build the plain text body of a message, including its tapbacks and edit notes
+    fn format_body(&self, message: &Message) -> Result<String, TableError> {
+        let mut body = String::new();
+
+        if message.is_announcement() {
+            body.push_str(&self.format_announcement(message));
+        } else if message.is_shareplay() {
+            body.push_str("SharePlay Message\nEnded");
+        } else if let Some(text) = &message.text {
+            // Attachments and app balloons are represented by placeholder characters
+            body.push_str(text.replace('\u{FFFC}', "").trim());
+        }
+
+        if body.is_empty()
+            && let Variant::App(_) = message.variant()
+        {
+            body.push_str(&format!(
+                "App message: {}",
+                message.balloon_bundle_id.as_deref().unwrap_or("unknown")
+            ));
+        }
+
+        if let Some(edited_parts) = &message.edited_parts {
+            for part in &edited_parts.parts {
+                match part.status {
+                    EditStatus::Unsent => {
+                        body.push_str("\n\nA part of this message was unsent.");
+                    }
+                    EditStatus::Edited => {
+                        for event in &part.edit_history {
+                            if let Some(text) = &event.text {
+                                body.push_str(&format!("\n\nEdited from: {text}"));
+                            }
+                        }
+                    }
+                    EditStatus::Original => {}
+                }
+            }
+        }
+
+        // Tapbacks are listed after the text, in part order
+        if let Some(tapbacks_map) = self.config.tapbacks.get(&message.guid) {
+            let mut parts: Vec<&usize> = tapbacks_map.keys().collect();
+            parts.sort_unstable();
+            let mut tapbacks = vec![];
+            for tapback in parts.iter().flat_map(|idx| &tapbacks_map[*idx]) {
+                if let Variant::Tapback(_, TapbackAction::Added, kind) = tapback.variant() {
+                    tapbacks.push(format!(
+                        "{kind} by {}",
+                        self.config.who(
+                            tapback.handle_id,
+                            tapback.is_from_me(),
+                            &tapback.destination_caller_id,
+                        )
+                    ));
+                }
+            }
+            if !tapbacks.is_empty() {
+                body.push_str(&format!("\n\nTapbacks:\n{}", tapbacks.join("\n")));
+            }
+        }
+
+        Ok(body)
+    }
+
+    /// Describe a change to the conversation itself
+    fn format_announcement(&self, message: &Message) -> String {
+        let mut who = self.config.who(
+            message.handle_id,
+            message.is_from_me(),
+            &message.destination_caller_id,
+        );
+        // Rename yourself so we render the proper grammar here
+        if who == ME {
+            who = self.config.options.custom_name.as_deref().unwrap_or(YOU);
+        }
+
+        let action = match message.get_announcement() {
+            Some(Announcement::GroupAction(action)) => match action {
+                GroupAction::ParticipantAdded(person) => format!(
+                    "added {} to the conversation.",
+                    self.config.who(Some(person), false, &None)
+                ),
+                GroupAction::ParticipantRemoved(person) => format!(
+                    "removed {} from the conversation.",
+                    self.config.who(Some(person), false, &None)
+                ),
+                GroupAction::NameChange(name) => format!("renamed the conversation to {name}"),
+                GroupAction::ParticipantLeft => "left the conversation.".to_string(),
+                GroupAction::GroupIconChanged => "changed the group photo.".to_string(),
+                GroupAction::GroupIconRemoved => "removed the group photo.".to_string(),
+            },
+            Some(Announcement::AudioMessageKept) => "kept an audio message.".to_string(),
+            Some(Announcement::FullyUnsent) => "unsent a message!".to_string(),
+            Some(Announcement::Unknown(num)) => format!("performed unknown action {num}"),
+            None => return "Unable to format announcement!".to_string(),
+        };
+        format!("{who} {action}")
+    }
+
+    /// Build a MIME part that contains the original bytes of an attachment
+    fn format_attachment(&self, attachment: &Attachment) -> Option<String> {
+        let bytes = attachment
+            .as_bytes(
+                &self.config.options.platform,
+                &self.config.options.db_path,
+                self.config.options.attachment_root.as_deref(),
+            )
+            .ok()??;
+
+        let name = quote_parameter(attachment.filename().unwrap_or("attachment"));
+        let mime_type = match attachment.mime_type().as_mime_type() {
+            mime if mime.is_empty() => "application/octet-stream".to_string(),
+            mime => mime,
+        };
+
+        Some(format!(
+            "Content-Type: {mime_type}; name={name}\nContent-Disposition: attachment; filename={name}\nContent-Transfer-Encoding: base64\n\n{}",
+            wrap_base64(&bytes)
+        ))
+    }
+
+    /// Get the bare address of the sender, used by the separator line
+    fn sender_address(&self, message: &Message) -> String {
+        if message.is_from_me() {
+            self.own_address(&message.destination_caller_id)
+        } else {
+            self.handle_address(message.handle_id)
+        }
+    }
+
+    /// Get the address of the database owner
+    fn own_address(&self, destination_caller_id: &Option<String>) -> String {
+        destination_caller_id
+            .as_deref()
+            .map_or(format!("me@{DOMAIN}"), email_address)
+    }
+
+    /// Get the address of a contact handle
+    fn handle_address(&self, handle_id: Option<i32>) -> String {
+        handle_id
+            .and_then(|handle_id| self.config.participants.get(&handle_id))
+            // Deduplicated contacts list every one of their handles
+            .and_then(|handles| handles.split_whitespace().next())
+            .map_or(format!("unknown@{DOMAIN}"), email_address)
+    }
+
+    /// Build a mailbox, i.e. `"Name" <address>`, for a sender or recipient
+    fn mailbox(
+        &self,
+        handle_id: Option<i32>,
+        is_from_me: bool,
+        destination_caller_id: &Option<String>,
+    ) -> String {
+        let name = self
+            .config
+            .who(handle_id, is_from_me, destination_caller_id);
+        let address = if is_from_me {
+            self.own_address(destination_caller_id)
+        } else {
+            self.handle_address(handle_id)
+        };
+        format!("{} <{address}>", display_name(name))
+    }
+
+    /// Everyone in the conversation other than the sender
+    fn recipients(&self, message: &Message) -> Vec<String> {
+        let mut recipients = vec![];
+
+        // Messages from other people are always sent to the database owner
+        if !message.is_from_me() {
+            recipients.push(self.mailbox(None, true, &message.destination_caller_id));
+        }
+
+        match self
+            .config
+            .conversation(message)
+            .and_then(|(chatroom, _)| self.config.chatroom_participants.get(&chatroom.rowid))
+        {
+            Some(participants) => recipients.extend(
+                participants
+                    .iter()
+                    .filter(|participant| {
+                        message.is_from_me() || Some(**participant) != message.handle_id
+                    })
+                    .map(|participant| self.mailbox(Some(*participant), false, &None)),
+            ),
+            // Without a chat, messages from the database owner are addressed to their handle
+            None if message.is_from_me() => {
+                recipients.push(self.mailbox(message.handle_id, false, &None));
+            }
+            None => {}
+        }
+
+        recipients
+    }
+}
+
+/// Build the `text/plain` MIME part for a message body
+///
+/// Bodies are sent as `8bit` text unless a line is too long for RFC 5322, in which case they are `base64` encoded.
+fn format_text_part(body: &str) -> String {
+    let content_type = "Content-Type: text/plain; charset=utf-8";
+    if body.lines().any(|line| line.len() > MAX_LINE_LENGTH) {
+        format!(
+            "{content_type}\nContent-Transfer-Encoding: base64\n\n{}",
+            wrap_base64(body.as_bytes())
+        )
+    } else {
+        format!(
+            "{content_type}\nContent-Transfer-Encoding: 8bit\n\n{}\n",
+            escape_from_lines(body)
+        )
+    }
+}
+
+/// Turn a handle into an email address, i.e. `+15558675309@imessage.invalid`
+fn email_address(handle: &str) -> String {
+    if handle.contains('@') {
+        return handle.to_string();
+    }
+    let local: String = handle
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '+')
+        .collect();
+    if local.is_empty() {
+        format!("unknown@{DOMAIN}")
+    } else {
+        format!("{local}@{DOMAIN}")
+    }
+}
+
+/// Build a globally unique message ID from a message GUID
+fn message_id(guid: &str) -> String {
+    format!("<{guid}@{DOMAIN}>")
+}
+
+/// Quote a display name, or encode it if it contains characters that are not ASCII
+fn display_name(name: &str) -> Cow<'_, str> {
+    if name.is_ascii() {
+        Cow::Owned(format!(
+            "\"{}\"",
+            name.replace('\\', "\\\\").replace('"', "\\\"")
+        ))
+    } else {
+        encode_header(name)
+    }
+}
+
+/// Quote a MIME parameter such as a file name, encoding it if it contains characters that are not ASCII
+///
+/// Quoted strings cannot be folded, so the encoded name is a single word regardless of its length.
+fn quote_parameter(value: &str) -> String {
+    if value.is_ascii() {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("\"=?UTF-8?B?{}?=\"", STANDARD.encode(value))
+    }
+}
+
+/// Encode header text as RFC 2047 encoded words if it contains characters that are not printable ASCII
+fn encode_header(text: &str) -> Cow<'_, str> {
+    if text.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return Cow::Borrowed(text);
+    }
+
+    // Split on character boundaries so every encoded word holds valid UTF-8
+    let mut words = vec![];
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        if idx + c.len_utf8() - start > ENCODED_WORD_BYTES {
+            words.push(&text[start..idx]);
+            start = idx;
+        }
+    }
+    words.push(&text[start..]);
+
+    Cow::Owned(
+        words
+            .iter()
+            .map(|word| format!("=?UTF-8?B?{}?=", STANDARD.encode(word)))
+            .collect::<Vec<_>>()
+            .join("\n "),
+    )
+}
+
+/// Encode bytes as `base64`, wrapping lines to the length required by MIME
+fn wrap_base64(bytes: &[u8]) -> String {
+    let encoded = STANDARD.encode(bytes);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / BASE64_LINE_LENGTH + 1);
+    for line in encoded.as_bytes().chunks(BASE64_LINE_LENGTH) {
+        // `base64` output is always ASCII
+        wrapped.push_str(std::str::from_utf8(line).unwrap_or_default());
+        wrapped.push('\n');
+    }
+    wrapped
+}
+
+/// Quote lines that would otherwise be read as the start of a new message, using the `mboxrd` convention
+fn escape_from_lines(body: &str) -> String {
+    body.lines()
+        .map(|line| {
+            if line.trim_start_matches('>').starts_with("From ") {
+                Cow::Owned(format!(">{line}"))
+            } else {
+                Cow::Borrowed(line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{copy, write},
+    };
+
+    use rusqlite::Connection;
+
+    use crate::{
+        Config, Exporter, Mbox, Options,
+        app::export_type::ExportType,
+        exporters::mbox::{
+            display_name, email_address, encode_header, escape_from_lines, format_text_part,
+            quote_parameter, wrap_base64,
+        },
+    };
+    use imessage_database::tables::table::ME;
+
+    #[test]
+    fn can_create() {
+        let options = Options::fake_options(ExportType::Mbox);
+        let config = Config::fake_app(options);
+        let exporter = Mbox::new(&config).unwrap();
+        assert_eq!(exporter.files.len(), 0);
+        assert!(exporter.orphaned.is_none());
+    }
+
+    #[test]
+    fn can_format_message_from_them() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Mbox);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "+15558675309".to_string());
+        let exporter = Mbox::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+        message.handle_id = Some(999999);
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+
+        let actual = exporter.format_message(&message).unwrap();
+        let expected = concat!(
+            "From +15558675309@imessage.invalid Tue May 17 17:29:42 2022\n",
+            "From: \"+15558675309\" <+15558675309@imessage.invalid>\n",
+            "To: \"Me\" <me@imessage.invalid>\n",
+            "Date: Tue, 17 May 2022 17:29:42 -0700\n",
+            "Message-ID: <fake_guid@imessage.invalid>\n",
+            "X-iMessage-Service: iMessage\n",
+            "MIME-Version: 1.0\n",
+            "Content-Type: text/plain; charset=utf-8\n",
+            "Content-Transfer-Encoding: 8bit\n",
+            "\n",
+            "Hello world\n",
+            "\n",
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_message_from_me_reply() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Mbox);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "person@example.com".to_string());
+        let exporter = Mbox::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+        message.handle_id = Some(999999);
+        message.is_from_me = true;
+        message.destination_caller_id = Some("+1 (555) 123-4567".to_string());
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.subject = Some("Café".to_string());
+        message.text = Some("From here on".to_string());
+        message.thread_originator_guid = Some("parent_guid".to_string());
+
+        let actual = exporter.format_message(&message).unwrap();
+        let expected = concat!(
+            "From +15551234567@imessage.invalid Tue May 17 17:29:42 2022\n",
+            "From: \"Me\" <+15551234567@imessage.invalid>\n",
+            "To: \"person@example.com\" <person@example.com>\n",
+            "Date: Tue, 17 May 2022 17:29:42 -0700\n",
+            "Message-ID: <fake_guid@imessage.invalid>\n",
+            "In-Reply-To: <parent_guid@imessage.invalid>\n",
+            "References: <parent_guid@imessage.invalid>\n",
+            "Subject: =?UTF-8?B?Q2Fmw6k=?=\n",
+            "X-iMessage-Service: iMessage\n",
+            "MIME-Version: 1.0\n",
+            "Content-Type: text/plain; charset=utf-8\n",
+            "Content-Transfer-Encoding: 8bit\n",
+            "\n",
+            ">From here on\n",
+            "\n",
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_body_with_tapbacks() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Mbox);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(0, ME.to_string());
+
+        let mut tapback = Config::fake_message();
+        tapback.associated_message_type = Some(2001);
+        tapback.associated_message_guid = Some("fake_guid".to_string());
+        tapback.is_from_me = true;
+        config.tapbacks.insert(
+            "fake_guid".to_string(),
+            std::collections::HashMap::from([(0, vec![tapback])]),
+        );
+        let exporter = Mbox::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.guid = "fake_guid".to_string();
+        message.text = Some("\u{FFFC}Hello world".to_string());
+
+        let actual = exporter.format_body(&message).unwrap();
+        let expected = "Hello world\n\nTapbacks:\nLiked by Me";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_announcement() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Mbox);
+        let config = Config::fake_app(options);
+        let exporter = Mbox::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.group_title = Some("Hello world".to_string());
+        message.is_from_me = true;
+        message.item_type = 2;
+
+        let actual = exporter.format_body(&message).unwrap();
+        let expected = "You renamed the conversation to Hello world";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_attachment() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Mbox);
+        let config = Config::fake_app(options);
+        let exporter = Mbox::new(&config).unwrap();
+
+        let path = temp_dir().join("imessage-exporter-mbox-attachment.png");
+        write(&path, b"Hello world").unwrap();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = Some(path.display().to_string());
+
+        let actual = exporter.format_attachment(&attachment).unwrap();
+        let expected = concat!(
+            "Content-Type: image/png; name=\"d.jpg\"\n",
+            "Content-Disposition: attachment; filename=\"d.jpg\"\n",
+            "Content-Transfer-Encoding: base64\n",
+            "\n",
+            "SGVsbG8gd29ybGQ=\n",
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_attachment_missing() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::Mbox);
+        let config = Config::fake_app(options);
+        let exporter = Mbox::new(&config).unwrap();
+
+        let attachment = Config::fake_attachment();
+
+        assert!(exporter.format_attachment(&attachment).is_none());
+    }
+
+    #[test]
+    fn can_format_message_attachments_unreadable() {
+        // Copy the database without the attachment join table, so the attachment rows cannot be read
+        let mut options = Options::fake_options(ExportType::Mbox);
+        let db_path = temp_dir().join("imessage-exporter-mbox-attachments.db");
+        copy(&options.db_path, &db_path).unwrap();
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("DROP TABLE message_attachment_join;")
+            .unwrap();
+        options.db_path = db_path;
+
+        // Create exporter
+        let config = Config::fake_app(options);
+        let exporter = Mbox::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hello world".to_string());
+        message.num_attachments = 1;
+
+        let actual = exporter.format_message(&message).unwrap();
+
+        assert!(actual.contains("Hello world\n"));
+        assert!(actual.contains("Attachment missing: unable to read attachment data\n"));
+        assert!(!actual.contains("multipart/mixed"));
+    }
+
+    #[test]
+    fn can_format_text_part_long_line() {
+        let body = "a".repeat(1000);
+        let actual = format_text_part(&body);
+
+        assert!(actual.starts_with(
+            "Content-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: base64\n\n"
+        ));
+        assert!(actual.lines().all(|line| line.len() <= 76));
+    }
+
+    #[test]
+    fn can_build_email_address() {
+        assert_eq!(
+            email_address("+1 (555) 867-5309"),
+            "+15558675309@imessage.invalid"
+        );
+        assert_eq!(email_address("name@example.com"), "name@example.com");
+        assert_eq!(email_address("()"), "unknown@imessage.invalid");
+    }
+
+    #[test]
+    fn can_quote_display_name() {
+        assert_eq!(display_name("Sam \"The Man\""), "\"Sam \\\"The Man\\\"\"");
+        assert_eq!(display_name("Zoë"), "=?UTF-8?B?Wm/Dqw==?=");
+    }
+
+    #[test]
+    fn can_quote_parameter() {
+        assert_eq!(quote_parameter("a \"b\".png"), "\"a \\\"b\\\".png\"");
+        assert_eq!(quote_parameter("é.png"), "\"=?UTF-8?B?w6kucG5n?=\"");
+    }
+
+    #[test]
+    fn can_encode_long_header() {
+        let header = "é".repeat(30);
+        let actual = encode_header(&header);
+
+        assert_eq!(actual.lines().count(), 2);
+        assert!(actual.lines().all(|line| line.trim().len() <= 75));
+    }
+
+    #[test]
+    fn can_leave_ascii_header() {
+        assert_eq!(encode_header("Hello world"), "Hello world");
+    }
+
+    #[test]
+    fn can_escape_from_lines() {
+        assert_eq!(
+            escape_from_lines("From me\n>From you\nNot From here"),
+            ">From me\n>>From you\nNot From here"
+        );
+    }
+
+    #[test]
+    fn can_wrap_base64() {
+        let actual = wrap_base64(&[0; 100]);
+
+        assert_eq!(actual.lines().count(), 2);
+        assert_eq!(actual.lines().next().unwrap().len(), 76);
+        assert!(actual.ends_with('\n'));
+    }
+}
@@ -1,4 +1,11 @@
+pub mod csv;
 pub mod exporter;
 pub mod html;
-pub mod txt;
 pub mod json;
+pub mod json_schema;
+pub mod markdown;
+pub mod mbox;
+pub mod sms_backup;
+pub mod sqlite;
+pub mod templates;
+pub mod txt;
@@ -0,0 +1,65 @@
+// Searches the messages listed in `search_index.js`, which defines `SEARCH_INDEX` as rows of
+// [text, sender, date, conversation, link]
+const MAX_RESULTS = 200;
+
+const input = document.getElementById("query");
+const summary = document.getElementById("summary");
+const results = document.getElementById("results");
+
+// Lowercase every message once so each query only has to scan
+const haystack = SEARCH_INDEX.map((row) => row[0].toLowerCase());
+
+function search() {
+    const terms = input.value.toLowerCase().split(/\s+/).filter((term) => term.length > 0);
+    results.replaceChildren();
+    if (terms.length === 0) {
+        summary.textContent = `${SEARCH_INDEX.length} messages`;
+        return;
+    }
+
+    let found = 0;
+    // Show the newest matches first
+    for (let idx = haystack.length - 1; idx >= 0; idx--) {
+        if (!terms.every((term) => haystack[idx].includes(term))) {
+            continue;
+        }
+        found++;
+        if (found > MAX_RESULTS) {
+            continue;
+        }
+
+        const [text, sender, date, conversation, link] = SEARCH_INDEX[idx];
+        const result = document.createElement("div");
+        result.className = "result";
+
+        const header = document.createElement("p");
+        const anchor = document.createElement("a");
+        anchor.href = link;
+        anchor.textContent = conversation;
+        const timestamp = document.createElement("span");
+        timestamp.className = "timestamp";
+        timestamp.textContent = ` ${date} `;
+        const who = document.createElement("span");
+        who.className = "sender";
+        who.textContent = sender;
+        header.append(anchor, timestamp, who);
+
+        const body = document.createElement("p");
+        body.className = "bubble";
+        body.textContent = text;
+
+        result.append(header, body);
+        results.append(result);
+    }
+
+    summary.textContent = found > MAX_RESULTS
+        ? `Showing the newest ${MAX_RESULTS} of ${found} matches`
+        : `${found} matches`;
+}
+
+let timer;
+input.addEventListener("input", () => {
+    clearTimeout(timer);
+    timer = setTimeout(search, 150);
+});
+search();
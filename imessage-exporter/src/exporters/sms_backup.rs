@@ -0,0 +1,732 @@
+use std::{
+    borrow::Cow,
+    fs::{File, remove_file},
+    io::{BufReader, BufWriter, Write, copy},
+    path::PathBuf,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::Local;
+
+use crate::{
+    app::{
+        error::RuntimeError, export_type::ExportType, progress::ExportProgress, runtime::Config,
+    },
+    exporters::exporter::Exporter,
+};
+
+use imessage_database::{
+    error::table::TableError,
+    tables::{attachment::Attachment, messages::Message, table::Table},
+    util::dates::format,
+};
+
+// MARK: SMS Backup
+/// Prefix that SMS Backup & Restore looks for when it scans for backups
+const BACKUP_PREFIX: &str = "sms-";
+
+/// Date format used in backup file names
+const BACKUP_DATE_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// Placeholder that Android uses for the device's own number when it is not known
+const OWN_ADDRESS: &str = "insert-address-token";
+
+/// Separates the addresses of a group message
+const ADDRESS_SEPARATOR: &str = "~";
+
+/// Android message box for received messages
+const BOX_INBOX: u8 = 1;
+/// Android message box for sent messages
+const BOX_SENT: u8 = 2;
+
+/// MMS PDU type of a received message (`m-retrieve-conf`)
+const MMS_RETRIEVE_CONF: u8 = 132;
+/// MMS PDU type of a sent message (`m-send-req`)
+const MMS_SEND_REQ: u8 = 128;
+
+/// MMS address type of the sender
+const ADDRESS_FROM: u8 = 137;
+/// MMS address type of a recipient
+const ADDRESS_TO: u8 = 151;
+
+/// `MIBenum` for `UTF-8`, used as the character set of MMS text
+const CHARSET_UTF8: u8 = 106;
+
+pub struct SmsBackup<'a> {
+    /// Data that is setup from the application's runtime
+    pub config: &'a Config,
+    /// Location of the finished backup
+    pub path: PathBuf,
+    /// Writer for message entries, which are buffered until the total count is known
+    pub entries: Option<BufWriter<File>>,
+    /// Number of entries written so far
+    count: usize,
+    /// Progress Bar model for alerting the user about current export state
+    pb: ExportProgress,
+}
+
+// MARK: Exporter
+impl<'a> Exporter<'a> for SmsBackup<'a> {
+    fn new(config: &'a Config) -> Result<Self, RuntimeError> {
+        let mut path = config.options.export_path.clone();
+        path.push(format!(
+            "{BACKUP_PREFIX}{}",
+            Local::now().format(BACKUP_DATE_FORMAT)
+        ));
+        path.set_extension(ExportType::SmsBackup.to_string());
+
+        Ok(SmsBackup {
+            config,
+            path,
+            entries: None,
+            count: 0,
+            pb: ExportProgress::new(),
+        })
+    }
+
+    fn iter_messages(&mut self) -> Result<(), RuntimeError> {
+        // Tell the user what we are doing
+        eprintln!(
+            "Exporting to {} as {}...",
+            self.path.display(),
+            ExportType::SmsBackup
+        );
+
+        // Keep track of current message ROWID
+        let mut current_message_row = -1;
+
+        // Set up progress bar
+        let mut current_message = 0;
+        let total_messages =
+            Message::get_count(self.config.db(), &self.config.options.query_context)?;
+        self.pb.start(total_messages);
+
+        let mut statement =
+            Message::stream_rows(self.config.db(), &self.config.options.query_context)?;
+
+        let messages = statement
+            .query_map([], |row| Ok(Message::from_row(row)))
+            .map_err(|err| RuntimeError::DatabaseError(TableError::QueryError(err)))?;
+
+        for message in messages {
+            let mut msg = Message::extract(message)?;
+
+            // Early escape if we try and render the same message GUID twice
+            // See https://github.com/ReagentX/imessage-exporter/issues/135 for rationale
+            if msg.rowid == current_message_row {
+                current_message += 1;
+                continue;
+            }
+            current_message_row = msg.rowid;
+
+            // Generate the text of the message
+            let _ = msg.generate_text(self.config.db());
+
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
+            }
+
+            // Android has no equivalent for tapbacks or conversation announcements
+            if !msg.is_tapback() && !msg.is_announcement() {
+                let entry = self.format_message(&msg);
+                SmsBackup::write_to_file(self.get_or_create_file(&msg)?, &entry)?;
+                self.count += 1;
+            }
+
+            current_message += 1;
+            if current_message % 99 == 0 {
+                self.pb.set_position(current_message);
+            }
+        }
+        self.pb.finish();
+
+        eprintln!("Writing backup file...");
+        self.finish()
+    }
+
+    /// Every message is written to a single backup
+    fn get_or_create_file(
+        &mut self,
+        _message: &Message,
+    ) -> Result<&mut BufWriter<File>, RuntimeError> {
+        let buf = match self.entries.take() {
+            Some(buf) => buf,
+            None => BufWriter::new(File::create(self.entries_path())?),
+        };
+        Ok(self.entries.insert(buf))
+    }
+
+    fn write_to_file(file: &mut BufWriter<File>, text: &str) -> Result<(), RuntimeError> {
+        file.write_all(text.as_bytes())
+            .map_err(RuntimeError::DiskError)
+    }
+}
+
+impl SmsBackup<'_> {
+    /// Location of the buffered entries while the export is running
+    fn entries_path(&self) -> PathBuf {
+        let mut path = self.path.clone();
+        path.set_extension("part");
+        path
+    }
+
+    /// Write the backup, which needs the number of entries in its root element
+    fn finish(&mut self) -> Result<(), RuntimeError> {
+        let mut backup = BufWriter::new(File::create(&self.path)?);
+        SmsBackup::write_to_file(
+            &mut backup,
+            &format!(
+                "<?xml version='1.0' encoding='UTF-8' standalone='yes' ?>\n<smses count=\"{}\" backup_date=\"{}\" type=\"full\">\n",
+                self.count,
+                Local::now().timestamp_millis()
+            ),
+        )?;
+
+        if let Some(mut entries) = self.entries.take() {
+            entries.flush()?;
+            drop(entries);
+
+            let entries_path = self.entries_path();
+            copy(&mut BufReader::new(File::open(&entries_path)?), &mut backup)?;
+            remove_file(entries_path)?;
+        }
+
+        SmsBackup::write_to_file(&mut backup, "</smses>\n")?;
+        backup.flush().map_err(RuntimeError::DiskError)
+    }
+
+    /// Format a message as an `<sms>` entry, or as an `<mms>` entry if it has attachments or several recipients
+    ///
+    /// If the attachments cannot be read from the database, the message is written without them.
+    fn format_message(&self, message: &Message) -> String {
+        let attachments = if message.num_attachments > 0 {
+            Attachment::from_message(self.config.db(), message).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let participants = self.participants(message);
+
+        if attachments.is_empty() && participants.len() <= 1 {
+            self.format_sms(message, participants.first().copied())
+        } else {
+            self.format_mms(message, &participants, &attachments)
+        }
+    }
+
+    /// Format a text message with a single other participant
+    fn format_sms(&self, message: &Message, participant: Option<i32>) -> String {
+        let date = self.timestamp(message);
+        let address = self.address(participant);
+
+        format!(
+            "  <sms protocol=\"0\" address=\"{}\" date=\"{date}\" type=\"{}\" subject=\"{}\" body=\"{}\" toa=\"null\" sc_toa=\"null\" service_center=\"null\" read=\"{}\" status=\"-1\" locked=\"0\" date_sent=\"{date}\" sub_id=\"-1\" readable_date=\"{}\" contact_name=\"{}\" />\n",
+            escape_attribute(&address),
+            if message.is_from_me() {
+                BOX_SENT
+            } else {
+                BOX_INBOX
+            },
+            message
+                .subject
+                .as_deref()
+                .map_or(Cow::Borrowed("null"), escape_attribute),
+            escape_attribute(&body(message)),
+            u8::from(message.is_read || message.is_from_me()),
+            escape_attribute(&format(&message.date(&self.config.offset))),
+            escape_attribute(&self.contact_name(participant)),
+        )
+    }
+
+    /// Format a multimedia or group message
+    fn format_mms(
+        &self,
+        message: &Message,
+        participants: &[i32],
+        attachments: &[Attachment],
+    ) -> String {
+        let date = self.timestamp(message);
+        let addresses = participants
+            .iter()
+            .map(|participant| self.address(Some(*participant)))
+            .collect::<Vec<_>>();
+        let contact_names = participants
+            .iter()
+            .map(|participant| self.contact_name(Some(*participant)))
+            .collect::<Vec<_>>();
+
+        // Parts: the text of the message followed by every attachment, with a note for unreadable files
+        let mut parts = vec![];
+        let mut text_only = true;
+        let text = body(message);
+        if !text.is_empty() {
+            parts.push(format_text_part(parts.len(), &text));
+        }
+        for attachment in attachments {
+            match self.format_part(attachment, parts.len()) {
+                Some(part) => {
+                    parts.push(part);
+                    text_only = false;
+                }
+                None => parts.push(format_text_part(
+                    parts.len(),
+                    &format!(
+                        "Attachment missing: {}",
+                        attachment.filename().unwrap_or("attachment")
+                    ),
+                )),
+            }
+        }
+        let parts = parts.concat();
+
+        // Addresses: the sender, then everyone else in the conversation
+        let mut addrs = String::new();
+        if message.is_from_me() {
+            addrs.push_str(&format_addr(&self.own_address(message), ADDRESS_FROM));
+            for address in &addresses {
+                addrs.push_str(&format_addr(address, ADDRESS_TO));
+            }
+        } else {
+            let sender = self.address(message.handle_id);
+            addrs.push_str(&format_addr(&sender, ADDRESS_FROM));
+            for address in addresses.iter().filter(|address| **address != sender) {
+                addrs.push_str(&format_addr(address, ADDRESS_TO));
+            }
+            addrs.push_str(&format_addr(&self.own_address(message), ADDRESS_TO));
+        }
+
+        let (msg_box, m_type) = if message.is_from_me() {
+            (BOX_SENT, MMS_SEND_REQ)
+        } else {
+            (BOX_INBOX, MMS_RETRIEVE_CONF)
+        };
+
+        format!(
+            "  <mms date=\"{date}\" rr=\"null\" sub=\"{}\" ct_t=\"application/vnd.wap.multipart.related\" read_status=\"null\" seen=\"1\" msg_box=\"{msg_box}\" address=\"{}\" sub_cs=\"null\" resp_st=\"null\" retr_st=\"null\" d_tm=\"null\" text_only=\"{}\" exp=\"null\" locked=\"0\" m_id=\"{}\" st=\"null\" retr_txt_cs=\"null\" retr_txt=\"null\" creator=\"null\" date_sent=\"{}\" read=\"{}\" m_size=\"null\" rpt_a=\"null\" ct_cls=\"null\" pri=\"null\" sub_id=\"-1\" tr_id=\"null\" resp_txt=\"null\" ct_l=\"null\" m_cls=\"personal\" d_rpt=\"null\" v=\"18\" m_type=\"{m_type}\" readable_date=\"{}\" contact_name=\"{}\">\n    <parts>\n{parts}    </parts>\n    <addrs>\n{addrs}    </addrs>\n  </mms>\n",
+            message
+                .subject
+                .as_deref()
+                .map_or(Cow::Borrowed("null"), escape_attribute),
+            escape_attribute(&addresses.join(ADDRESS_SEPARATOR)),
+            u8::from(text_only),
+            escape_attribute(&message.guid),
+            // Android stores the `date_sent` of an MMS in seconds, unlike its `date`
+            date / 1000,
+            u8::from(message.is_read || message.is_from_me()),
+            escape_attribute(&format(&message.date(&self.config.offset))),
+            escape_attribute(&contact_names.join(", ")),
+        )
+    }
+
+    /// Format an attachment as an MMS part that contains its original bytes, or `None` if the file cannot be read
+    fn format_part(&self, attachment: &Attachment, seq: usize) -> Option<String> {
+        let filename = attachment.filename().unwrap_or("attachment");
+        let bytes = match attachment.as_bytes(
+            &self.config.options.platform,
+            &self.config.options.db_path,
+            self.config.options.attachment_root.as_deref(),
+        ) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => {
+                eprintln!("Attachment missing: {filename}");
+                return None;
+            }
+            Err(why) => {
+                eprintln!("Unable to read {filename}: {why}");
+                return None;
+            }
+        };
+
+        let name = escape_attribute(filename);
+        let mime_type = match attachment.mime_type().as_mime_type() {
+            mime if mime.is_empty() => "application/octet-stream".to_string(),
+            mime => mime,
+        };
+
+        Some(format!(
+            "      <part seq=\"{seq}\" ct=\"{}\" name=\"{name}\" chset=\"null\" cd=\"null\" fn=\"null\" cid=\"&lt;{name}&gt;\" cl=\"{name}\" ctt_s=\"null\" ctt_t=\"null\" text=\"null\" data=\"{}\" />\n",
+            escape_attribute(&mime_type),
+            STANDARD.encode(bytes),
+        ))
+    }
+
+    /// Everyone in the conversation other than the database owner
+    fn participants(&self, message: &Message) -> Vec<i32> {
+        match self
+            .config
+            .conversation(message)
+            .and_then(|(chatroom, _)| self.config.chatroom_participants.get(&chatroom.rowid))
+        {
+            Some(participants) => participants.iter().copied().collect(),
+            None => message
+                .handle_id
+                .filter(|id| *id != 0)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Milliseconds since the Unix epoch, which Android uses for message dates
+    fn timestamp(&self, message: &Message) -> i64 {
+        message
+            .date(&self.config.offset)
+            .map(|date| date.timestamp_millis())
+            .unwrap_or_default()
+    }
+
+    /// Get the phone number or email address of a contact handle
+    fn address(&self, handle_id: Option<i32>) -> String {
+        handle_id
+            .and_then(|handle_id| self.config.participants.get(&handle_id))
+            // Deduplicated contacts list every one of their handles
+            .and_then(|handles| handles.split_whitespace().next())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Get the phone number or email address the database owner used for a message
+    fn own_address(&self, message: &Message) -> String {
+        message
+            .destination_caller_id
+            .clone()
+            .unwrap_or_else(|| OWN_ADDRESS.to_string())
+    }
+
+    /// Get the display name of a contact, using the name Android shows for unknown contacts as a fallback
+    fn contact_name(&self, handle_id: Option<i32>) -> String {
+        match handle_id {
+            Some(_) => self.config.who(handle_id, false, &None).to_string(),
+            None => "(Unknown)".to_string(),
+        }
+    }
+}
+
+/// Format an MMS part that contains text
+fn format_text_part(seq: usize, text: &str) -> String {
+    format!(
+        "      <part seq=\"{seq}\" ct=\"text/plain\" name=\"null\" chset=\"{CHARSET_UTF8}\" cd=\"null\" fn=\"null\" cid=\"&lt;text{seq}&gt;\" cl=\"text{seq}.txt\" ctt_s=\"null\" ctt_t=\"null\" text=\"{}\" />\n",
+        escape_attribute(text)
+    )
+}
+
+/// Format an MMS address entry
+fn format_addr(address: &str, kind: u8) -> String {
+    format!(
+        "      <addr address=\"{}\" type=\"{kind}\" charset=\"{CHARSET_UTF8}\" />\n",
+        escape_attribute(address)
+    )
+}
+
+/// Get the text of a message without the placeholders for attachments and app balloons
+fn body(message: &Message) -> String {
+    message
+        .text
+        .as_deref()
+        .unwrap_or_default()
+        .replace('\u{FFFC}', "")
+        .trim()
+        .to_string()
+}
+
+/// Escape text for an XML attribute, preserving line breaks and removing characters XML cannot represent
+fn escape_attribute(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"', '\n', '\r', '\t'])
+        && !text.chars().any(|c| c.is_control())
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+impl Drop for SmsBackup<'_> {
+    fn drop(&mut self) {
+        // Remove the buffered entries if the export stopped before `finish()` consumed them
+        drop(self.entries.take());
+        let entries_path = self.entries_path();
+        if entries_path.exists()
+            && let Err(e) = remove_file(&entries_path)
+        {
+            eprintln!(
+                "warning: failed to remove temporary backup entries at {}: {e}",
+                entries_path.display()
+            );
+        }
+    }
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        env::temp_dir,
+        fs::{create_dir_all, read_to_string, write},
+    };
+
+    use crate::{
+        Config, Exporter, Options, SmsBackup,
+        app::export_type::ExportType,
+        exporters::sms_backup::{body, escape_attribute, format_addr, format_text_part},
+    };
+    use imessage_database::tables::chat::Chat;
+
+    #[test]
+    fn can_create() {
+        let options = Options::fake_options(ExportType::SmsBackup);
+        let config = Config::fake_app(options);
+        let exporter = SmsBackup::new(&config).unwrap();
+        assert!(exporter.entries.is_none());
+        assert_eq!(exporter.count, 0);
+        assert!(
+            exporter
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("sms-")
+        );
+        assert_eq!(exporter.path.extension().unwrap(), "xml");
+    }
+
+    #[test]
+    fn can_format_sms_from_them() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::SmsBackup);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "+15558675309".to_string());
+        let exporter = SmsBackup::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.handle_id = Some(999999);
+        // May 17, 2022  5:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+
+        let actual = exporter.format_message(&message);
+        let expected = "  <sms protocol=\"0\" address=\"+15558675309\" date=\"1652833782000\" type=\"1\" subject=\"null\" body=\"Hello world\" toa=\"null\" sc_toa=\"null\" service_center=\"null\" read=\"0\" status=\"-1\" locked=\"0\" date_sent=\"1652833782000\" sub_id=\"-1\" readable_date=\"May 17, 2022  5:29:42 PM\" contact_name=\"+15558675309\" />\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_sms_from_me() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::SmsBackup);
+        let mut config = Config::fake_app(options);
+        config
+            .participants
+            .insert(999999, "+15558675309".to_string());
+        let exporter = SmsBackup::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.handle_id = Some(999999);
+        message.is_from_me = true;
+        message.date = 674526582885055488;
+        message.text = Some("Hello \"world\"\nBye".to_string());
+
+        let actual = exporter.format_message(&message);
+
+        assert!(actual.starts_with("  <sms "));
+        assert!(actual.contains(" type=\"2\" "));
+        assert!(actual.contains(" read=\"1\" "));
+        assert!(actual.contains(" body=\"Hello &quot;world&quot;&#10;Bye\" "));
+    }
+
+    #[test]
+    fn can_format_group_mms_from_them() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::SmsBackup);
+        let mut config = Config::fake_app(options);
+        config.participants.insert(1, "+15558675309".to_string());
+        config
+            .participants
+            .insert(2, "person@example.com".to_string());
+        config
+            .chatroom_participants
+            .insert(1, BTreeSet::from([1, 2]));
+        config.chatrooms.insert(
+            1,
+            Chat {
+                rowid: 1,
+                chat_identifier: "chat123".to_string(),
+                service_name: Some("SMS".to_string()),
+                display_name: None,
+            },
+        );
+        config.real_chatrooms.insert(1, 1);
+        let exporter = SmsBackup::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.handle_id = Some(2);
+        message.chat_id = Some(1);
+        message.destination_caller_id = Some("+15550000000".to_string());
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+
+        let actual = exporter.format_message(&message);
+
+        assert!(actual.starts_with("  <mms date=\"1652833782000\" "));
+        assert!(actual.contains(" msg_box=\"1\" "));
+        assert!(actual.contains(" m_type=\"132\" "));
+        assert!(actual.contains(" text_only=\"1\" "));
+        assert!(actual.contains(" date_sent=\"1652833782\" "));
+        assert!(actual.contains(" address=\"+15558675309~person@example.com\" "));
+        assert!(actual.contains("text=\"Hello world\""));
+        assert!(actual.contains(&format_addr("person@example.com", 137)));
+        assert!(actual.contains(&format_addr("+15558675309", 151)));
+        assert!(actual.contains(&format_addr("+15550000000", 151)));
+        assert!(!actual.contains(&format_addr("person@example.com", 151)));
+    }
+
+    #[test]
+    fn can_format_part() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::SmsBackup);
+        let config = Config::fake_app(options);
+        let exporter = SmsBackup::new(&config).unwrap();
+
+        let path = temp_dir().join("imessage-exporter-sms-backup-attachment.png");
+        write(&path, b"Hello world").unwrap();
+
+        let mut attachment = Config::fake_attachment();
+        attachment.filename = Some(path.display().to_string());
+
+        let actual = exporter.format_part(&attachment, 0).unwrap();
+        let expected = "      <part seq=\"0\" ct=\"image/png\" name=\"d.jpg\" chset=\"null\" cd=\"null\" fn=\"null\" cid=\"&lt;d.jpg&gt;\" cl=\"d.jpg\" ctt_s=\"null\" ctt_t=\"null\" text=\"null\" data=\"SGVsbG8gd29ybGQ=\" />\n";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn can_format_part_missing() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::SmsBackup);
+        let config = Config::fake_app(options);
+        let exporter = SmsBackup::new(&config).unwrap();
+
+        let attachment = Config::fake_attachment();
+
+        assert!(exporter.format_part(&attachment, 0).is_none());
+    }
+
+    #[test]
+    fn can_format_mms_attachment_missing() {
+        // Create exporter
+        let options = Options::fake_options(ExportType::SmsBackup);
+        let config = Config::fake_app(options);
+        let exporter = SmsBackup::new(&config).unwrap();
+
+        let path = temp_dir().join("imessage-exporter-sms-backup-readable.png");
+        write(&path, b"Hello world").unwrap();
+        let mut readable = Config::fake_attachment();
+        readable.filename = Some(path.display().to_string());
+
+        let mut message = Config::fake_message();
+        message.text = Some("Hello world".to_string());
+
+        // The attachment file is missing, so the MMS only contains text
+        let actual = exporter.format_mms(&message, &[], &[Config::fake_attachment()]);
+
+        assert!(actual.contains(" text_only=\"1\" "));
+        assert!(actual.contains(&format_text_part(0, "Hello world")));
+        assert!(actual.contains(&format_text_part(1, "Attachment missing: d.jpg")));
+
+        // Parts are numbered in order, around the missing attachment
+        let attachments = [Config::fake_attachment(), readable];
+        let actual = exporter.format_mms(&message, &[], &attachments);
+
+        assert!(actual.contains(" text_only=\"0\" "));
+        assert!(actual.contains(&format_text_part(1, "Attachment missing: d.jpg")));
+        assert!(actual.contains(&exporter.format_part(&attachments[1], 2).unwrap()));
+    }
+
+    #[test]
+    fn can_write_backup() {
+        let export_path = temp_dir().join("imessage-exporter-sms-backup");
+        create_dir_all(&export_path).unwrap();
+
+        // Create exporter
+        let mut options = Options::fake_options(ExportType::SmsBackup);
+        options.export_path = export_path;
+        let config = Config::fake_app(options);
+        let mut exporter = SmsBackup::new(&config).unwrap();
+
+        let message = Config::fake_message();
+        for _ in 0..2 {
+            let file = exporter.get_or_create_file(&message).unwrap();
+            SmsBackup::write_to_file(file, "  <sms />\n").unwrap();
+            exporter.count += 1;
+        }
+        exporter.finish().unwrap();
+
+        let actual = read_to_string(&exporter.path).unwrap();
+        assert!(actual.starts_with(
+            "<?xml version='1.0' encoding='UTF-8' standalone='yes' ?>\n<smses count=\"2\" "
+        ));
+        assert!(actual.ends_with("type=\"full\">\n  <sms />\n  <sms />\n</smses>\n"));
+        assert!(!exporter.entries_path().exists());
+    }
+
+    #[test]
+    fn can_remove_entries_after_error() {
+        let export_path = temp_dir().join("imessage-exporter-sms-backup-error");
+        create_dir_all(&export_path).unwrap();
+
+        // Create exporter
+        let mut options = Options::fake_options(ExportType::SmsBackup);
+        options.export_path = export_path;
+        let config = Config::fake_app(options);
+        let mut exporter = SmsBackup::new(&config).unwrap();
+
+        let file = exporter
+            .get_or_create_file(&Config::fake_message())
+            .unwrap();
+        SmsBackup::write_to_file(file, "  <sms />\n").unwrap();
+        let entries_path = exporter.entries_path();
+        assert!(entries_path.exists());
+
+        // Stop without calling `finish()`, as if the export failed
+        drop(exporter);
+
+        assert!(!entries_path.exists());
+    }
+
+    #[test]
+    fn can_get_body() {
+        let mut message = Config::fake_message();
+        message.text = Some("\u{FFFC}Hello world\u{FFFC}".to_string());
+        assert_eq!(body(&message), "Hello world");
+
+        message.text = None;
+        assert_eq!(body(&message), "");
+    }
+
+    #[test]
+    fn can_escape_attribute() {
+        assert_eq!(escape_attribute("Hello world"), "Hello world");
+        assert_eq!(
+            escape_attribute("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+        assert_eq!(escape_attribute("a\r\nb\tc"), "a&#13;&#10;b&#9;c");
+        assert_eq!(escape_attribute("a\u{0}b\u{1b}c"), "abc");
+    }
+}
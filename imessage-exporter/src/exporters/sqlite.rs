@@ -0,0 +1,1007 @@
+use std::{fs::remove_file, path::PathBuf};
+
+use chrono::Local;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+
+use crate::{
+    app::{
+        compatibility::attachment_manager::AttachmentManagerMode, error::RuntimeError,
+        export_type::ExportType, progress::ExportProgress, runtime::Config,
+    },
+    exporters::{
+        exporter::{Exporter, expressive_label, variant_label},
+        json::JSON,
+        json_schema::{EditKind, ReactionAction, ReactionKind},
+    },
+};
+
+use imessage_database::{
+    error::table::TableError,
+    message_types::{
+        edited::EditStatus,
+        variants::{Tapback, Variant},
+    },
+    tables::{
+        attachment::{Attachment, MediaType},
+        handle::Handle,
+        messages::{
+            Message,
+            models::{BubbleComponent, TextAttributes},
+        },
+        table::Table,
+    },
+    util::dates::get_local_time,
+};
+
+// MARK: SQLite
+/// Name of the archive file written to the export directory
+const ARCHIVE: &str = "messages";
+
+/// Version of the archive layout, also stored in `PRAGMA user_version`
+pub const ARCHIVE_VERSION: i32 = 1;
+
+/// Tables created in a new archive
+///
+/// Every table stores data that has already been decoded from `typedstream`, `plist`, and `protobuf`
+/// payloads, with duplicated handles and chats resolved. Dates are local time `RFC 3339` strings.
+const SCHEMA: &str = "
+-- Information about the export that created this archive
+CREATE TABLE metadata (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT
+);
+
+-- Every contact handle, i.e. a phone number or email address
+CREATE TABLE participants (
+    id INTEGER PRIMARY KEY,          -- ROWID in the source handle table
+    unique_id INTEGER,               -- Shared by every handle that belongs to the same contact
+    handle TEXT NOT NULL,            -- Phone number or email address
+    person_centric_id TEXT,
+    name TEXT                        -- Resolved display name
+);
+
+-- Every conversation
+CREATE TABLE chats (
+    id INTEGER PRIMARY KEY,          -- ROWID in the source chat table
+    unique_id INTEGER,               -- Shared by every chat with the same set of participants
+    chat_identifier TEXT NOT NULL,
+    service TEXT,
+    display_name TEXT
+);
+
+-- Members of each conversation
+CREATE TABLE chat_participants (
+    chat_id INTEGER NOT NULL REFERENCES chats(id),
+    participant_id INTEGER NOT NULL REFERENCES participants(id),
+    PRIMARY KEY (chat_id, participant_id)
+);
+
+-- Every message, excluding tapbacks, which are stored in `reactions`
+CREATE TABLE messages (
+    id INTEGER PRIMARY KEY,          -- ROWID in the source message table
+    guid TEXT NOT NULL UNIQUE,
+    chat_id INTEGER REFERENCES chats(id),
+    participant_id INTEGER REFERENCES participants(id),  -- NULL for messages sent by the database owner
+    sender TEXT,                     -- Resolved display name of the sender
+    is_from_me INTEGER NOT NULL,
+    service TEXT,
+    date TEXT,
+    date_read TEXT,
+    date_delivered TEXT,
+    date_edited TEXT,
+    text TEXT,                       -- Decoded body of the message
+    subject TEXT,
+    variant TEXT,                    -- normal, edited, app, shareplay, or unknown
+    -- Effect the message was sent with: slam, loud, gentle, invisible_ink, confetti, echo, fireworks,
+    -- balloons, heart, lasers, shooting_star, sparkles, spotlight, or the raw style ID of a new effect
+    expressive TEXT,
+    reply_to_guid TEXT,              -- GUID of the message that started the thread
+    reply_to_part INTEGER,           -- Part of the message that started the thread
+    is_deleted INTEGER NOT NULL
+);
+CREATE INDEX messages_chat_date ON messages(chat_id, date);
+CREATE INDEX messages_reply_to ON messages(reply_to_guid);
+
+-- The bubbles that make up each message, in order
+CREATE TABLE message_parts (
+    message_id INTEGER NOT NULL REFERENCES messages(id),
+    part INTEGER NOT NULL,
+    kind TEXT NOT NULL,              -- text, attachment, app, or retracted
+    text TEXT,                       -- Text of the part, if it is a text part
+    attachment_id INTEGER REFERENCES attachments(id),
+    edited INTEGER NOT NULL,
+    PRIMARY KEY (message_id, part)
+);
+
+-- Tapbacks on each message
+CREATE TABLE reactions (
+    id INTEGER PRIMARY KEY,          -- ROWID in the source message table
+    guid TEXT NOT NULL UNIQUE,
+    message_id INTEGER NOT NULL REFERENCES messages(id),
+    part INTEGER NOT NULL,
+    kind TEXT NOT NULL,              -- loved, liked, disliked, laughed, emphasized, questioned, emoji, or sticker
+    emoji TEXT,
+    action TEXT NOT NULL,            -- added or removed
+    participant_id INTEGER REFERENCES participants(id),
+    sender TEXT,
+    is_from_me INTEGER NOT NULL,
+    date TEXT
+);
+CREATE INDEX reactions_message ON reactions(message_id);
+
+-- Edit and unsend history for each message part
+CREATE TABLE edits (
+    message_id INTEGER NOT NULL REFERENCES messages(id),
+    part INTEGER NOT NULL,
+    revision INTEGER NOT NULL,       -- Order of the version, starting at 0 for the original
+    status TEXT NOT NULL,            -- edited or unsent
+    date TEXT,
+    text TEXT,                       -- NULL for unsent parts
+    PRIMARY KEY (message_id, part, revision)
+);
+
+-- Files sent with each message
+CREATE TABLE attachments (
+    id INTEGER PRIMARY KEY,          -- ROWID in the source attachment table
+    message_id INTEGER NOT NULL REFERENCES messages(id),
+    filename TEXT,
+    transfer_name TEXT,
+    uti TEXT,
+    mime_type TEXT,
+    total_bytes INTEGER,
+    is_sticker INTEGER NOT NULL,
+    hide_attachment INTEGER NOT NULL,
+    emoji_description TEXT,          -- Prompt used to generate a Genmoji
+    transcription TEXT,              -- Transcript of an audio message
+    resolved_path TEXT,              -- Location of the file on the source device
+    exported_path TEXT               -- Location of the copied file, relative to the export
+);
+CREATE INDEX attachments_message ON attachments(message_id);
+
+-- App integrations and rich links
+CREATE TABLE balloons (
+    message_id INTEGER PRIMARY KEY REFERENCES messages(id),
+    bundle_id TEXT,
+    kind TEXT,                       -- Same as the `kind` field in `data`
+    data TEXT NOT NULL               -- JSON object that matches the `Balloon` definition in the JSON export schema
+);
+
+-- Full text search over message bodies and subjects
+CREATE VIRTUAL TABLE messages_fts USING fts5(
+    text,
+    subject,
+    content = 'messages',
+    content_rowid = 'id'
+);
+CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+    INSERT INTO messages_fts(rowid, text, subject) VALUES (new.id, new.text, new.subject);
+END;
+CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+    INSERT INTO messages_fts(messages_fts, rowid, text, subject) VALUES ('delete', old.id, old.text, old.subject);
+END;
+CREATE TRIGGER messages_fts_update AFTER UPDATE ON messages BEGIN
+    INSERT INTO messages_fts(messages_fts, rowid, text, subject) VALUES ('delete', old.id, old.text, old.subject);
+    INSERT INTO messages_fts(rowid, text, subject) VALUES (new.id, new.text, new.subject);
+END;
+";
+
+/// Writes every conversation into a single queryable SQLite archive
+///
+/// This is the only exporter that does not implement [`Exporter`]. That trait hands out a
+/// `BufWriter<File>` for each message and writes formatted text into it, but the archive is one
+/// [`Connection`] that is written with parameterized statements inside a single transaction, so
+/// there is no file or text to return. [`SQLite::new`] and [`SQLite::iter_messages`] mirror the
+/// trait's methods so the runtime can drive it like the other exporters.
+pub struct SQLite<'a> {
+    /// Data that is setup from the application's runtime
+    pub config: &'a Config,
+    /// Connection to the archive being written
+    pub archive: Connection,
+    /// Formats balloon payloads the same way JSON exports do
+    balloons: JSON<'a>,
+    /// Progress Bar model for alerting the user about current export state
+    pb: ExportProgress,
+}
+
+impl<'a> SQLite<'a> {
+    /// Create a new exporter with references to the cached data, replacing any existing archive
+    pub fn new(config: &'a Config) -> Result<Self, RuntimeError> {
+        let path = SQLite::path(config);
+        if path.exists() {
+            remove_file(&path)?;
+        }
+
+        let archive = Connection::open(&path).map_err(query_error)?;
+        archive.execute_batch(SCHEMA).map_err(query_error)?;
+        archive
+            .pragma_update(None, "user_version", ARCHIVE_VERSION)
+            .map_err(query_error)?;
+
+        Ok(SQLite {
+            config,
+            archive,
+            balloons: JSON::new(config)?,
+            pb: ExportProgress::new(),
+        })
+    }
+
+    /// Location of the archive in the export directory
+    fn path(config: &Config) -> PathBuf {
+        let mut path = config.options.export_path.clone();
+        path.push(ARCHIVE);
+        path.set_extension(ExportType::Sqlite.to_string());
+        path
+    }
+
+    /// Begin iterating over the messages table
+    pub fn iter_messages(&mut self) -> Result<(), RuntimeError> {
+        // Tell the user what we are doing
+        eprintln!(
+            "Exporting to {} as {}...",
+            SQLite::path(self.config).display(),
+            ExportType::Sqlite
+        );
+
+        // A single transaction is much faster than committing every row
+        let transaction = self.archive.unchecked_transaction().map_err(query_error)?;
+
+        self.write_metadata()?;
+        self.write_participants()?;
+        self.write_chats()?;
+
+        // Keep track of current message ROWID
+        let mut current_message_row = -1;
+
+        // Set up progress bar
+        let mut current_message = 0;
+        let total_messages =
+            Message::get_count(self.config.db(), &self.config.options.query_context)?;
+        self.pb.start(total_messages);
+
+        let mut statement =
+            Message::stream_rows(self.config.db(), &self.config.options.query_context)?;
+
+        let messages = statement
+            .query_map([], |row| Ok(Message::from_row(row)))
+            .map_err(query_error)?;
+
+        for message in messages {
+            let mut msg = Message::extract(message)?;
+
+            // Early escape if we try and render the same message GUID twice
+            // See https://github.com/ReagentX/imessage-exporter/issues/135 for rationale
+            if msg.rowid == current_message_row {
+                current_message += 1;
+                continue;
+            }
+            current_message_row = msg.rowid;
+
+            // Generate the text of the message
+            let _ = msg.generate_text(self.config.db());
+
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
+            }
+
+            // Tapbacks are written with the message they react to
+            if !msg.is_tapback() {
+                self.write_message(&msg)?;
+            }
+
+            current_message += 1;
+            if current_message % 99 == 0 {
+                self.pb.set_position(current_message);
+            }
+        }
+        self.pb.finish();
+
+        eprintln!("Writing archive to disk...");
+        transaction.commit().map_err(query_error)?;
+        self.archive
+            .execute_batch("PRAGMA optimize;")
+            .map_err(query_error)
+    }
+
+    /// Record how and when the archive was created
+    fn write_metadata(&self) -> Result<(), RuntimeError> {
+        let mut statement = self
+            .archive
+            .prepare("INSERT INTO metadata (key, value) VALUES (?1, ?2)")
+            .map_err(query_error)?;
+
+        for (key, value) in [
+            ("archive_version", ARCHIVE_VERSION.to_string()),
+            ("exporter", env!("CARGO_PKG_NAME").to_string()),
+            ("exporter_version", env!("CARGO_PKG_VERSION").to_string()),
+            ("exported_at", Local::now().to_rfc3339()),
+            ("source", self.config.options.db_path.display().to_string()),
+            ("platform", self.config.options.platform.to_string()),
+        ] {
+            statement
+                .execute(params![key, value])
+                .map_err(query_error)?;
+        }
+        Ok(())
+    }
+
+    /// Write every contact handle
+    fn write_participants(&self) -> Result<(), RuntimeError> {
+        let mut insert = self
+            .archive
+            .prepare(
+                "INSERT INTO participants (id, unique_id, handle, person_centric_id, name)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(query_error)?;
+
+        let mut statement = Handle::get(self.config.db())?;
+        let handles = statement
+            .query_map([], |row| Ok(Handle::from_row(row)))
+            .map_err(query_error)?;
+
+        for handle in handles {
+            let handle = Handle::extract(handle)?;
+            insert
+                .execute(params![
+                    handle.rowid,
+                    self.config.real_participants.get(&handle.rowid),
+                    handle.id,
+                    handle.person_centric_id,
+                    self.config.who(Some(handle.rowid), false, &None),
+                ])
+                .map_err(query_error)?;
+        }
+        Ok(())
+    }
+
+    /// Write every conversation and its members
+    fn write_chats(&self) -> Result<(), RuntimeError> {
+        let mut insert_chat = self
+            .archive
+            .prepare(
+                "INSERT INTO chats (id, unique_id, chat_identifier, service, display_name)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(query_error)?;
+
+        // Members whose handle no longer exists are skipped to keep the foreign keys valid
+        let mut insert_member = self
+            .archive
+            .prepare(
+                "INSERT INTO chat_participants (chat_id, participant_id)
+                SELECT ?1, id FROM participants WHERE id = ?2",
+            )
+            .map_err(query_error)?;
+
+        for chat in self.config.chatrooms.values() {
+            insert_chat
+                .execute(params![
+                    chat.rowid,
+                    self.config.real_chatrooms.get(&chat.rowid),
+                    chat.chat_identifier,
+                    chat.service().to_string(),
+                    chat.display_name(),
+                ])
+                .map_err(query_error)?;
+
+            if let Some(participants) = self.config.chatroom_participants.get(&chat.rowid) {
+                for participant in participants {
+                    insert_member
+                        .execute(params![chat.rowid, participant])
+                        .map_err(query_error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a message along with its parts, attachments, tapbacks, edits, and balloon
+    fn write_message(&self, message: &Message) -> Result<(), RuntimeError> {
+        self.archive
+            .prepare_cached(
+                "INSERT INTO messages (
+                    id, guid, chat_id, participant_id, sender, is_from_me, service,
+                    date, date_read, date_delivered, date_edited, text, subject,
+                    variant, expressive, reply_to_guid, reply_to_part, is_deleted
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            )
+            .and_then(|mut statement| {
+                statement.execute(params![
+                    message.rowid,
+                    message.guid,
+                    self.config
+                        .conversation(message)
+                        .map(|(chatroom, _)| chatroom.rowid),
+                    self.participant_id(message),
+                    self.config.who(
+                        message.handle_id,
+                        message.is_from_me(),
+                        &message.destination_caller_id,
+                    ),
+                    message.is_from_me(),
+                    message.service().to_string(),
+                    self.format_date(message.date),
+                    self.format_date(message.date_read),
+                    self.format_date(message.date_delivered),
+                    self.format_date(message.date_edited),
+                    message.text,
+                    message.subject,
+                    variant_label(&message.variant()),
+                    expressive_label(&message.get_expressive()),
+                    message.thread_originator_guid,
+                    message
+                        .thread_originator_guid
+                        .as_ref()
+                        .map(|_| message.get_reply_index()),
+                    message.is_deleted(),
+                ])
+            })
+            .map_err(query_error)?;
+
+        let attachments = self.write_attachments(message)?;
+        self.write_parts(message, &attachments)?;
+        self.write_reactions(message)?;
+        self.write_edits(message)?;
+        self.write_balloon(message)
+    }
+
+    /// Write the attachments that belong to a message, copying the files if requested
+    ///
+    /// Returns the attachment ROWIDs in the order they appear in the message body.
+    fn write_attachments(&self, message: &Message) -> Result<Vec<i32>, RuntimeError> {
+        if message.num_attachments == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut attachments =
+            Attachment::from_message(self.config.db(), message).unwrap_or_default();
+        let mut transcriptions =
+            message
+                .components
+                .iter()
+                .filter_map(|component| match component {
+                    BubbleComponent::Attachment(meta) => Some(meta.transcription.as_deref()),
+                    _ => None,
+                });
+
+        let mut statement = self
+            .archive
+            .prepare_cached(
+                "INSERT OR IGNORE INTO attachments (
+                    id, message_id, filename, transfer_name, uti, mime_type, total_bytes, is_sticker,
+                    hide_attachment, emoji_description, transcription, resolved_path, exported_path
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            )
+            .map_err(query_error)?;
+
+        for attachment in &mut attachments {
+            self.copy_attachment(attachment, message);
+            statement
+                .execute(params![
+                    attachment.rowid,
+                    message.rowid,
+                    attachment.filename,
+                    attachment.transfer_name,
+                    attachment.uti,
+                    attachment.mime_type().as_mime_type(),
+                    attachment.total_bytes,
+                    attachment.is_sticker,
+                    attachment.hide_attachment != 0,
+                    attachment.emoji_description,
+                    transcriptions.next().flatten(),
+                    attachment.resolved_attachment_path(
+                        &self.config.options.platform,
+                        &self.config.options.db_path,
+                        self.config.options.attachment_root.as_deref(),
+                    ),
+                    attachment
+                        .copied_path
+                        .as_ref()
+                        .map(|_| self.config.message_attachment_path(attachment)),
+                ])
+                .map_err(query_error)?;
+        }
+
+        Ok(attachments
+            .iter()
+            .map(|attachment| attachment.rowid)
+            .collect())
+    }
+
+    /// Write the bubbles that make up a message
+    fn write_parts(&self, message: &Message, attachments: &[i32]) -> Result<(), RuntimeError> {
+        let mut statement = self
+            .archive
+            .prepare_cached(
+                "INSERT INTO message_parts (message_id, part, kind, text, attachment_id, edited)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(query_error)?;
+
+        let mut attachment_ids = attachments.iter();
+        for (idx, component) in message.components.iter().enumerate() {
+            let (kind, text, attachment_id) = match component {
+                BubbleComponent::Text(ranges) => ("text", part_text(message, ranges), None),
+                BubbleComponent::Attachment(_) => ("attachment", None, attachment_ids.next()),
+                BubbleComponent::App => ("app", None, None),
+                BubbleComponent::Retracted => ("retracted", None, None),
+            };
+            statement
+                .execute(params![
+                    message.rowid,
+                    idx,
+                    kind,
+                    text,
+                    attachment_id,
+                    message.is_part_edited(idx),
+                ])
+                .map_err(query_error)?;
+        }
+        Ok(())
+    }
+
+    /// Write the tapbacks on every part of a message
+    fn write_reactions(&self, message: &Message) -> Result<(), RuntimeError> {
+        let Some(tapbacks) = self.config.tapbacks.get(&message.guid) else {
+            return Ok(());
+        };
+
+        let mut statement = self
+            .archive
+            .prepare_cached(
+                "INSERT OR IGNORE INTO reactions (
+                    id, guid, message_id, part, kind, emoji, action, participant_id, sender, is_from_me, date
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )
+            .map_err(query_error)?;
+
+        for tapback in tapbacks.values().flatten() {
+            if let Variant::Tapback(part, action, kind) = tapback.variant() {
+                statement
+                    .execute(params![
+                        tapback.rowid,
+                        tapback.guid,
+                        message.rowid,
+                        part,
+                        label(&ReactionKind::from(&kind)),
+                        match kind {
+                            Tapback::Emoji(emoji) => emoji,
+                            _ => None,
+                        },
+                        label(&ReactionAction::from(&action)),
+                        self.participant_id(tapback),
+                        self.config.who(
+                            tapback.handle_id,
+                            tapback.is_from_me(),
+                            &tapback.destination_caller_id,
+                        ),
+                        tapback.is_from_me(),
+                        self.format_date(tapback.date),
+                    ])
+                    .map_err(query_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the edit and unsend history of every part of a message
+    fn write_edits(&self, message: &Message) -> Result<(), RuntimeError> {
+        let Some(edited_parts) = &message.edited_parts else {
+            return Ok(());
+        };
+
+        let mut statement = self
+            .archive
+            .prepare_cached(
+                "INSERT INTO edits (message_id, part, revision, status, date, text)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(query_error)?;
+
+        for (idx, part) in edited_parts.parts.iter().enumerate() {
+            let status = label(&EditKind::from(&part.status));
+            match part.status {
+                EditStatus::Edited => {
+                    for (revision, event) in part.edit_history.iter().enumerate() {
+                        statement
+                            .execute(params![
+                                message.rowid,
+                                idx,
+                                revision,
+                                status,
+                                self.format_date(event.date),
+                                event.text,
+                            ])
+                            .map_err(query_error)?;
+                    }
+                }
+                EditStatus::Unsent => {
+                    statement
+                        .execute(params![
+                            message.rowid,
+                            idx,
+                            0,
+                            status,
+                            self.format_date(message.date_edited),
+                            None::<&str>,
+                        ])
+                        .map_err(query_error)?;
+                }
+                EditStatus::Original => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the parsed contents of an app integration or rich link
+    fn write_balloon(&self, message: &Message) -> Result<(), RuntimeError> {
+        if !matches!(message.variant(), Variant::App(_)) {
+            return Ok(());
+        }
+
+        // Balloons that reference attachments already wrote them in `write_attachments`
+        let data = self.balloons.format_balloon(message)?;
+        let kind = serde_json::from_str::<serde_json::Value>(&data)
+            .ok()
+            .and_then(|value| value.get("kind")?.as_str().map(String::from));
+
+        self.archive
+            .prepare_cached(
+                "INSERT INTO balloons (message_id, bundle_id, kind, data) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .and_then(|mut statement| {
+                statement.execute(params![
+                    message.rowid,
+                    message.balloon_bundle_id,
+                    kind,
+                    data
+                ])
+            })
+            .map_err(query_error)?;
+        Ok(())
+    }
+
+    /// Copy an attachment to the export directory, if requested
+    fn copy_attachment(&self, attachment: &mut Attachment, message: &Message) {
+        // When encoding videos, alert the user that the time estimate may be inaccurate
+        let will_encode = matches!(attachment.mime_type(), MediaType::Video(_))
+            && matches!(
+                self.config.options.attachment_manager.mode,
+                AttachmentManagerMode::Full
+            );
+
+        if will_encode {
+            self.pb
+                .set_busy_style("Encoding video, estimates paused...".to_string());
+        }
+
+        // Copy the file, if requested
+        self.config
+            .options
+            .attachment_manager
+            .handle_attachment(message, attachment, self.config);
+
+        if will_encode {
+            self.pb.set_default_style();
+        }
+    }
+
+    /// Get the participant that sent a message, if it was not the database owner
+    fn participant_id(&self, message: &Message) -> Option<i32> {
+        message
+            .handle_id
+            .filter(|handle_id| !message.is_from_me() && *handle_id != 0)
+            .filter(|handle_id| self.config.participants.contains_key(handle_id))
+    }
+
+    /// Format a timestamp in local time, leaving the column empty if it was never set
+    fn format_date(&self, date: i64) -> Option<String> {
+        if date == 0 {
+            return None;
+        }
+        get_local_time(&date, &self.config.offset)
+            .ok()
+            .map(|date| date.to_rfc3339())
+    }
+}
+
+/// Get the text covered by a text part, if its ranges are valid byte offsets into the message body
+fn part_text<'b>(message: &'b Message, ranges: &[TextAttributes]) -> Option<&'b str> {
+    let start = ranges.iter().map(|range| range.start).min()?;
+    let end = ranges.iter().map(|range| range.end).max()?;
+    message.text.as_deref()?.get(start..end)
+}
+
+/// Get the name a JSON export uses for a unit enum variant, so both exports share a vocabulary
+fn label<T: Serialize>(value: &T) -> Option<String> {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(String::from))
+}
+
+/// Wrap an error from the archive connection
+fn query_error(why: rusqlite::Error) -> RuntimeError {
+    RuntimeError::DatabaseError(TableError::QueryError(why))
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all},
+    };
+
+    use imessage_database::{
+        message_types::edited::{EditStatus, EditedEvent, EditedMessage, EditedMessagePart},
+        tables::messages::models::{BubbleComponent, TextAttributes},
+    };
+
+    use crate::{
+        Config, Options, SQLite,
+        app::export_type::ExportType,
+        exporters::sqlite::{ARCHIVE_VERSION, part_text},
+    };
+
+    /// Options that write the archive to a directory owned by a single test
+    fn fake_options(test: &str) -> Options {
+        let mut options = Options::fake_options(ExportType::Sqlite);
+        options.export_path = temp_dir().join(format!("imessage-exporter-sqlite-{test}"));
+        let _ = remove_dir_all(&options.export_path);
+        create_dir_all(&options.export_path).unwrap();
+        options
+    }
+
+    /// Count the rows returned by a query against the archive
+    fn count(exporter: &SQLite, query: &str) -> i64 {
+        exporter
+            .archive
+            .query_row(query, [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn can_create() {
+        let config = Config::fake_app(fake_options("create"));
+        let exporter = SQLite::new(&config).unwrap();
+
+        let version: i32 = exporter
+            .archive
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, ARCHIVE_VERSION);
+        assert_eq!(
+            count(
+                &exporter,
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'messages_fts'"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn can_replace_existing_archive() {
+        let config = Config::fake_app(fake_options("replace"));
+        let exporter = SQLite::new(&config).unwrap();
+        exporter
+            .archive
+            .execute("INSERT INTO metadata (key, value) VALUES ('a', 'b')", [])
+            .unwrap();
+        drop(exporter);
+
+        let exporter = SQLite::new(&config).unwrap();
+        assert_eq!(count(&exporter, "SELECT COUNT(*) FROM metadata"), 0);
+    }
+
+    #[test]
+    fn can_write_message() {
+        let config = Config::fake_app(fake_options("message"));
+        let exporter = SQLite::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.rowid = 1;
+        message.guid = "fake_guid".to_string();
+        // May 17, 2022  8:29:42 PM
+        message.date = 674526582885055488;
+        message.text = Some("Hello world".to_string());
+        message.is_from_me = true;
+        message.expressive_send_style_id =
+            Some("com.apple.MobileSMS.expressivesend.invisibleink".to_string());
+        message.components = vec![BubbleComponent::Text(vec![TextAttributes::new(
+            0,
+            11,
+            vec![],
+        )])];
+
+        exporter.write_message(&message).unwrap();
+
+        let (sender, is_from_me, participant): (String, bool, Option<i32>) = exporter
+            .archive
+            .query_row(
+                "SELECT sender, is_from_me, participant_id FROM messages WHERE guid = 'fake_guid'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(sender, "Me");
+        assert!(is_from_me);
+        assert_eq!(participant, None);
+
+        let (variant, expressive): (String, String) = exporter
+            .archive
+            .query_row(
+                "SELECT variant, expressive FROM messages WHERE guid = 'fake_guid'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(variant, "normal");
+        assert_eq!(expressive, "invisible_ink");
+
+        let part: String = exporter
+            .archive
+            .query_row(
+                "SELECT text FROM message_parts WHERE message_id = 1 AND part = 0",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(part, "Hello world");
+    }
+
+    #[test]
+    fn can_search_messages() {
+        let config = Config::fake_app(fake_options("search"));
+        let exporter = SQLite::new(&config).unwrap();
+
+        for (rowid, text) in [(1, "Dinner at eight?"), (2, "Running late")] {
+            let mut message = Config::fake_message();
+            message.rowid = rowid;
+            message.guid = format!("guid_{rowid}");
+            message.text = Some(text.to_string());
+            exporter.write_message(&message).unwrap();
+        }
+
+        let rowid: i32 = exporter
+            .archive
+            .query_row(
+                "SELECT rowid FROM messages_fts WHERE messages_fts MATCH 'dinner'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(rowid, 1);
+    }
+
+    #[test]
+    fn can_write_reactions() {
+        let mut config = Config::fake_app(fake_options("reactions"));
+
+        let mut tapback = Config::fake_message();
+        tapback.rowid = 2;
+        tapback.guid = "tapback_guid".to_string();
+        tapback.associated_message_type = Some(2000);
+        tapback.associated_message_guid = Some("p:0/fake_guid".to_string());
+        tapback.is_from_me = true;
+        config
+            .tapbacks
+            .insert("fake_guid".to_string(), HashMap::from([(0, vec![tapback])]));
+
+        let exporter = SQLite::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.rowid = 1;
+        message.guid = "fake_guid".to_string();
+        exporter.write_message(&message).unwrap();
+
+        let (kind, action, message_id): (String, String, i32) = exporter
+            .archive
+            .query_row(
+                "SELECT kind, action, message_id FROM reactions WHERE guid = 'tapback_guid'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(kind, "loved");
+        assert_eq!(action, "added");
+        assert_eq!(message_id, 1);
+    }
+
+    #[test]
+    fn can_write_edits() {
+        let config = Config::fake_app(fake_options("edits"));
+        let exporter = SQLite::new(&config).unwrap();
+
+        let mut message = Config::fake_message();
+        message.rowid = 1;
+        message.guid = "fake_guid".to_string();
+        // May 17, 2022  9:30:31 PM
+        message.date_edited = 674530231992568192;
+        message.edited_parts = Some(EditedMessage {
+            parts: vec![
+                EditedMessagePart {
+                    status: EditStatus::Edited,
+                    edit_history: vec![
+                        EditedEvent {
+                            date: 674526582885055488,
+                            text: Some("Hello".to_string()),
+                            components: vec![],
+                            guid: None,
+                        },
+                        EditedEvent {
+                            date: 674526592885055488,
+                            text: Some("Hello world".to_string()),
+                            components: vec![],
+                            guid: None,
+                        },
+                    ],
+                },
+                EditedMessagePart {
+                    status: EditStatus::Original,
+                    edit_history: vec![],
+                },
+                EditedMessagePart {
+                    status: EditStatus::Unsent,
+                    edit_history: vec![],
+                },
+            ],
+        });
+
+        exporter.write_message(&message).unwrap();
+
+        assert_eq!(
+            count(
+                &exporter,
+                "SELECT COUNT(*) FROM edits WHERE part = 0 AND status = 'edited'"
+            ),
+            2
+        );
+        assert_eq!(
+            count(&exporter, "SELECT COUNT(*) FROM edits WHERE part = 1"),
+            0
+        );
+        assert_eq!(
+            count(
+                &exporter,
+                "SELECT COUNT(*) FROM edits WHERE part = 2 AND status = 'unsent' AND text IS NULL"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn can_export_test_db() {
+        let config = Config::fake_app(fake_options("export"));
+        let mut exporter = SQLite::new(&config).unwrap();
+
+        exporter.iter_messages().unwrap();
+
+        assert_eq!(count(&exporter, "SELECT COUNT(*) FROM messages"), 2);
+        assert_eq!(
+            count(
+                &exporter,
+                "SELECT COUNT(*) FROM metadata WHERE key = 'archive_version' AND value = '1'"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn can_get_part_text() {
+        let mut message = Config::fake_message();
+        message.text = Some("Hello world".to_string());
+
+        let ranges = vec![
+            TextAttributes::new(0, 6, vec![]),
+            TextAttributes::new(6, 11, vec![]),
+        ];
+        assert_eq!(part_text(&message, &ranges), Some("Hello world"));
+
+        let invalid = vec![TextAttributes::new(6, 20, vec![])];
+        assert_eq!(part_text(&message, &invalid), None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fs::read_to_string, io::ErrorKind, path::Path};
+
+use crate::app::error::RuntimeError;
+
+// MARK: Templates
+/// Template that wraps every HTML file, split around `{{content}}`
+pub const TEMPLATE_PAGE: &str = "page.html";
+/// Template that wraps every top-level message
+pub const TEMPLATE_MESSAGE: &str = "message.html";
+/// Template that wraps every app balloon
+pub const TEMPLATE_BALLOON: &str = "balloon.html";
+/// Template that wraps every tapback
+pub const TEMPLATE_TAPBACK: &str = "tapback.html";
+/// Stylesheet applied after the built-in styles
+pub const TEMPLATE_STYLE: &str = "style.css";
+
+/// Placeholder in the page template where exported messages are written
+pub const CONTENT: &str = "content";
+/// Stands in for the content while the page template is split, since it cannot appear in a template file
+const CONTENT_MARKER: &str = "\u{0}content\u{0}";
+
+/// User-supplied templates that customize HTML exports
+///
+/// Every template is optional; anything missing from the template directory uses the built-in markup.
+#[derive(Debug, Default)]
+pub struct Templates {
+    /// Wraps every page; supports `{{head}}` and `{{content}}`
+    pub page: Option<String>,
+    /// Wraps every top-level message; supports `{{message}}`, `{{guid}}`, `{{sender}}`, `{{date}}`, `{{service}}`, and `{{direction}}`
+    pub message: Option<String>,
+    /// Wraps every app balloon; supports `{{balloon}}` and `{{bundle_id}}`
+    pub balloon: Option<String>,
+    /// Wraps every tapback; supports `{{tapback}}` and `{{sender}}`
+    pub tapback: Option<String>,
+    /// CSS that overrides the built-in styles
+    pub style: Option<String>,
+}
+
+impl Templates {
+    /// Read the templates from a directory
+    pub fn load(dir: &Path) -> Result<Self, RuntimeError> {
+        let templates = Templates {
+            page: read_template(&dir.join(TEMPLATE_PAGE))?,
+            message: read_template(&dir.join(TEMPLATE_MESSAGE))?,
+            balloon: read_template(&dir.join(TEMPLATE_BALLOON))?,
+            tapback: read_template(&dir.join(TEMPLATE_TAPBACK))?,
+            style: read_template(&dir.join(TEMPLATE_STYLE))?,
+        };
+
+        if templates.page.is_some() && templates.page_layout("").is_none() {
+            return Err(RuntimeError::InvalidOptions(format!(
+                "{TEMPLATE_PAGE} must contain {{{{{CONTENT}}}}}"
+            )));
+        }
+
+        Ok(templates)
+    }
+
+    /// Split the page template into the markup before and after the exported messages
+    pub fn page_layout(&self, head: &str) -> Option<(String, String)> {
+        let page = render(
+            self.page.as_ref()?,
+            &[("head", head), (CONTENT, CONTENT_MARKER)],
+        );
+        page.split_once(CONTENT_MARKER)
+            .map(|(header, footer)| (header.to_string(), footer.to_string()))
+    }
+}
+
+/// Read a template file, if it exists
+fn read_template(path: &Path) -> Result<Option<String>, RuntimeError> {
+    match read_to_string(path) {
+        Ok(template) => Ok(Some(template)),
+        Err(why) if why.kind() == ErrorKind::NotFound => Ok(None),
+        Err(why) => Err(RuntimeError::DiskError(why)),
+    }
+}
+
+/// Replace every `{{name}}` placeholder in a template with its value
+///
+/// Unknown placeholders are left as-is, and values are never searched for placeholders themselves.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out_s = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out_s.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match values.iter().find(|(key, _)| *key == name) {
+                    Some((_, value)) => out_s.push_str(value),
+                    None => out_s.push_str(&rest[start..start + end + 4]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out_s.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out_s.push_str(rest);
+    out_s
+}
+
+// MARK: Tests
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all, write},
+    };
+
+    use crate::exporters::templates::{
+        TEMPLATE_MESSAGE, TEMPLATE_PAGE, TEMPLATE_STYLE, Templates, render,
+    };
+
+    #[test]
+    fn can_render() {
+        let actual = render(
+            "<div class=\"{{ direction }}\">{{message}}</div>",
+            &[("message", "Hello world"), ("direction", "sent")],
+        );
+        assert_eq!(actual, "<div class=\"sent\">Hello world</div>");
+    }
+
+    #[test]
+    fn can_render_unknown_placeholder() {
+        let actual = render("{{message}} {{unknown}}", &[("message", "Hello")]);
+        assert_eq!(actual, "Hello {{unknown}}");
+    }
+
+    #[test]
+    fn can_render_without_reparsing_values() {
+        let actual = render(
+            "{{message}}{{sender}}",
+            &[("message", "{{sender}}"), ("sender", "Me")],
+        );
+        assert_eq!(actual, "{{sender}}Me");
+    }
+
+    #[test]
+    fn can_render_unclosed_placeholder() {
+        let actual = render("Hello {{message", &[("message", "world")]);
+        assert_eq!(actual, "Hello {{message");
+    }
+
+    #[test]
+    fn can_load_templates() {
+        let dir = temp_dir().join("imessage-exporter-templates");
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        write(dir.join(TEMPLATE_PAGE), "<main>{{content}}</main>").unwrap();
+        write(dir.join(TEMPLATE_MESSAGE), "<article>{{message}}</article>").unwrap();
+        write(dir.join(TEMPLATE_STYLE), "body { color: red; }").unwrap();
+
+        let templates = Templates::load(&dir).unwrap();
+
+        assert_eq!(templates.page.as_deref(), Some("<main>{{content}}</main>"));
+        assert_eq!(
+            templates.message.as_deref(),
+            Some("<article>{{message}}</article>")
+        );
+        assert_eq!(templates.style.as_deref(), Some("body { color: red; }"));
+        assert!(templates.balloon.is_none());
+        assert!(templates.tapback.is_none());
+    }
+
+    #[test]
+    fn can_split_page_layout() {
+        let templates = Templates {
+            page: Some("<html><head>{{head}}</head><body>{{ content }}</body></html>".to_string()),
+            ..Default::default()
+        };
+
+        let (header, footer) = templates.page_layout("<style></style>").unwrap();
+
+        assert_eq!(header, "<html><head><style></style></head><body>");
+        assert_eq!(footer, "</body></html>");
+    }
+
+    #[test]
+    fn cant_load_page_without_content() {
+        let dir = temp_dir().join("imessage-exporter-templates-invalid");
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        write(dir.join(TEMPLATE_PAGE), "<main></main>").unwrap();
+
+        assert!(Templates::load(&dir).is_err());
+    }
+}
@@ -116,6 +116,12 @@ impl<'a> Exporter<'a> for TXT<'a> {
             // Generate the text of the message
             let _ = msg.generate_text(self.config.db());
 
+            // Skip messages that do not match the filters that require decoded text
+            if !msg.matches_filters(self.config.db(), &self.config.options.query_context) {
+                current_message += 1;
+                continue;
+            }
+
             // Render the announcement in-line
             if msg.is_announcement() {
                 let announcement = self.format_announcement(&msg);
@@ -3,16 +3,41 @@
 mod app;
 mod exporters;
 
-pub use exporters::{exporter::Exporter, html::HTML, txt::TXT, json::JSON};
+pub use exporters::{
+    csv::CSV, exporter::Exporter, html::HTML, json::JSON, markdown::Markdown, mbox::Mbox,
+    sms_backup::SmsBackup, sqlite::SQLite, txt::TXT,
+};
 
 use app::{
-    options::{Options, from_command_line},
+    export_type::ExportType,
+    options::{OPTION_EXPORT_TYPE, OPTION_PRINT_SCHEMA, Options, from_command_line},
     runtime::Config,
 };
+use exporters::json_schema::schema;
 
 fn main() {
     // Get args from command line
     let args = from_command_line();
+
+    // The schema does not depend on the database, so print it before validating anything else:
+    // building `Options` rejects an export directory that already holds files of the same type,
+    // so `-f json --print-schema` would otherwise fail after any previous JSON export
+    if args.get_flag(OPTION_PRINT_SCHEMA) {
+        let export_type: Option<&String> = args.get_one(OPTION_EXPORT_TYPE);
+        match export_type.map(|export_type| ExportType::from_cli(export_type)) {
+            None => print_schema(&ExportType::Json),
+            Some(Some(export_type @ (ExportType::Json | ExportType::NdJson))) => {
+                print_schema(&export_type);
+            }
+            Some(_) => eprintln!(
+                "Invalid command line options: --{OPTION_PRINT_SCHEMA} requires --{OPTION_EXPORT_TYPE} {} or {}",
+                ExportType::Json,
+                ExportType::NdJson
+            ),
+        }
+        return;
+    }
+
     // Create application options
     let options = Options::from_args(&args);
 
@@ -38,3 +63,11 @@ fn main() {
         }
     }
 }
+
+/// Write the JSON Schema for an export type to stdout
+fn print_schema(export_type: &ExportType) {
+    match serde_json::to_string_pretty(&schema(export_type)) {
+        Ok(schema) => println!("{schema}"),
+        Err(why) => eprintln!("Unable to generate schema: {why}"),
+    }
+}